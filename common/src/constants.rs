@@ -4,6 +4,14 @@
 pub const SPARK_DEFAULTS_CONF: &str = "spark-defaults.conf";
 /// Name of the environment variables file where spark nodes look for configuration data
 pub const SPARK_ENV_SH: &str = "spark-env.sh";
+/// Name of the log4j config file of the Spark daemons
+pub const LOG4J_PROPERTIES: &str = "log4j.properties";
+/// Name of the Hadoop core config file (e.g. for S3 access)
+pub const CORE_SITE_XML: &str = "core-site.xml";
+/// Name of the HDFS config file
+pub const HDFS_SITE_XML: &str = "hdfs-site.xml";
+/// Name of the Hive config file (e.g. for the metastore)
+pub const HIVE_SITE_XML: &str = "hive-site.xml";
 /// Basic start up parameter: We do not want to let processes work in background,
 /// so SPARK_NO_DAEMONIZE = true must be set at all times (before starting the process).
 pub const SPARK_NO_DAEMONIZE: &str = "SPARK_NO_DAEMONIZE";
@@ -22,21 +30,37 @@ pub const SPARK_DEFAULTS_AUTHENTICATE: &str = "spark.authenticate";
 /// Common parameter: Must be set on all nodes (Master, Worker, HistoryServer) to activate
 /// a secret password which needs to be supplied via spark-submit.
 pub const SPARK_DEFAULTS_AUTHENTICATE_SECRET: &str = "spark.authenticate.secret";
+/// Environment variable Spark reads the authentication secret from. Used instead of
+/// spark.authenticate.secret to keep the secret off the spark-submit command line.
+pub const SPARK_ENV_AUTH_SECRET: &str = "_SPARK_AUTH_SECRET";
+/// Submit parameter: The address the executors use to connect to a driver in client mode.
+pub const SPARK_DEFAULTS_DRIVER_HOST: &str = "spark.driver.host";
+/// Environment variable of the submitter pod that holds the pod IP (kubernetes runtime).
+pub const SPARK_ENV_DRIVER_HOST: &str = "SPARK_DRIVER_HOST";
 /// Common parameter: Must be set to '0' on all nodes (Master, Worker, HistoryServer) to disable
 /// automatic port search. Otherwise the nodes will increase their given port if it's already in use.
 pub const SPARK_DEFAULTS_PORT_MAX_RETRIES: &str = "spark.port.maxRetries";
 /// Master specific parameter: Set the master port in environment variables.
 pub const SPARK_DEFAULTS_MASTER_PORT: &str = "spark.master.port";
+/// Master specific parameter: Set the master web ui port in spark-defaults.conf.
+pub const SPARK_DEFAULTS_MASTER_WEBUI_PORT: &str = "spark.master.ui.port";
+/// Master specific parameter: Set the host the master binds to and advertises in environment
+/// variables.
+pub const SPARK_ENV_MASTER_HOST: &str = "SPARK_MASTER_HOST";
 /// Master specific parameter: Set the master port in environment variables.
 pub const SPARK_ENV_MASTER_PORT: &str = "SPARK_MASTER_PORT";
 /// Master specific parameter: Set the master web ui port in environment variables.
 pub const SPARK_ENV_MASTER_WEBUI_PORT: &str = "SPARK_MASTER_WEBUI_PORT";
+/// Worker specific parameter: Decommission the worker and its executors on SIGPWR (Spark 3.1).
+pub const SPARK_DEFAULTS_DECOMMISSION_ENABLED: &str = "spark.decommission.enabled";
 /// Worker specific parameter: Set the worker cores in environment variables.
 pub const SPARK_ENV_WORKER_CORES: &str = "SPARK_WORKER_CORES";
 /// Worker specific parameter: Set the worker memory (500m, 2g) in environment variables.
 pub const SPARK_ENV_WORKER_MEMORY: &str = "SPARK_WORKER_MEMORY";
 /// Worker specific parameter: Set the worker port in environment variables.
 pub const SPARK_ENV_WORKER_PORT: &str = "SPARK_WORKER_PORT";
+/// Worker specific parameter: Set the worker web ui port in spark-defaults.conf.
+pub const SPARK_DEFAULTS_WORKER_WEBUI_PORT: &str = "spark.worker.ui.port";
 /// Worker specific parameter: Set the worker web ui port in environment variables.
 pub const SPARK_ENV_WORKER_WEBUI_PORT: &str = "SPARK_WORKER_WEBUI_PORT";
 /// HistoryServer specific parameter: Set directory where to search for logs. Normally should
@@ -47,3 +71,17 @@ pub const SPARK_DEFAULTS_HISTORY_FS_LOG_DIRECTORY: &str = "spark.history.fs.logD
 pub const SPARK_DEFAULTS_HISTORY_STORE_PATH: &str = "spark.history.store.path";
 /// HistoryServer specific parameter: Set HistoryServer web ui port to access the common logs.
 pub const SPARK_DEFAULTS_HISTORY_WEBUI_PORT: &str = "spark.history.ui.port";
+
+/// Container image repository of the Spark images (kubernetes runtime). The image tag is the
+/// Spark version.
+pub const SPARK_IMAGE_REPOSITORY: &str = "docker.stackable.tech/stackable/spark";
+/// Spark installation directory within the Spark images (kubernetes runtime).
+pub const SPARK_IMAGE_HOME: &str = "/stackable/spark";
+/// Directory the config map is mounted to within the Spark images (kubernetes runtime).
+pub const SPARK_IMAGE_CONFIG_DIR: &str = "/stackable/config";
+/// Default SPARK_WORKER_DIR within the Spark images (kubernetes runtime).
+pub const SPARK_IMAGE_WORK_DIR: &str = "/stackable/spark/work";
+/// Default SPARK_LOG_DIR within the Spark images (kubernetes runtime).
+pub const SPARK_IMAGE_LOG_DIR: &str = "/stackable/spark/logs";
+/// Temporary directory (e.g. spark.local.dir) within the Spark images (kubernetes runtime).
+pub const SPARK_IMAGE_TMP_DIR: &str = "/tmp";
@@ -0,0 +1,340 @@
+//! This module provides the SparkApplication CRD which submits a job to a SparkCluster.
+use crate::{ConfigOption, Runtime, SparkVersion};
+use kube::{CustomResource, Resource};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use stackable_operator::Crd;
+use stackable_spark_common::constants::{
+    SPARK_DEFAULTS_AUTHENTICATE, SPARK_DEFAULTS_DRIVER_HOST, SPARK_ENV_DRIVER_HOST,
+    SPARK_IMAGE_HOME,
+};
+
+#[derive(Clone, CustomResource, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[kube(
+    group = "spark.stackable.tech",
+    version = "v1",
+    kind = "SparkApplication",
+    shortname = "sa",
+    namespaced
+)]
+#[kube(status = "SparkApplicationStatus")]
+#[kube(
+    printcolumn = r#"{"name":"Cluster", "type":"string", "description":"The SparkCluster the application is submitted to", "jsonPath":".spec.sparkCluster"}"#,
+    printcolumn = r#"{"name":"State", "type":"string", "description":"The state of the application", "jsonPath":".status.state"}"#,
+    printcolumn = r#"{"name":"App ID", "type":"string", "description":"The application id assigned by the master", "jsonPath":".status.appId"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#
+)]
+#[serde(rename_all = "camelCase")]
+pub struct SparkApplicationSpec {
+    /// Name of the SparkCluster (in the same namespace) to submit the application to
+    pub spark_cluster: String,
+    /// The jar or python file that contains the application
+    pub main_application_file: String,
+    /// The main class of a Java or Scala application
+    pub main_class: Option<String>,
+    /// Arguments passed to the main class or file
+    pub args: Option<Vec<String>>,
+    /// Additional jars added to the driver and executor classpath
+    pub jars: Option<Vec<String>>,
+    /// Spark properties passed via "--conf" (e.g. spark.executor.extraJavaOptions)
+    pub spark_conf: Option<Vec<ConfigOption>>,
+    /// Run the driver in the submitter pod (client) or on a worker (cluster). Default: client
+    pub deploy_mode: Option<DeployMode>,
+    pub driver: Option<DriverResources>,
+    pub executor: Option<ExecutorResources>,
+}
+
+#[derive(
+    Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize, strum_macros::Display,
+)]
+pub enum DeployMode {
+    #[serde(rename = "client")]
+    #[strum(serialize = "client")]
+    Client,
+    #[serde(rename = "cluster")]
+    #[strum(serialize = "cluster")]
+    Cluster,
+}
+
+impl Default for DeployMode {
+    fn default() -> Self {
+        DeployMode::Client
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriverResources {
+    pub cores: Option<usize>,
+    /// Memory in JVM format (e.g. "1g")
+    pub memory: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutorResources {
+    /// Cores per executor
+    pub cores: Option<usize>,
+    /// Memory per executor in JVM format (e.g. "1g")
+    pub memory: Option<String>,
+    /// Maximum cores of all executors of the application
+    pub total_cores: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SparkApplicationStatus {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<ApplicationState>,
+    /// The application id assigned by the master
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+    /// Name of the pod that runs spark-submit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submitter_pod: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submitted_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Consecutive polls in which the alive master did not list the application after the
+    /// submitter pod succeeded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unlisted_polls: Option<u32>,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Deserialize,
+    Eq,
+    JsonSchema,
+    PartialEq,
+    Serialize,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+pub enum ApplicationState {
+    /// Waiting for the cluster or the submitter pod
+    Pending,
+    /// The submitter pod was created
+    Submitted,
+    /// The master reports the application as running
+    Running,
+    /// The master reports the application as finished
+    Finished,
+    /// The submission failed or the master reports the application as failed or killed
+    Failed,
+}
+
+impl ApplicationState {
+    /// Finished and failed applications are not processed anymore
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, ApplicationState::Finished | ApplicationState::Failed)
+    }
+}
+
+impl Crd for SparkApplication {
+    const RESOURCE_NAME: &'static str = "sparkapplications.spark.stackable.tech";
+    const CRD_DEFINITION: &'static str = include_str!("../../deploy/crd/sparkapplication.crd.yaml");
+}
+
+impl SparkApplication {
+    /// Returns the configured deploy mode or the default (client)
+    pub fn get_deploy_mode(&self) -> DeployMode {
+        self.spec.deploy_mode.clone().unwrap_or_default()
+    }
+
+    /// Returns the current state or Pending if no state is set yet
+    pub fn get_state(&self) -> ApplicationState {
+        self.status
+            .as_ref()
+            .and_then(|status| status.state.clone())
+            .unwrap_or(ApplicationState::Pending)
+    }
+
+    /// Build the spark-submit command for this application. The authentication secret is
+    /// not part of the command, it is passed via the SPARK_ENV_AUTH_SECRET env var. For the
+    /// kubernetes runtime in client mode the driver is reachable via the pod IP, which the
+    /// kubelet expands from the SPARK_ENV_DRIVER_HOST env var.
+    ///
+    /// # Arguments
+    /// * `master_urls` - Slice of all master urls in format <node_name>:<port>
+    /// * `runtime` - The runtime of the cluster (decides the spark-submit location)
+    /// * `version` - The version of the cluster
+    /// * `authenticate` - Whether the cluster requires authentication
+    ///
+    pub fn build_submit_command(
+        &self,
+        master_urls: &[String],
+        runtime: &Runtime,
+        version: &SparkVersion,
+        authenticate: bool,
+    ) -> Vec<String> {
+        let spark_submit = match runtime {
+            Runtime::StackableAgent => {
+                format!("spark-{}-bin-hadoop2.7/bin/spark-submit", version)
+            }
+            Runtime::Kubernetes => format!("{}/bin/spark-submit", SPARK_IMAGE_HOME),
+        };
+
+        let mut command = vec![
+            spark_submit,
+            "--master".to_string(),
+            format!("spark://{}", master_urls.join(",")),
+            "--deploy-mode".to_string(),
+            self.get_deploy_mode().to_string(),
+            "--name".to_string(),
+            self.get_app_name(),
+        ];
+
+        if let Some(main_class) = &self.spec.main_class {
+            command.push("--class".to_string());
+            command.push(main_class.clone());
+        }
+
+        if let Some(jars) = self.spec.jars.as_ref().filter(|jars| !jars.is_empty()) {
+            command.push("--jars".to_string());
+            command.push(jars.join(","));
+        }
+
+        let mut conf = vec![];
+        if authenticate {
+            conf.push((SPARK_DEFAULTS_AUTHENTICATE.to_string(), "true".to_string()));
+        }
+        if runtime == &Runtime::Kubernetes && self.get_deploy_mode() == DeployMode::Client {
+            conf.push((
+                SPARK_DEFAULTS_DRIVER_HOST.to_string(),
+                format!("$({})", SPARK_ENV_DRIVER_HOST),
+            ));
+        }
+        if let Some(spark_conf) = &self.spec.spark_conf {
+            for option in spark_conf {
+                conf.push((option.name.clone(), option.value.clone()));
+            }
+        }
+        for (name, value) in conf {
+            command.push("--conf".to_string());
+            command.push(format!("{}={}", name, value));
+        }
+
+        if let Some(driver) = &self.spec.driver {
+            push_option(&mut command, "--driver-cores", &driver.cores);
+            push_option(&mut command, "--driver-memory", &driver.memory);
+        }
+
+        if let Some(executor) = &self.spec.executor {
+            push_option(&mut command, "--executor-cores", &executor.cores);
+            push_option(&mut command, "--executor-memory", &executor.memory);
+            push_option(
+                &mut command,
+                "--total-executor-cores",
+                &executor.total_cores,
+            );
+        }
+
+        command.push(self.spec.main_application_file.clone());
+        command.extend(self.spec.args.clone().unwrap_or_default());
+
+        command
+    }
+
+    /// The name the application is registered with at the master. The namespace is added
+    /// to keep names unique over namespaces.
+    pub fn get_app_name(&self) -> String {
+        match self.namespace() {
+            Some(namespace) => format!("{}.{}", namespace, self.name()),
+            None => self.name(),
+        }
+    }
+}
+
+/// Push a command line option and its value if the value is set.
+fn push_option<T: ToString>(command: &mut Vec<String>, option: &str, value: &Option<T>) {
+    if let Some(value) = value {
+        command.push(option.to_string());
+        command.push(value.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APPLICATION: &str = r#"
+apiVersion: spark.stackable.tech/v1
+kind: SparkApplication
+metadata:
+  name: pi
+  namespace: default
+spec:
+  sparkCluster: spark-cluster
+  mainApplicationFile: local:///stackable/spark/examples/jars/spark-examples.jar
+  mainClass: org.apache.spark.examples.SparkPi
+  args:
+    - "100"
+  sparkConf:
+    - name: spark.eventLog.enabled
+      value: "true"
+  deployMode: cluster
+  executor:
+    cores: 1
+    memory: "1g"
+"#;
+
+    #[test]
+    fn test_build_submit_command() {
+        let mut application: SparkApplication = serde_yaml::from_str(APPLICATION).unwrap();
+        let master_urls = [
+            "master_node_1:7077".to_string(),
+            "master_node_2:7077".to_string(),
+        ];
+
+        let command = application.build_submit_command(
+            &master_urls,
+            &Runtime::Kubernetes,
+            &SparkVersion::v3_0_1,
+            true,
+        );
+
+        assert_eq!(command[0], format!("{}/bin/spark-submit", SPARK_IMAGE_HOME));
+        let command = command.join(" ");
+        assert!(command.contains("--master spark://master_node_1:7077,master_node_2:7077"));
+        assert!(command.contains("--deploy-mode cluster"));
+        assert!(command.contains("--name default.pi"));
+        assert!(command.contains("--class org.apache.spark.examples.SparkPi"));
+        assert!(command.contains("--conf spark.authenticate=true"));
+        assert!(!command.contains("spark.authenticate.secret"));
+        assert!(!command.contains("spark.driver.host"));
+        assert!(command.contains("--conf spark.eventLog.enabled=true"));
+        assert!(command.contains("--executor-cores 1 --executor-memory 1g"));
+        assert!(!command.contains("--driver-cores"));
+        assert!(command.ends_with("spark-examples.jar 100"));
+
+        // the driver runs in the submitter pod and is reachable via the pod IP
+        application.spec.deploy_mode = Some(DeployMode::Client);
+        let command = application
+            .build_submit_command(
+                &master_urls,
+                &Runtime::Kubernetes,
+                &SparkVersion::v3_0_1,
+                false,
+            )
+            .join(" ");
+        assert!(command.contains("--conf spark.driver.host=$(SPARK_DRIVER_HOST)"));
+        assert!(!command.contains("spark.authenticate"));
+    }
+
+    #[test]
+    fn test_get_state() {
+        let mut application: SparkApplication = serde_yaml::from_str(APPLICATION).unwrap();
+        assert_eq!(application.get_state(), ApplicationState::Pending);
+
+        application.status = Some(SparkApplicationStatus {
+            state: Some(ApplicationState::Failed),
+            ..SparkApplicationStatus::default()
+        });
+        assert!(application.get_state().is_terminal());
+    }
+}
@@ -4,6 +4,16 @@ use serde::{Deserialize, Serialize};
 use stackable_operator::command_controller::Command;
 use stackable_operator::Crd;
 
+/// Access to the settings and the status that decide when a finished command is deleted.
+pub trait CommandRetention {
+    /// Seconds after which the finished command is deleted (None: only the history limit
+    /// of the cluster applies)
+    fn get_ttl_seconds_after_finished(&self) -> Option<u64>;
+
+    /// The status of the command
+    fn get_status(&self) -> Option<&CommandStatus>;
+}
+
 #[derive(Clone, CustomResource, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
 #[kube(
     group = "command.spark.stackable.tech",
@@ -15,6 +25,12 @@ use stackable_operator::Crd;
 #[serde(rename_all = "camelCase")]
 pub struct RestartCommandSpec {
     pub name: String,
+    /// Seconds after which the running command is retried or fails
+    pub timeout_seconds: Option<u64>,
+    /// Number of retries after a timeout before the command fails. Default: 0
+    pub retries: Option<u16>,
+    /// Seconds after which the finished command is deleted
+    pub ttl_seconds_after_finished: Option<u64>,
 }
 
 impl Crd for Restart {
@@ -28,6 +44,16 @@ impl Command for Restart {
     }
 }
 
+impl CommandRetention for Restart {
+    fn get_ttl_seconds_after_finished(&self) -> Option<u64> {
+        self.spec.ttl_seconds_after_finished
+    }
+
+    fn get_status(&self) -> Option<&CommandStatus> {
+        self.status.as_ref()
+    }
+}
+
 #[derive(Clone, CustomResource, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
 #[kube(
     group = "command.spark.stackable.tech",
@@ -39,6 +65,12 @@ impl Command for Restart {
 #[serde(rename_all = "camelCase")]
 pub struct StartCommandSpec {
     pub name: String,
+    /// Seconds after which the running command is retried or fails
+    pub timeout_seconds: Option<u64>,
+    /// Number of retries after a timeout before the command fails. Default: 0
+    pub retries: Option<u16>,
+    /// Seconds after which the finished command is deleted
+    pub ttl_seconds_after_finished: Option<u64>,
 }
 
 impl Crd for Start {
@@ -52,6 +84,16 @@ impl Command for Start {
     }
 }
 
+impl CommandRetention for Start {
+    fn get_ttl_seconds_after_finished(&self) -> Option<u64> {
+        self.spec.ttl_seconds_after_finished
+    }
+
+    fn get_status(&self) -> Option<&CommandStatus> {
+        self.status.as_ref()
+    }
+}
+
 #[derive(Clone, CustomResource, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
 #[kube(
     group = "command.spark.stackable.tech",
@@ -63,6 +105,12 @@ impl Command for Start {
 #[serde(rename_all = "camelCase")]
 pub struct StopCommandSpec {
     pub name: String,
+    /// Seconds after which the running command is retried or fails
+    pub timeout_seconds: Option<u64>,
+    /// Number of retries after a timeout before the command fails. Default: 0
+    pub retries: Option<u16>,
+    /// Seconds after which the finished command is deleted
+    pub ttl_seconds_after_finished: Option<u64>,
 }
 
 impl Crd for Stop {
@@ -76,6 +124,178 @@ impl Command for Stop {
     }
 }
 
+impl CommandRetention for Stop {
+    fn get_ttl_seconds_after_finished(&self) -> Option<u64> {
+        self.spec.ttl_seconds_after_finished
+    }
+
+    fn get_status(&self) -> Option<&CommandStatus> {
+        self.status.as_ref()
+    }
+}
+
+#[derive(Clone, CustomResource, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[kube(
+    group = "command.spark.stackable.tech",
+    version = "v1",
+    kind = "Scale",
+    namespaced
+)]
+#[kube(status = "CommandStatus")]
+#[serde(rename_all = "camelCase")]
+pub struct ScaleCommandSpec {
+    pub name: String,
+    /// The worker role group to scale
+    pub role_group: String,
+    /// The desired number of instances of the role group
+    pub instances: u16,
+    /// Seconds after which the running command is retried or fails
+    pub timeout_seconds: Option<u64>,
+    /// Number of retries after a timeout before the command fails. Default: 0
+    pub retries: Option<u16>,
+    /// Seconds after which the finished command is deleted
+    pub ttl_seconds_after_finished: Option<u64>,
+}
+
+impl Crd for Scale {
+    const RESOURCE_NAME: &'static str = "scales.command.spark.stackable.tech";
+    const CRD_DEFINITION: &'static str = include_str!("../../deploy/crd/scale.command.crd.yaml");
+}
+
+impl Command for Scale {
+    fn get_owner_name(&self) -> String {
+        self.spec.name.clone()
+    }
+}
+
+impl CommandRetention for Scale {
+    fn get_ttl_seconds_after_finished(&self) -> Option<u64> {
+        self.spec.ttl_seconds_after_finished
+    }
+
+    fn get_status(&self) -> Option<&CommandStatus> {
+        self.status.as_ref()
+    }
+}
+
+#[derive(Clone, CustomResource, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[kube(
+    group = "command.spark.stackable.tech",
+    version = "v1",
+    kind = "Pause",
+    namespaced
+)]
+#[kube(status = "CommandStatus")]
+#[serde(rename_all = "camelCase")]
+pub struct PauseCommandSpec {
+    pub name: String,
+    /// Seconds after which the running command is retried or fails
+    pub timeout_seconds: Option<u64>,
+    /// Number of retries after a timeout before the command fails. Default: 0
+    pub retries: Option<u16>,
+    /// Seconds after which the finished command is deleted
+    pub ttl_seconds_after_finished: Option<u64>,
+}
+
+impl Crd for Pause {
+    const RESOURCE_NAME: &'static str = "pauses.command.spark.stackable.tech";
+    const CRD_DEFINITION: &'static str = include_str!("../../deploy/crd/pause.command.crd.yaml");
+}
+
+impl Command for Pause {
+    fn get_owner_name(&self) -> String {
+        self.spec.name.clone()
+    }
+}
+
+impl CommandRetention for Pause {
+    fn get_ttl_seconds_after_finished(&self) -> Option<u64> {
+        self.spec.ttl_seconds_after_finished
+    }
+
+    fn get_status(&self) -> Option<&CommandStatus> {
+        self.status.as_ref()
+    }
+}
+
+#[derive(Clone, CustomResource, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[kube(
+    group = "command.spark.stackable.tech",
+    version = "v1",
+    kind = "Resume",
+    namespaced
+)]
+#[kube(status = "CommandStatus")]
+#[serde(rename_all = "camelCase")]
+pub struct ResumeCommandSpec {
+    pub name: String,
+    /// Seconds after which the running command is retried or fails
+    pub timeout_seconds: Option<u64>,
+    /// Number of retries after a timeout before the command fails. Default: 0
+    pub retries: Option<u16>,
+    /// Seconds after which the finished command is deleted
+    pub ttl_seconds_after_finished: Option<u64>,
+}
+
+impl Crd for Resume {
+    const RESOURCE_NAME: &'static str = "resumes.command.spark.stackable.tech";
+    const CRD_DEFINITION: &'static str = include_str!("../../deploy/crd/resume.command.crd.yaml");
+}
+
+impl Command for Resume {
+    fn get_owner_name(&self) -> String {
+        self.spec.name.clone()
+    }
+}
+
+impl CommandRetention for Resume {
+    fn get_ttl_seconds_after_finished(&self) -> Option<u64> {
+        self.spec.ttl_seconds_after_finished
+    }
+
+    fn get_status(&self) -> Option<&CommandStatus> {
+        self.status.as_ref()
+    }
+}
+
+/// Aborts the command that is currently processed for the cluster. The aborted command
+/// ends in state `Error`.
+#[derive(Clone, CustomResource, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[kube(
+    group = "command.spark.stackable.tech",
+    version = "v1",
+    kind = "Cancel",
+    namespaced
+)]
+#[kube(status = "CommandStatus")]
+#[serde(rename_all = "camelCase")]
+pub struct CancelCommandSpec {
+    pub name: String,
+    /// Seconds after which the finished command is deleted
+    pub ttl_seconds_after_finished: Option<u64>,
+}
+
+impl Crd for Cancel {
+    const RESOURCE_NAME: &'static str = "cancels.command.spark.stackable.tech";
+    const CRD_DEFINITION: &'static str = include_str!("../../deploy/crd/cancel.command.crd.yaml");
+}
+
+impl Command for Cancel {
+    fn get_owner_name(&self) -> String {
+        self.spec.name.clone()
+    }
+}
+
+impl CommandRetention for Cancel {
+    fn get_ttl_seconds_after_finished(&self) -> Option<u64> {
+        self.spec.ttl_seconds_after_finished
+    }
+
+    fn get_status(&self) -> Option<&CommandStatus> {
+        self.status.as_ref()
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CommandStatus {
@@ -85,6 +305,9 @@ pub struct CommandStatus {
     pub finished_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message: Option<CommandStatusMessage>,
+    /// Why the command ended in state `Error`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
 }
 
 #[derive(
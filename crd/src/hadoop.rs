@@ -0,0 +1,135 @@
+//! This module provides the Hadoop and Hive config files (core-site.xml, hdfs-site.xml and
+//! hive-site.xml) that are defined as properties in the custom resource and written to the
+//! Spark config directory (e.g. for S3 access or a Hive metastore).
+use crate::merge_chained;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use stackable_spark_common::constants::{CORE_SITE_XML, HDFS_SITE_XML, HIVE_SITE_XML};
+use std::collections::BTreeMap;
+
+/// Properties of the Hadoop and Hive config files of a role (group)
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HadoopConfig {
+    /// Properties of core-site.xml (e.g. fs.s3a.endpoint)
+    pub core_site: Option<BTreeMap<String, String>>,
+    /// Properties of hdfs-site.xml
+    pub hdfs_site: Option<BTreeMap<String, String>>,
+    /// Properties of hive-site.xml (e.g. hive.metastore.uris)
+    pub hive_site: Option<BTreeMap<String, String>>,
+}
+
+impl HadoopConfig {
+    /// Merge this role group config with the role config. The properties of both levels are
+    /// combined, role group properties override role properties of the same name.
+    ///
+    /// # Arguments
+    /// * `role_config` - The Hadoop config of the role
+    ///
+    pub fn merge(&self, role_config: &Self) -> Self {
+        HadoopConfig {
+            core_site: merge_chained(&role_config.core_site, &self.core_site),
+            hdfs_site: merge_chained(&role_config.hdfs_site, &self.hdfs_site),
+            hive_site: merge_chained(&role_config.hive_site, &self.hive_site),
+        }
+    }
+
+    /// Render all defined files. Returns the file names mapped to their content.
+    pub fn render_files(&self) -> BTreeMap<String, String> {
+        let mut files = BTreeMap::new();
+
+        for (file_name, properties) in &[
+            (CORE_SITE_XML, &self.core_site),
+            (HDFS_SITE_XML, &self.hdfs_site),
+            (HIVE_SITE_XML, &self.hive_site),
+        ] {
+            if let Some(properties) = properties {
+                files.insert(file_name.to_string(), render_site_xml(properties));
+            }
+        }
+
+        files
+    }
+}
+
+/// Render properties in the Hadoop configuration XML format.
+///
+/// # Arguments
+/// * `properties` - Property names mapped to their values
+///
+pub fn render_site_xml(properties: &BTreeMap<String, String>) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\"?>\n<configuration>\n");
+
+    for (name, value) in properties {
+        xml.push_str(&format!(
+            "  <property>\n    <name>{}</name>\n    <value>{}</value>\n  </property>\n",
+            escape_xml(name),
+            escape_xml(value)
+        ));
+    }
+
+    xml.push_str("</configuration>\n");
+    xml
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_site_xml() {
+        let mut properties = BTreeMap::new();
+        properties.insert(
+            "fs.s3a.endpoint".to_string(),
+            "http://minio:9000".to_string(),
+        );
+        properties.insert("javax.jdo.option".to_string(), "a&b<c>".to_string());
+
+        assert_eq!(
+            render_site_xml(&properties),
+            "<?xml version=\"1.0\"?>\n\
+             <configuration>\n  \
+             <property>\n    <name>fs.s3a.endpoint</name>\n    <value>http://minio:9000</value>\n  </property>\n  \
+             <property>\n    <name>javax.jdo.option</name>\n    <value>a&amp;b&lt;c&gt;</value>\n  </property>\n\
+             </configuration>\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_and_render_files() {
+        let mut role_core_site = BTreeMap::new();
+        role_core_site.insert("a".to_string(), "role".to_string());
+        role_core_site.insert("b".to_string(), "role".to_string());
+        let role_config = HadoopConfig {
+            core_site: Some(role_core_site),
+            ..HadoopConfig::default()
+        };
+
+        let mut role_group_core_site = BTreeMap::new();
+        role_group_core_site.insert("b".to_string(), "role_group".to_string());
+        let role_group_config = HadoopConfig {
+            core_site: Some(role_group_core_site),
+            hive_site: Some(BTreeMap::new()),
+            ..HadoopConfig::default()
+        };
+
+        let merged = role_group_config.merge(&role_config);
+        let core_site = merged.core_site.as_ref().unwrap();
+        assert_eq!(core_site.get("a"), Some(&"role".to_string()));
+        assert_eq!(core_site.get("b"), Some(&"role_group".to_string()));
+
+        let files = merged.render_files();
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            vec![CORE_SITE_XML, HIVE_SITE_XML]
+        );
+    }
+}
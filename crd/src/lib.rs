@@ -1,30 +1,62 @@
 //! This module provides all required CRD definitions and additional helper methods.
+pub mod application;
 pub mod commands;
 pub mod error;
+pub mod hadoop;
+pub mod logging;
+pub mod probes;
+pub mod properties;
+pub mod scheduled_application;
+pub mod security;
+pub mod volumes;
 
 pub use crate::error::CrdError;
-pub use commands::{Restart, Start, Stop};
-use k8s_openapi::api::core::v1::Pod;
+pub use application::{ApplicationState, SparkApplication, SparkApplicationStatus};
+pub use commands::{Cancel, CommandRetention, Pause, Restart, Resume, Scale, Start, Stop};
+use hadoop::HadoopConfig;
+use k8s_openapi::api::core::v1::{EnvVar, Pod, Toleration};
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, LabelSelector};
 use kube::CustomResource;
+use logging::LoggingConfig;
+use probes::ProbesConfig;
+use properties::PropertyFile;
+pub use scheduled_application::{
+    ConcurrencyPolicy, ScheduledRun, SparkScheduledApplication, SparkScheduledApplicationStatus,
+};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
 use schemars::JsonSchema;
+use security::SecurityConfig;
 use semver::{SemVerError, Version};
 use serde::{Deserialize, Serialize};
 use stackable_operator::label_selector::schema;
 use stackable_operator::labels::{APP_COMPONENT_LABEL, APP_ROLE_GROUP_LABEL};
 use stackable_operator::Crd;
 use stackable_spark_common::constants::{
-    SPARK_DEFAULTS_AUTHENTICATE_SECRET, SPARK_DEFAULTS_EVENT_LOG_DIR,
-    SPARK_DEFAULTS_HISTORY_FS_LOG_DIRECTORY, SPARK_DEFAULTS_HISTORY_STORE_PATH,
-    SPARK_DEFAULTS_HISTORY_WEBUI_PORT, SPARK_DEFAULTS_MASTER_PORT, SPARK_DEFAULTS_PORT_MAX_RETRIES,
-    SPARK_ENV_MASTER_PORT, SPARK_ENV_MASTER_WEBUI_PORT, SPARK_ENV_WORKER_CORES,
-    SPARK_ENV_WORKER_MEMORY, SPARK_ENV_WORKER_PORT, SPARK_ENV_WORKER_WEBUI_PORT,
+    SPARK_DEFAULTS_AUTHENTICATE_SECRET, SPARK_DEFAULTS_DECOMMISSION_ENABLED,
+    SPARK_DEFAULTS_EVENT_LOG_DIR, SPARK_DEFAULTS_HISTORY_FS_LOG_DIRECTORY,
+    SPARK_DEFAULTS_HISTORY_STORE_PATH, SPARK_DEFAULTS_HISTORY_WEBUI_PORT,
+    SPARK_DEFAULTS_PORT_MAX_RETRIES, SPARK_ENV_MASTER_PORT, SPARK_ENV_MASTER_WEBUI_PORT,
+    SPARK_ENV_WORKER_CORES, SPARK_ENV_WORKER_MEMORY, SPARK_ENV_WORKER_PORT,
+    SPARK_ENV_WORKER_WEBUI_PORT, SPARK_IMAGE_CONFIG_DIR, SPARK_IMAGE_HOME, SPARK_IMAGE_LOG_DIR,
+    SPARK_IMAGE_REPOSITORY,
 };
 use std::collections::{BTreeMap, HashMap};
 use std::hash::Hash;
+use std::iter::FromIterator;
+use std::time::Duration;
 use strum_macros::EnumIter;
+use volumes::{EventLogVolume, ExtraVolume};
 
 const DEFAULT_LOG_DIR: &str = "/tmp";
+const DEFAULT_MASTER_WEB_UI_PORT: &str = "8080";
+const DEFAULT_WORKER_WEB_UI_PORT: &str = "8081";
+const DEFAULT_HISTORY_WEB_UI_PORT: &str = "18080";
+const DEFAULT_TARGET_CORE_UTILIZATION: u8 = 80;
+const DEFAULT_SCALE_UP_COOLDOWN_SECONDS: u64 = 60;
+const DEFAULT_SCALE_DOWN_COOLDOWN_SECONDS: u64 = 300;
+const DEFAULT_DECOMMISSION_TIMEOUT_SECONDS: u64 = 300;
+const DEFAULT_COMMAND_HISTORY_LIMIT: usize = 10;
 
 #[derive(Clone, CustomResource, Debug, Deserialize, JsonSchema, Serialize)]
 #[kube(
@@ -35,60 +67,452 @@ const DEFAULT_LOG_DIR: &str = "/tmp";
     namespaced
 )]
 #[kube(status = "SparkClusterStatus")]
+#[kube(
+    scale = r#"{"specReplicasPath":".spec.workers.replicas", "statusReplicasPath":".status.workers.ready", "labelSelectorPath":".status.workers.selector"}"#
+)]
+#[kube(
+    printcolumn = r#"{"name":"Version", "type":"string", "description":"The current Spark version", "jsonPath":".status.currentVersion"}"#,
+    printcolumn = r#"{"name":"Masters", "type":"string", "description":"Ready/desired master pods", "jsonPath":".status.masters.summary"}"#,
+    printcolumn = r#"{"name":"Workers", "type":"string", "description":"Ready/desired worker pods", "jsonPath":".status.workers.summary"}"#,
+    printcolumn = r#"{"name":"Available", "type":"string", "description":"Whether the cluster is available", "jsonPath":".status.conditions[?(@.type==\"Available\")].status"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#
+)]
 #[serde(rename_all = "camelCase")]
 pub struct SparkClusterSpec {
+    /// Config options for all roles. Overridden by the role and role group config
+    pub config: Option<CommonConfig>,
     pub masters: NodeGroup<MasterConfig>,
     pub workers: NodeGroup<WorkerConfig>,
     pub history_servers: Option<NodeGroup<HistoryServerConfig>>,
     pub version: SparkVersion,
     pub secret: Option<String>,
     pub log_dir: Option<String>,
+    /// Shared volume of the event log directory, required for the history server with the
+    /// kubernetes runtime
+    pub event_log_volume: Option<EventLogVolume>,
     pub max_port_retries: Option<usize>,
+    /// Seconds a ready worker may stay unregistered at the alive master before it is recreated
+    pub worker_registration_timeout_seconds: Option<u64>,
+    /// How pods are placed on nodes (default: Node)
+    pub placement: Option<Placement>,
+    /// Node placement only: create at most `instances` pods per role group instead of one pod
+    /// per matching node. Required to scale role groups with Node placement (default: false)
+    pub limit_node_placement: Option<bool>,
+    /// Tolerations for all pods. If not set, the Krustlet tolerations are used for the
+    /// stackableAgent runtime
+    #[schemars(schema_with = "tolerations_schema")]
+    pub tolerations: Option<Vec<Toleration>>,
+    /// The kind of node agent that runs the pods (default: stackableAgent)
+    pub runtime: Option<Runtime>,
+    /// Number of finished commands of each kind that are kept (default: 10)
+    pub command_history_limit: Option<usize>,
+    /// What happens to the PersistentVolumeClaims of the cluster when it is deleted
+    /// (default: Retain)
+    pub pvc_retention_policy: Option<PvcRetentionPolicy>,
+    /// Pod template merged onto all pods of the cluster (strategic merge)
+    #[schemars(schema_with = "pod_overrides_schema")]
+    pub pod_overrides: Option<serde_json::Value>,
+    /// Security context of all pods (only applied for the kubernetes runtime)
+    pub security: Option<SecurityConfig>,
+}
+
+/// What happens to the PersistentVolumeClaims of a cluster when the cluster is deleted.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum PvcRetentionPolicy {
+    /// Keep the PersistentVolumeClaims (and their data)
+    Retain,
+    /// Delete the PersistentVolumeClaims together with the cluster
+    Delete,
+}
+
+impl Default for PvcRetentionPolicy {
+    fn default() -> Self {
+        PvcRetentionPolicy::Retain
+    }
+}
+
+/// The kind of node agent that runs the pods. This decides how images are named, where the
+/// configuration is mounted and how the Spark processes are started.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum Runtime {
+    /// The Stackable agent (Krustlet) which installs Spark from packages
+    #[serde(rename = "stackableAgent")]
+    StackableAgent,
+    /// A regular kubelet with a container runtime which runs Spark container images
+    #[serde(rename = "kubernetes")]
+    Kubernetes,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Runtime::StackableAgent
+    }
+}
+
+impl Runtime {
+    /// Returns the package name (stackableAgent) or the container image (kubernetes)
+    ///
+    /// # Arguments
+    /// * `version` - Current specified cluster version
+    ///
+    pub fn get_image_name(&self, version: &SparkVersion) -> String {
+        match self {
+            Runtime::StackableAgent => format!("spark:{}", version),
+            Runtime::Kubernetes => format!("{}:{}", SPARK_IMAGE_REPOSITORY, version),
+        }
+    }
+
+    /// Returns the directory the config map is mounted to. The Stackable agent resolves
+    /// the "{{configroot}}" template to the config directory of the pod, a regular kubelet
+    /// requires an absolute path.
+    pub fn get_config_dir(&self) -> &'static str {
+        match self {
+            Runtime::StackableAgent => "{{configroot}}/conf",
+            Runtime::Kubernetes => SPARK_IMAGE_CONFIG_DIR,
+        }
+    }
+
+    /// Returns the Spark log directory (`SPARK_LOG_DIR`). The Stackable agent resolves the
+    /// "{{packageroot}}" template to the directory the package is installed to, the Spark
+    /// images use an absolute path.
+    ///
+    /// # Arguments
+    /// * `version` - Current specified cluster version
+    ///
+    pub fn get_log_dir(&self, version: &SparkVersion) -> String {
+        match self {
+            Runtime::StackableAgent => {
+                format!("{{{{packageroot}}}}/spark-{}-bin-hadoop2.7/logs", version)
+            }
+            Runtime::Kubernetes => SPARK_IMAGE_LOG_DIR.to_string(),
+        }
+    }
+}
+
+/// Defines how the operator places the pods of a role group on nodes.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum Placement {
+    /// Pin one pod to every node that matches the selector via `nodeName` (e.g. for Krustlet)
+    Node,
+    /// Create `instances` pods per role group and let the Kubernetes scheduler place them on
+    /// nodes that match the selector
+    Scheduler,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Placement::Node
+    }
+}
+
+/// The k8s_openapi Toleration does not implement JsonSchema, so we only check for a list of
+/// objects and let the API server validate the content.
+fn tolerations_schema(_: &mut SchemaGenerator) -> Schema {
+    serde_json::from_value(serde_json::json!({
+        "type": "array",
+        "nullable": true,
+        "items": {
+            "type": "object",
+            "x-kubernetes-preserve-unknown-fields": true
+        }
+    }))
+    .unwrap()
+}
+
+/// The k8s_openapi EnvVar does not implement JsonSchema, so we only check for a list of
+/// objects with a name and let the API server validate the content.
+fn env_schema(_: &mut SchemaGenerator) -> Schema {
+    serde_json::from_value(serde_json::json!({
+        "type": "array",
+        "nullable": true,
+        "items": {
+            "type": "object",
+            "required": ["name"],
+            "x-kubernetes-preserve-unknown-fields": true
+        }
+    }))
+    .unwrap()
+}
+
+/// Pod overrides are a partial pod template (metadata and spec) that is validated by the
+/// API server when the pod is created, so only an object is required here.
+fn pod_overrides_schema(_: &mut SchemaGenerator) -> Schema {
+    serde_json::from_value(serde_json::json!({
+        "type": "object",
+        "nullable": true,
+        "x-kubernetes-preserve-unknown-fields": true
+    }))
+    .unwrap()
 }
 
 #[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NodeGroup<T> {
+    /// Config for all role groups of the role. Overridden by the role group config
+    pub config: Option<T>,
+    /// Pod template merged onto all pods of the role (after the cluster pod overrides)
+    #[schemars(schema_with = "pod_overrides_schema")]
+    pub pod_overrides: Option<serde_json::Value>,
     pub selectors: HashMap<String, SelectorAndConfig<T>>,
+    /// Total number of instances of all role groups. Overrides the instances of the role
+    /// groups and is distributed evenly over them (ordered by name)
+    pub replicas: Option<u16>,
+}
+
+impl<T> NodeGroup<T> {
+    /// Returns the desired number of instances of a role group. If `replicas` is set, it is
+    /// distributed evenly over all role groups ordered by name (the first role groups receive
+    /// the remainder).
+    ///
+    /// # Arguments
+    /// * `role_group` - The role group of the selector
+    ///
+    pub fn get_instances(&self, role_group: &str) -> Option<u16> {
+        let selector = self.selectors.get(role_group)?;
+
+        let replicas = match self.replicas {
+            Some(replicas) => replicas,
+            None => return Some(selector.instances),
+        };
+
+        let mut role_groups = self.selectors.keys().collect::<Vec<_>>();
+        role_groups.sort();
+        let index = role_groups.iter().position(|name| *name == role_group)? as u16;
+        let count = role_groups.len() as u16;
+
+        Some(replicas / count + u16::from(index < replicas % count))
+    }
+}
+
+impl<T> NodeGroup<T> {
+    /// Returns the pod overrides of the role and of the role group (in this order)
+    ///
+    /// # Arguments
+    /// * `role_group` - The role group of the selector
+    ///
+    pub fn get_pod_overrides(&self, role_group: &str) -> Vec<serde_json::Value> {
+        self.pod_overrides
+            .iter()
+            .chain(
+                self.selectors
+                    .get(role_group)
+                    .and_then(|selector| selector.pod_overrides.as_ref()),
+            )
+            .cloned()
+            .collect()
+    }
+}
+
+impl<T: RoleConfig> NodeGroup<T> {
+    /// Returns the config of a role group merged with the role config. Fields set on the
+    /// role group take precedence over the role config.
+    ///
+    /// # Arguments
+    /// * `role_group` - The role group of the selector
+    ///
+    pub fn get_config(&self, role_group: &str) -> Option<T> {
+        let selector = self.selectors.get(role_group)?;
+        let role_group_config = selector.config.clone().unwrap_or_default();
+
+        Some(match &self.config {
+            Some(role_config) => role_group_config.merge(role_config),
+            None => role_group_config,
+        })
+    }
 }
 
 impl SparkClusterSpec {
+    /// Returns the configured placement or the default (Node)
+    pub fn get_placement(&self) -> Placement {
+        self.placement.clone().unwrap_or_default()
+    }
+
+    /// Returns whether Node placement is limited to the instances of the role groups
+    /// (default: false, one pod per matching node)
+    pub fn is_node_placement_limited(&self) -> bool {
+        self.limit_node_placement.unwrap_or(false)
+    }
+
+    /// Returns the configured runtime or the default (stackableAgent)
+    pub fn get_runtime(&self) -> Runtime {
+        self.runtime.clone().unwrap_or_default()
+    }
+
+    /// Returns whether the masters, workers and history servers share the event logs. The
+    /// kubernetes runtime requires a log dir and a valid event log volume, otherwise every pod
+    /// writes to its own directory and the history server cannot read the applications.
+    pub fn has_shared_event_log(&self) -> bool {
+        match self.get_runtime() {
+            Runtime::StackableAgent => true,
+            Runtime::Kubernetes => {
+                self.log_dir.is_some()
+                    && self
+                        .event_log_volume
+                        .as_ref()
+                        .map(EventLogVolume::is_valid)
+                        .unwrap_or(false)
+            }
+        }
+    }
+
+    /// Returns whether workers are decommissioned gracefully before their pods are stopped.
+    /// This requires Spark 3.1 (`spark.decommission.enabled`) and the kubernetes runtime, which
+    /// runs the preStop hook that signals the worker and waits for its executors to exit.
+    pub fn supports_graceful_decommission(&self) -> bool {
+        self.get_runtime() == Runtime::Kubernetes && self.version.supports_decommission()
+    }
+
+    /// Returns the seconds a worker of the given role group may take to decommission. This is
+    /// taken from the autoscaling settings of the role group or the default.
+    ///
+    /// # Arguments
+    /// * `role_group` - The role group of the worker selector
+    ///
+    pub fn get_decommission_timeout(&self, role_group: &str) -> Duration {
+        Duration::from_secs(
+            self.workers
+                .selectors
+                .get(role_group)
+                .and_then(|selector| selector.autoscaling.as_ref())
+                .and_then(|autoscaling| autoscaling.decommission_timeout_seconds)
+                .unwrap_or(DEFAULT_DECOMMISSION_TIMEOUT_SECONDS),
+        )
+    }
+
+    /// Returns the configured security settings or the defaults
+    pub fn get_security(&self) -> SecurityConfig {
+        self.security.clone().unwrap_or_default()
+    }
+
+    /// Returns the configured PVC retention policy or the default (Retain)
+    pub fn get_pvc_retention_policy(&self) -> PvcRetentionPolicy {
+        self.pvc_retention_policy.clone().unwrap_or_default()
+    }
+
+    /// Returns the configured number of finished commands to keep or the default
+    pub fn get_command_history_limit(&self) -> usize {
+        self.command_history_limit
+            .unwrap_or(DEFAULT_COMMAND_HISTORY_LIMIT)
+    }
+
+    /// Returns the pod overrides of the cluster, the role and the role group in the order
+    /// they are applied to a pod of the role group
+    ///
+    /// # Arguments
+    /// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+    /// * `role_group` - The role group of the selector
+    ///
+    pub fn get_pod_overrides(
+        &self,
+        node_type: &SparkNodeType,
+        role_group: &str,
+    ) -> Vec<serde_json::Value> {
+        let mut pod_overrides = self.pod_overrides.iter().cloned().collect::<Vec<_>>();
+
+        pod_overrides.extend(match node_type {
+            SparkNodeType::Master => self.masters.get_pod_overrides(role_group),
+            SparkNodeType::Worker => self.workers.get_pod_overrides(role_group),
+            SparkNodeType::HistoryServer => self
+                .history_servers
+                .as_ref()
+                .map(|group| group.get_pod_overrides(role_group))
+                .unwrap_or_default(),
+        });
+
+        pod_overrides
+    }
+
+    /// Returns the names of all role groups of a role
+    ///
+    /// # Arguments
+    /// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+    ///
+    pub fn get_role_groups(&self, node_type: &SparkNodeType) -> Vec<String> {
+        match node_type {
+            SparkNodeType::Master => self.masters.selectors.keys().cloned().collect(),
+            SparkNodeType::Worker => self.workers.selectors.keys().cloned().collect(),
+            SparkNodeType::HistoryServer => self
+                .history_servers
+                .as_ref()
+                .map(|group| group.selectors.keys().cloned().collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns the desired number of instances of a role group
+    ///
+    /// # Arguments
+    /// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+    /// * `role_group` - The role group of the selector
+    ///
+    pub fn get_instances(&self, node_type: &SparkNodeType, role_group: &str) -> Option<u16> {
+        match node_type {
+            SparkNodeType::Master => self.masters.get_instances(role_group),
+            SparkNodeType::Worker => self.workers.get_instances(role_group),
+            SparkNodeType::HistoryServer => self
+                .history_servers
+                .as_ref()
+                .and_then(|group| group.get_instances(role_group)),
+        }
+    }
+
+    /// Returns the node selector of a role group
+    ///
+    /// # Arguments
+    /// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+    /// * `role_group` - The role group of the selector
+    ///
+    pub fn get_selector(
+        &self,
+        node_type: &SparkNodeType,
+        role_group: &str,
+    ) -> Option<LabelSelector> {
+        match node_type {
+            SparkNodeType::Master => self
+                .masters
+                .selectors
+                .get(role_group)
+                .and_then(|selector| selector.selector.clone()),
+            SparkNodeType::Worker => self
+                .workers
+                .selectors
+                .get(role_group)
+                .and_then(|selector| selector.selector.clone()),
+            SparkNodeType::HistoryServer => self
+                .history_servers
+                .as_ref()
+                .and_then(|group| group.selectors.get(role_group))
+                .and_then(|selector| selector.selector.clone()),
+        }
+    }
+
+    /// Returns the merged config of a role group (see `Config` for the precedence of the
+    /// cluster, role and role group config)
+    ///
+    /// # Arguments
+    /// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+    /// * `role_group` - The role group of the selector
+    ///
     pub fn get_config(
         &self,
         node_type: &SparkNodeType,
         role_group: &str,
     ) -> Option<Box<dyn Config>> {
         match node_type {
-            SparkNodeType::Master => {
-                if let Some(selector) = self.masters.selectors.get(role_group) {
-                    return Some(Box::new(selector.config.clone().unwrap_or(MasterConfig {
-                        ..MasterConfig::default()
-                    })));
-                }
-            }
-            SparkNodeType::Worker => {
-                if let Some(selector) = self.workers.selectors.get(role_group) {
-                    return Some(Box::new(selector.config.clone().unwrap_or(WorkerConfig {
-                        ..WorkerConfig::default()
-                    })));
-                }
-            }
-            SparkNodeType::HistoryServer => {
-                if let Some(history_servers) = &self.history_servers {
-                    {
-                        if let Some(selector) = history_servers.selectors.get(role_group) {
-                            return Some(Box::new(selector.config.clone().unwrap_or(
-                                HistoryServerConfig {
-                                    ..HistoryServerConfig::default()
-                                },
-                            )));
-                        }
-                    }
-                }
-            }
+            SparkNodeType::Master => self
+                .masters
+                .get_config(role_group)
+                .map(|config| Box::new(config) as Box<dyn Config>),
+            SparkNodeType::Worker => self
+                .workers
+                .get_config(role_group)
+                .map(|config| Box::new(config) as Box<dyn Config>),
+            SparkNodeType::HistoryServer => self
+                .history_servers
+                .as_ref()
+                .and_then(|group| group.get_config(role_group))
+                .map(|config| Box::new(config) as Box<dyn Config>),
         }
-
-        None
     }
 }
 
@@ -100,18 +524,82 @@ pub struct SelectorAndConfig<T> {
     pub config: Option<T>,
     #[schemars(schema_with = "schema")]
     pub selector: Option<LabelSelector>,
+    /// Autoscaling of the role group (only evaluated for workers)
+    pub autoscaling: Option<Autoscaling>,
+    /// Pod template merged onto all pods of the role group (after the role pod overrides)
+    #[schemars(schema_with = "pod_overrides_schema")]
+    pub pod_overrides: Option<serde_json::Value>,
+}
+
+/// Scale a worker role group between `min_instances` and `max_instances` depending on the
+/// used cores and waiting applications reported by the alive master.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Autoscaling {
+    pub min_instances: u16,
+    pub max_instances: u16,
+    /// Target percentage of used worker cores. Default: 80
+    pub target_core_utilization: Option<u8>,
+    /// Seconds after the last scaling before the role group is scaled up. Default: 60
+    pub scale_up_cooldown_seconds: Option<u64>,
+    /// Seconds after the last scaling before the role group is scaled down. Default: 300
+    pub scale_down_cooldown_seconds: Option<u64>,
+    /// Seconds a worker may take to decommission before it is stopped. Default: 300
+    pub decommission_timeout_seconds: Option<u64>,
 }
 
+impl Autoscaling {
+    /// Returns the configured target core utilization in percent or the default
+    pub fn get_target_core_utilization(&self) -> u8 {
+        self.target_core_utilization
+            .unwrap_or(DEFAULT_TARGET_CORE_UTILIZATION)
+    }
+
+    /// Returns the configured scale up cooldown or the default
+    pub fn get_scale_up_cooldown(&self) -> Duration {
+        Duration::from_secs(
+            self.scale_up_cooldown_seconds
+                .unwrap_or(DEFAULT_SCALE_UP_COOLDOWN_SECONDS),
+        )
+    }
+
+    /// Returns the configured scale down cooldown or the default
+    pub fn get_scale_down_cooldown(&self) -> Duration {
+        Duration::from_secs(
+            self.scale_down_cooldown_seconds
+                .unwrap_or(DEFAULT_SCALE_DOWN_COOLDOWN_SECONDS),
+        )
+    }
+}
+
+/// Config options that are applied to all roles of the cluster
 #[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
+pub struct CommonConfig {
+    pub spark_defaults: Option<Vec<ConfigOption>>,
+    pub spark_env_sh: Option<Vec<ConfigOption>>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MasterConfig {
     pub master_port: Option<u16>,
     pub master_web_ui_port: Option<u16>,
     pub spark_defaults: Option<Vec<ConfigOption>>,
     pub spark_env_sh: Option<Vec<ConfigOption>>,
+    pub logging: Option<LoggingConfig>,
+    /// Properties of the Hadoop and Hive config files written to the Spark config directory
+    pub hadoop_config: Option<HadoopConfig>,
+    /// ConfigMaps and Secrets mounted into the Spark container
+    pub volumes: Option<Vec<ExtraVolume>>,
+    /// Environment variables of the Spark container (e.g. secret references)
+    #[schemars(schema_with = "env_schema")]
+    pub env: Option<Vec<EnvVar>>,
+    /// Liveness and readiness probes of the Spark container
+    pub probes: Option<ProbesConfig>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WorkerConfig {
     pub cores: Option<usize>,
@@ -120,22 +608,66 @@ pub struct WorkerConfig {
     pub worker_web_ui_port: Option<u16>,
     pub spark_defaults: Option<Vec<ConfigOption>>,
     pub spark_env_sh: Option<Vec<ConfigOption>>,
+    pub logging: Option<LoggingConfig>,
+    /// Properties of the Hadoop and Hive config files written to the Spark config directory
+    pub hadoop_config: Option<HadoopConfig>,
+    /// ConfigMaps and Secrets mounted into the Spark container
+    pub volumes: Option<Vec<ExtraVolume>>,
+    /// Environment variables of the Spark container (e.g. secret references)
+    #[schemars(schema_with = "env_schema")]
+    pub env: Option<Vec<EnvVar>>,
+    /// Liveness and readiness probes of the Spark container
+    pub probes: Option<ProbesConfig>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct HistoryServerConfig {
     pub store_path: Option<String>,
     pub history_web_ui_port: Option<u16>,
     pub spark_defaults: Option<Vec<ConfigOption>>,
     pub spark_env_sh: Option<Vec<ConfigOption>>,
+    pub logging: Option<LoggingConfig>,
+    /// Properties of the Hadoop and Hive config files written to the Spark config directory
+    pub hadoop_config: Option<HadoopConfig>,
+    /// ConfigMaps and Secrets mounted into the Spark container
+    pub volumes: Option<Vec<ExtraVolume>>,
+    /// Environment variables of the Spark container (e.g. secret references)
+    #[schemars(schema_with = "env_schema")]
+    pub env: Option<Vec<EnvVar>>,
+    /// Liveness and readiness probes of the Spark container
+    pub probes: Option<ProbesConfig>,
+}
+
+/// A config that can be set on the role and on the role group level.
+pub trait RoleConfig: Clone + Default {
+    /// Merge this role group config with the role config. Fields set on the role group take
+    /// precedence, the config options of both levels are combined (role group options are
+    /// applied last and override role options of the same name).
+    ///
+    /// # Arguments
+    /// * `role_config` - The config of the role
+    ///
+    fn merge(&self, role_config: &Self) -> Self;
+
+    /// Returns the user defined config options for spark-defaults.conf
+    fn get_spark_defaults(&self) -> Option<&Vec<ConfigOption>>;
+
+    /// Returns the user defined config options for spark-env.sh
+    fn get_spark_env(&self) -> Option<&Vec<ConfigOption>>;
 }
 
+/// The config of a role group merged with its role config. The config files are built with
+/// the following precedence (later entries override earlier ones of the same name):
+/// 1. Values the operator derives from the spec and the dedicated config fields
+/// 2. Cluster wide config options (`spec.config`)
+/// 3. Role config options (e.g. `spec.masters.config`)
+/// 4. Role group config options (e.g. `spec.masters.selectors.<role_group>.config`)
 pub trait Config: Send + Sync {
     /// Get all required configuration options for spark-defaults.conf
     /// - from spec
     /// - from selector
-    /// - from user config properties
+    /// - from user config properties (cluster, role and role group)
     ///
     /// # Arguments
     /// * `spec` - SparkCluster spec for common properties
@@ -144,9 +676,49 @@ pub trait Config: Send + Sync {
 
     /// Get all required configuration options for spark-env.sh
     /// - from selector
-    /// - from user config properties
+    /// - from user config properties (cluster, role and role group)
     ///
-    fn get_spark_env_sh(&self) -> BTreeMap<String, String>;
+    /// # Arguments
+    /// * `spec` - SparkCluster spec for common properties
+    ///
+    fn get_spark_env_sh(&self, spec: &SparkClusterSpec) -> BTreeMap<String, String>;
+
+    /// Returns the logging config of the role group
+    fn get_logging(&self) -> Option<&LoggingConfig>;
+
+    /// Returns the Hadoop and Hive config of the role group
+    fn get_hadoop_config(&self) -> Option<&HadoopConfig>;
+
+    /// Returns the ConfigMaps and Secrets mounted into the Spark container of the role group
+    fn get_extra_volumes(&self) -> Option<&Vec<ExtraVolume>>;
+
+    /// Returns the user defined env vars of the Spark container of the role group
+    fn get_env(&self) -> Option<&Vec<EnvVar>>;
+
+    /// Returns the probes config of the Spark container of the role group
+    fn get_probes(&self) -> Option<&ProbesConfig>;
+
+    /// Returns the port of the web ui (configured or default)
+    ///
+    /// # Arguments
+    /// * `spec` - SparkCluster spec for common properties
+    ///
+    fn get_web_ui_port(&self, spec: &SparkClusterSpec) -> String;
+
+    /// Render the Hadoop and Hive config files (e.g. core-site.xml) of the role group.
+    /// Returns the file names mapped to their content.
+    fn get_hadoop_config_files(&self) -> BTreeMap<String, String> {
+        self.get_hadoop_config()
+            .map(HadoopConfig::render_files)
+            .unwrap_or_default()
+    }
+
+    /// Render the log4j properties of the role group. The log file defaults to the Spark log
+    /// directory of the runtime.
+    fn get_log4j_properties(&self, spec: &SparkClusterSpec) -> String {
+        let logging = self.get_logging().cloned().unwrap_or_default();
+        logging::render_log4j_properties(&logging, &spec.get_runtime().get_log_dir(&spec.version))
+    }
 }
 
 /// This is a workaround to properly access the methods of the Config trait
@@ -159,8 +731,32 @@ where
         (**self).get_spark_defaults_conf(&spec)
     }
 
-    fn get_spark_env_sh(&self) -> BTreeMap<String, String> {
-        (**self).get_spark_env_sh()
+    fn get_spark_env_sh(&self, spec: &SparkClusterSpec) -> BTreeMap<String, String> {
+        (**self).get_spark_env_sh(spec)
+    }
+
+    fn get_logging(&self) -> Option<&LoggingConfig> {
+        (**self).get_logging()
+    }
+
+    fn get_hadoop_config(&self) -> Option<&HadoopConfig> {
+        (**self).get_hadoop_config()
+    }
+
+    fn get_extra_volumes(&self) -> Option<&Vec<ExtraVolume>> {
+        (**self).get_extra_volumes()
+    }
+
+    fn get_env(&self) -> Option<&Vec<EnvVar>> {
+        (**self).get_env()
+    }
+
+    fn get_probes(&self) -> Option<&ProbesConfig> {
+        (**self).get_probes()
+    }
+
+    fn get_web_ui_port(&self, spec: &SparkClusterSpec) -> String {
+        (**self).get_web_ui_port(spec)
     }
 }
 
@@ -182,11 +778,15 @@ impl Config for MasterConfig {
         }
 
         add_common_spark_defaults(&mut config, spec);
-        add_user_defined_config_properties(&mut config, &self.spark_defaults);
+        add_user_defined_config_properties(
+            &mut config,
+            &self.spark_defaults,
+            &PropertyFile::SparkDefaults,
+        );
         config
     }
 
-    fn get_spark_env_sh(&self) -> BTreeMap<String, String> {
+    fn get_spark_env_sh(&self, spec: &SparkClusterSpec) -> BTreeMap<String, String> {
         let mut config = BTreeMap::new();
 
         if let Some(port) = &self.master_port {
@@ -199,9 +799,41 @@ impl Config for MasterConfig {
             );
         }
 
-        add_user_defined_config_properties(&mut config, &self.spark_env_sh);
+        add_common_spark_env_sh(&mut config, spec);
+        add_user_defined_config_properties(
+            &mut config,
+            &self.spark_env_sh,
+            &PropertyFile::SparkEnvSh,
+        );
         config
     }
+
+    fn get_logging(&self) -> Option<&LoggingConfig> {
+        self.logging.as_ref()
+    }
+
+    fn get_hadoop_config(&self) -> Option<&HadoopConfig> {
+        self.hadoop_config.as_ref()
+    }
+
+    fn get_extra_volumes(&self) -> Option<&Vec<ExtraVolume>> {
+        self.volumes.as_ref()
+    }
+
+    fn get_env(&self) -> Option<&Vec<EnvVar>> {
+        self.env.as_ref()
+    }
+
+    fn get_probes(&self) -> Option<&ProbesConfig> {
+        self.probes.as_ref()
+    }
+
+    fn get_web_ui_port(&self, _spec: &SparkClusterSpec) -> String {
+        // the web ui port is managed: it is only set via the masterWebUiPort field
+        self.master_web_ui_port
+            .map(|port| port.to_string())
+            .unwrap_or_else(|| DEFAULT_MASTER_WEB_UI_PORT.to_string())
+    }
 }
 
 impl Config for WorkerConfig {
@@ -221,12 +853,23 @@ impl Config for WorkerConfig {
             );
         }
 
+        if spec.supports_graceful_decommission() {
+            config.insert(
+                SPARK_DEFAULTS_DECOMMISSION_ENABLED.to_string(),
+                "true".to_string(),
+            );
+        }
+
         add_common_spark_defaults(&mut config, spec);
-        add_user_defined_config_properties(&mut config, &self.spark_defaults);
+        add_user_defined_config_properties(
+            &mut config,
+            &self.spark_defaults,
+            &PropertyFile::SparkDefaults,
+        );
         config
     }
 
-    fn get_spark_env_sh(&self) -> BTreeMap<String, String> {
+    fn get_spark_env_sh(&self, spec: &SparkClusterSpec) -> BTreeMap<String, String> {
         let mut config = BTreeMap::new();
 
         if let Some(cores) = &self.cores {
@@ -245,67 +888,350 @@ impl Config for WorkerConfig {
             );
         }
 
-        add_user_defined_config_properties(&mut config, &self.spark_env_sh);
-        config
+        add_common_spark_env_sh(&mut config, spec);
+        add_user_defined_config_properties(
+            &mut config,
+            &self.spark_env_sh,
+            &PropertyFile::SparkEnvSh,
+        );
+        config
+    }
+
+    fn get_logging(&self) -> Option<&LoggingConfig> {
+        self.logging.as_ref()
+    }
+
+    fn get_hadoop_config(&self) -> Option<&HadoopConfig> {
+        self.hadoop_config.as_ref()
+    }
+
+    fn get_extra_volumes(&self) -> Option<&Vec<ExtraVolume>> {
+        self.volumes.as_ref()
+    }
+
+    fn get_env(&self) -> Option<&Vec<EnvVar>> {
+        self.env.as_ref()
+    }
+
+    fn get_probes(&self) -> Option<&ProbesConfig> {
+        self.probes.as_ref()
+    }
+
+    fn get_web_ui_port(&self, _spec: &SparkClusterSpec) -> String {
+        // the web ui port is managed: it is only set via the workerWebUiPort field
+        self.worker_web_ui_port
+            .map(|port| port.to_string())
+            .unwrap_or_else(|| DEFAULT_WORKER_WEB_UI_PORT.to_string())
+    }
+}
+
+impl Config for HistoryServerConfig {
+    fn get_spark_defaults_conf(&self, spec: &SparkClusterSpec) -> BTreeMap<String, String> {
+        let mut config = BTreeMap::new();
+
+        let log_dir = spec.log_dir.as_deref().unwrap_or(DEFAULT_LOG_DIR);
+        config.insert(
+            SPARK_DEFAULTS_HISTORY_FS_LOG_DIRECTORY.to_string(),
+            log_dir.to_string(),
+        );
+
+        if let Some(store_path) = &self.store_path {
+            config.insert(
+                SPARK_DEFAULTS_HISTORY_STORE_PATH.to_string(),
+                store_path.to_string(),
+            );
+        }
+        if let Some(port) = &self.history_web_ui_port {
+            config.insert(
+                SPARK_DEFAULTS_HISTORY_WEBUI_PORT.to_string(),
+                port.to_string(),
+            );
+        }
+
+        add_common_spark_defaults(&mut config, spec);
+        add_user_defined_config_properties(
+            &mut config,
+            &self.spark_defaults,
+            &PropertyFile::SparkDefaults,
+        );
+        config
+    }
+
+    fn get_spark_env_sh(&self, spec: &SparkClusterSpec) -> BTreeMap<String, String> {
+        let mut config = BTreeMap::new();
+        add_common_spark_env_sh(&mut config, spec);
+        add_user_defined_config_properties(
+            &mut config,
+            &self.spark_env_sh,
+            &PropertyFile::SparkEnvSh,
+        );
+        config
+    }
+
+    fn get_logging(&self) -> Option<&LoggingConfig> {
+        self.logging.as_ref()
+    }
+
+    fn get_hadoop_config(&self) -> Option<&HadoopConfig> {
+        self.hadoop_config.as_ref()
+    }
+
+    fn get_extra_volumes(&self) -> Option<&Vec<ExtraVolume>> {
+        self.volumes.as_ref()
+    }
+
+    fn get_env(&self) -> Option<&Vec<EnvVar>> {
+        self.env.as_ref()
+    }
+
+    fn get_probes(&self) -> Option<&ProbesConfig> {
+        self.probes.as_ref()
+    }
+
+    fn get_web_ui_port(&self, spec: &SparkClusterSpec) -> String {
+        self.get_spark_defaults_conf(spec)
+            .get(SPARK_DEFAULTS_HISTORY_WEBUI_PORT)
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_HISTORY_WEB_UI_PORT.to_string())
+    }
+}
+
+fn add_common_spark_defaults(config: &mut BTreeMap<String, String>, spec: &SparkClusterSpec) {
+    if let Some(secret) = &spec.secret {
+        config.insert(
+            SPARK_DEFAULTS_AUTHENTICATE_SECRET.to_string(),
+            secret.to_string(),
+        );
+    }
+
+    let max_port_retries = &spec.max_port_retries.unwrap_or(0);
+    config.insert(
+        SPARK_DEFAULTS_PORT_MAX_RETRIES.to_string(),
+        max_port_retries.to_string(),
+    );
+
+    if let Some(common_config) = &spec.config {
+        add_user_defined_config_properties(
+            config,
+            &common_config.spark_defaults,
+            &PropertyFile::SparkDefaults,
+        );
+    }
+}
+
+fn add_common_spark_env_sh(config: &mut BTreeMap<String, String>, spec: &SparkClusterSpec) {
+    if let Some(common_config) = &spec.config {
+        add_user_defined_config_properties(
+            config,
+            &common_config.spark_env_sh,
+            &PropertyFile::SparkEnvSh,
+        );
+    }
+}
+
+/// Merge an optional config section (e.g. logging or probes) of the role and the role group.
+/// If both are set, `merge` combines them with the fields of the role group taking precedence,
+/// otherwise the one that is set is used.
+///
+/// # Arguments
+/// * `role_section` - The config section of the role
+/// * `role_group_section` - The config section of the role group
+/// * `merge` - Merges the role group section (first argument) with the role section
+///
+pub(crate) fn merge_section<T: Clone>(
+    role_section: &Option<T>,
+    role_group_section: &Option<T>,
+    merge: fn(&T, &T) -> T,
+) -> Option<T> {
+    match (role_section, role_group_section) {
+        (Some(role_section), Some(role_group_section)) => {
+            Some(merge(role_group_section, role_section))
+        }
+        (role_section, role_group_section) => {
+            role_group_section.clone().or_else(|| role_section.clone())
+        }
+    }
+}
+
+/// Combine the entries (e.g. config options, properties or logger levels) of the role and the
+/// role group by chaining them: role entries first, then role group entries. The last entry
+/// of a name wins, either when collecting into a map or when the options are applied in order.
+///
+/// # Arguments
+/// * `role_entries` - The entries of the role
+/// * `role_group_entries` - The entries of the role group
+///
+pub(crate) fn merge_chained<C>(
+    role_entries: &Option<C>,
+    role_group_entries: &Option<C>,
+) -> Option<C>
+where
+    C: Clone + IntoIterator + FromIterator<<C as IntoIterator>::Item>,
+{
+    match (role_entries, role_group_entries) {
+        (None, None) => None,
+        _ => Some(
+            role_entries
+                .iter()
+                .chain(role_group_entries.iter())
+                .cloned()
+                .flatten()
+                .collect(),
+        ),
+    }
+}
+
+/// Combine the env vars of the role and the role group. Role group env vars replace role env
+/// vars of the same name.
+fn merge_env(
+    role_env: &Option<Vec<EnvVar>>,
+    role_group_env: &Option<Vec<EnvVar>>,
+) -> Option<Vec<EnvVar>> {
+    match (role_env, role_group_env) {
+        (None, None) => None,
+        _ => {
+            let role_group_env = role_group_env.clone().unwrap_or_default();
+            let mut env = role_env
+                .iter()
+                .flatten()
+                .filter(|env_var| {
+                    !role_group_env
+                        .iter()
+                        .any(|role_group_env_var| role_group_env_var.name == env_var.name)
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            env.extend(role_group_env);
+            Some(env)
+        }
+    }
+}
+
+impl RoleConfig for CommonConfig {
+    fn merge(&self, role_config: &Self) -> Self {
+        CommonConfig {
+            spark_defaults: merge_chained(&role_config.spark_defaults, &self.spark_defaults),
+            spark_env_sh: merge_chained(&role_config.spark_env_sh, &self.spark_env_sh),
+        }
+    }
+
+    fn get_spark_defaults(&self) -> Option<&Vec<ConfigOption>> {
+        self.spark_defaults.as_ref()
+    }
+
+    fn get_spark_env(&self) -> Option<&Vec<ConfigOption>> {
+        self.spark_env_sh.as_ref()
+    }
+}
+
+impl RoleConfig for MasterConfig {
+    fn merge(&self, role_config: &Self) -> Self {
+        MasterConfig {
+            master_port: self.master_port.or(role_config.master_port),
+            master_web_ui_port: self.master_web_ui_port.or(role_config.master_web_ui_port),
+            spark_defaults: merge_chained(&role_config.spark_defaults, &self.spark_defaults),
+            spark_env_sh: merge_chained(&role_config.spark_env_sh, &self.spark_env_sh),
+            logging: merge_section(&role_config.logging, &self.logging, LoggingConfig::merge),
+            hadoop_config: merge_section(
+                &role_config.hadoop_config,
+                &self.hadoop_config,
+                HadoopConfig::merge,
+            ),
+            volumes: volumes::merge_volumes(&role_config.volumes, &self.volumes),
+            env: merge_env(&role_config.env, &self.env),
+            probes: merge_section(&role_config.probes, &self.probes, ProbesConfig::merge),
+        }
+    }
+
+    fn get_spark_defaults(&self) -> Option<&Vec<ConfigOption>> {
+        self.spark_defaults.as_ref()
+    }
+
+    fn get_spark_env(&self) -> Option<&Vec<ConfigOption>> {
+        self.spark_env_sh.as_ref()
     }
 }
 
-impl Config for HistoryServerConfig {
-    fn get_spark_defaults_conf(&self, spec: &SparkClusterSpec) -> BTreeMap<String, String> {
-        let mut config = BTreeMap::new();
-
-        let log_dir = spec.log_dir.as_deref().unwrap_or(DEFAULT_LOG_DIR);
-        config.insert(
-            SPARK_DEFAULTS_HISTORY_FS_LOG_DIRECTORY.to_string(),
-            log_dir.to_string(),
-        );
-
-        if let Some(store_path) = &self.store_path {
-            config.insert(
-                SPARK_DEFAULTS_HISTORY_STORE_PATH.to_string(),
-                store_path.to_string(),
-            );
-        }
-        if let Some(port) = &self.history_web_ui_port {
-            config.insert(
-                SPARK_DEFAULTS_HISTORY_WEBUI_PORT.to_string(),
-                port.to_string(),
-            );
+impl RoleConfig for WorkerConfig {
+    fn merge(&self, role_config: &Self) -> Self {
+        WorkerConfig {
+            cores: self.cores.or(role_config.cores),
+            memory: self.memory.clone().or_else(|| role_config.memory.clone()),
+            worker_port: self.worker_port.or(role_config.worker_port),
+            worker_web_ui_port: self.worker_web_ui_port.or(role_config.worker_web_ui_port),
+            spark_defaults: merge_chained(&role_config.spark_defaults, &self.spark_defaults),
+            spark_env_sh: merge_chained(&role_config.spark_env_sh, &self.spark_env_sh),
+            logging: merge_section(&role_config.logging, &self.logging, LoggingConfig::merge),
+            hadoop_config: merge_section(
+                &role_config.hadoop_config,
+                &self.hadoop_config,
+                HadoopConfig::merge,
+            ),
+            volumes: volumes::merge_volumes(&role_config.volumes, &self.volumes),
+            env: merge_env(&role_config.env, &self.env),
+            probes: merge_section(&role_config.probes, &self.probes, ProbesConfig::merge),
         }
+    }
 
-        add_common_spark_defaults(&mut config, spec);
-        add_user_defined_config_properties(&mut config, &self.spark_defaults);
-        config
+    fn get_spark_defaults(&self) -> Option<&Vec<ConfigOption>> {
+        self.spark_defaults.as_ref()
     }
 
-    fn get_spark_env_sh(&self) -> BTreeMap<String, String> {
-        let mut config = BTreeMap::new();
-        add_user_defined_config_properties(&mut config, &self.spark_env_sh);
-        config
+    fn get_spark_env(&self) -> Option<&Vec<ConfigOption>> {
+        self.spark_env_sh.as_ref()
     }
 }
 
-fn add_common_spark_defaults(config: &mut BTreeMap<String, String>, spec: &SparkClusterSpec) {
-    if let Some(secret) = &spec.secret {
-        config.insert(
-            SPARK_DEFAULTS_AUTHENTICATE_SECRET.to_string(),
-            secret.to_string(),
-        );
+impl RoleConfig for HistoryServerConfig {
+    fn merge(&self, role_config: &Self) -> Self {
+        HistoryServerConfig {
+            store_path: self
+                .store_path
+                .clone()
+                .or_else(|| role_config.store_path.clone()),
+            history_web_ui_port: self.history_web_ui_port.or(role_config.history_web_ui_port),
+            spark_defaults: merge_chained(&role_config.spark_defaults, &self.spark_defaults),
+            spark_env_sh: merge_chained(&role_config.spark_env_sh, &self.spark_env_sh),
+            logging: merge_section(&role_config.logging, &self.logging, LoggingConfig::merge),
+            hadoop_config: merge_section(
+                &role_config.hadoop_config,
+                &self.hadoop_config,
+                HadoopConfig::merge,
+            ),
+            volumes: volumes::merge_volumes(&role_config.volumes, &self.volumes),
+            env: merge_env(&role_config.env, &self.env),
+            probes: merge_section(&role_config.probes, &self.probes, ProbesConfig::merge),
+        }
     }
 
-    let max_port_retries = &spec.max_port_retries.unwrap_or(0);
-    config.insert(
-        SPARK_DEFAULTS_PORT_MAX_RETRIES.to_string(),
-        max_port_retries.to_string(),
-    );
+    fn get_spark_defaults(&self) -> Option<&Vec<ConfigOption>> {
+        self.spark_defaults.as_ref()
+    }
+
+    fn get_spark_env(&self) -> Option<&Vec<ConfigOption>> {
+        self.spark_env_sh.as_ref()
+    }
 }
 
+/// Add the user defined config options to the config. Options that are rejected by the
+/// validation (overrides of managed properties and invalid values) are skipped, they are
+/// reported in the ConfigurationValid condition instead.
+///
+/// # Arguments
+/// * `config` - The config to add the options to
+/// * `config_properties` - The user defined config options
+/// * `file` - The file the options are written to
+///
 fn add_user_defined_config_properties(
     config: &mut BTreeMap<String, String>,
     config_properties: &Option<Vec<ConfigOption>>,
+    file: &PropertyFile,
 ) {
     if let Some(conf) = config_properties {
         for config_option in conf {
+            if properties::is_rejected(file, config_option) {
+                continue;
+            }
             config.insert(config_option.name.clone(), config_option.value.clone());
         }
     }
@@ -337,18 +1263,25 @@ pub enum SparkNodeType {
 }
 
 impl SparkNodeType {
-    /// Returns the container start command for a spark node
-    /// Right now works only for images using hadoop2.7
+    /// Returns the container start command for a spark node.
+    /// The Stackable agent packages use hadoop2.7 and are extracted relative to the package
+    /// root, the container images provide Spark in a fixed directory.
     /// # Arguments
     /// * `version` - Current specified cluster version
+    /// * `runtime` - The kind of node agent that runs the pods
     ///
-    pub fn get_command(&self, version: &str) -> String {
-        // TODO: remove hardcoded and adapt for versioning
-        format!(
-            "spark-{}-bin-hadoop2.7/sbin/start-{}.sh",
-            version,
-            self.to_string()
-        )
+    pub fn get_command(&self, version: &str, runtime: &Runtime) -> String {
+        match runtime {
+            // TODO: remove hardcoded and adapt for versioning
+            Runtime::StackableAgent => format!(
+                "spark-{}-bin-hadoop2.7/sbin/start-{}.sh",
+                version,
+                self.to_string()
+            ),
+            Runtime::Kubernetes => {
+                format!("{}/sbin/start-{}.sh", SPARK_IMAGE_HOME, self.to_string())
+            }
+        }
     }
 }
 
@@ -372,12 +1305,96 @@ pub struct SparkClusterStatus {
     pub current_command: Option<CurrentCommand>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cluster_execution_status: Option<ClusterExecutionStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub masters: Option<RoleStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workers: Option<RoleStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_servers: Option<RoleStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cluster_info: Option<ClusterInfo>,
+    /// The last autoscaling decision per worker role group
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub autoscaling: BTreeMap<String, AutoscalingStatus>,
+}
+
+/// The last autoscaling decision for a worker role group.
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoscalingStatus {
+    pub current_instances: u16,
+    pub desired_instances: u16,
+    /// Percentage of used worker cores at the time of the decision
+    pub core_utilization: usize,
+    /// Human readable reason of the decision
+    pub message: String,
+    pub last_scale_time: Option<String>,
+}
+
+/// State of the standalone cluster as reported by the master JSON API.
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterInfo {
+    /// Web UI address (<host>:<port>) of the master in state ALIVE
+    pub alive_master: Option<String>,
+    pub masters: Vec<MasterInfo>,
+    pub alive_workers: usize,
+    pub cores: usize,
+    pub cores_used: usize,
+    /// Total worker memory in MB
+    pub memory: usize,
+    /// Used worker memory in MB
+    pub memory_used: usize,
+    /// Names of all applications running on the alive master
+    pub running_applications: Vec<String>,
+    /// Names of worker pods that are ready but not registered at the alive master
+    pub unregistered_workers: Vec<String>,
+    /// Number of applications waiting for resources
+    #[serde(default)]
+    pub waiting_applications: usize,
+    /// Time of the last update (RFC3339). Changes of the used resources and applications
+    /// alone are written at most every 60 seconds.
+    pub last_update_time: Option<String>,
+}
+
+/// The state (e.g. ALIVE, STANDBY, UNREACHABLE) of one master.
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MasterInfo {
+    pub address: String,
+    pub state: String,
+}
+
+/// Number of ready and desired pods for one role (master, worker, history-server).
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleStatus {
+    pub ready: usize,
+    pub desired: usize,
+    /// Ready and desired pods in the format <ready>/<desired> for printer columns
+    pub summary: String,
+    /// Label selector of all pods of the role (used by the scale subresource)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+}
+
+impl RoleStatus {
+    pub fn new(ready: usize, desired: usize) -> Self {
+        RoleStatus {
+            ready,
+            desired,
+            summary: format!("{}/{}", ready, desired),
+            selector: None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
 pub enum ClusterExecutionStatus {
     Stopped,
     Running,
+    /// Pods keep running but are neither created nor deleted until the cluster is resumed
+    Paused,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
@@ -386,6 +1403,9 @@ pub struct CurrentCommand {
     pub command_ref: String,
     pub command_type: String,
     pub started_at: String,
+    /// Number of times the command was executed again after a timeout
+    #[serde(default)]
+    pub retries: u16,
 }
 
 impl Crd for SparkCluster {
@@ -424,6 +1444,15 @@ pub enum SparkVersion {
 }
 
 impl SparkVersion {
+    /// Returns whether this version supports the graceful decommission of workers
+    /// (`spark.decommission.enabled`, available since Spark 3.1).
+    pub fn supports_decommission(&self) -> bool {
+        match self {
+            SparkVersion::v2_4_7 | SparkVersion::v3_0_1 | SparkVersion::v3_0_2 => false,
+            SparkVersion::v3_1_1 => true,
+        }
+    }
+
     pub fn is_upgrade(&self, to: &Self) -> Result<bool, SemVerError> {
         let from_version = Version::parse(&self.to_string())?;
         let to_version = Version::parse(&to.to_string())?;
@@ -438,14 +1467,44 @@ impl SparkVersion {
 }
 
 /// Filter all existing pods for master node type and retrieve the selector config
-/// for the given role_group. Extract the nodeName from the pod and the specified port
-/// from the config to create the master urls for each pod.
+/// for the given role_group. Extract the host (node name or pod IP depending on the runtime)
+/// from the pod and the specified port from the config to create the master urls for each pod.
 ///
 /// # Arguments
 /// * `pods` - Slice of all existing pods
 /// * `spec` - The spark cluster spec
 ///
 pub fn get_master_urls(pods: &[Pod], spec: &SparkClusterSpec) -> Vec<String> {
+    get_master_addresses(pods, spec, get_master_port)
+}
+
+/// Filter all existing pods for master node type and create the web ui urls
+/// (<host>:<web_ui_port>) for each pod. The web ui offers the master JSON API.
+///
+/// # Arguments
+/// * `pods` - Slice of all existing pods
+/// * `spec` - The spark cluster spec
+///
+pub fn get_master_web_ui_urls(pods: &[Pod], spec: &SparkClusterSpec) -> Vec<String> {
+    get_master_addresses(pods, spec, get_master_web_ui_port)
+}
+
+/// Create an address in format <host>:<port> for every master pod. The port is
+/// retrieved from the selector config via `get_port`. The host depends on the runtime:
+/// the stackable agent runs the masters in the host network of their node, so the node name
+/// is used. On the kubernetes runtime the masters are only reachable via their pod IP;
+/// masters without a pod IP yet are skipped.
+///
+/// # Arguments
+/// * `pods` - Slice of all existing pods
+/// * `spec` - The spark cluster spec
+/// * `get_port` - Extracts the port from the selector config
+///
+fn get_master_addresses(
+    pods: &[Pod],
+    spec: &SparkClusterSpec,
+    get_port: fn(Box<dyn Config>, &SparkClusterSpec) -> String,
+) -> Vec<String> {
     let mut master_urls = Vec::new();
 
     for pod in pods {
@@ -462,10 +1521,18 @@ pub fn get_master_urls(pods: &[Pod], spec: &SparkClusterSpec) -> Vec<String> {
                     spec.get_config(&SparkNodeType::Master, role_group),
                     &pod.spec,
                 ) {
-                    let port = get_master_port(config, spec);
-
-                    if let Some(node_name) = &pod_spec.node_name {
-                        master_urls.push(create_master_url(node_name, &port))
+                    let port = get_port(config, spec);
+
+                    let host = match spec.get_runtime() {
+                        Runtime::StackableAgent => pod_spec.node_name.as_ref(),
+                        Runtime::Kubernetes => pod
+                            .status
+                            .as_ref()
+                            .and_then(|status| status.pod_ip.as_ref()),
+                    };
+
+                    if let Some(host) = host {
+                        master_urls.push(create_master_url(host, &port))
                     }
                 }
             }
@@ -475,40 +1542,46 @@ pub fn get_master_urls(pods: &[Pod], spec: &SparkClusterSpec) -> Vec<String> {
     master_urls
 }
 
-/// Search for the selected master port in the master config
-/// Priority is: spark_defaults.conf > spark_env.sh > default port
+/// Search for the selected master port in the master config. The master port is managed, so
+/// it is only written to spark_env.sh from the masterPort field (default port otherwise).
 ///
 /// # Arguments
 /// * `config` - The custom resource config of the specified master
 /// * `spec` - The spark cluster spec
 ///
 fn get_master_port(config: Box<dyn Config>, spec: &SparkClusterSpec) -> String {
-    return if let Some(spark_defaults_port) = config
-        .get_spark_defaults_conf(spec)
-        .get(SPARK_DEFAULTS_MASTER_PORT)
-    {
-        spark_defaults_port.clone()
-    } else if let Some(spark_env_port) = config.get_spark_env_sh().get(SPARK_ENV_MASTER_PORT) {
-        spark_env_port.clone()
-    } else {
+    config
+        .get_spark_env_sh(spec)
+        .get(SPARK_ENV_MASTER_PORT)
+        .cloned()
         // TODO: extract default / recommended from product config
-        "7077".to_string()
-    };
+        .unwrap_or_else(|| "7077".to_string())
 }
 
-/// Create master url in format: <node_name>:<port>
+/// Search for the selected master web ui port in the master config
 ///
 /// # Arguments
-/// * `node_name` - Master node_name / host name
+/// * `config` - The custom resource config of the specified master
+/// * `spec` - The spark cluster spec
+///
+fn get_master_web_ui_port(config: Box<dyn Config>, spec: &SparkClusterSpec) -> String {
+    config.get_web_ui_port(spec)
+}
+
+/// Create master url in format: <host>:<port>
+///
+/// # Arguments
+/// * `host` - Master node name or pod IP
 /// * `port` - Port on which the master is running
 ///
-fn create_master_url(node_name: &str, port: &str) -> String {
-    format!("{}:{}", node_name, port)
+fn create_master_url(host: &str, port: &str) -> String {
+    format!("{}:{}", host, port)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use k8s_openapi::api::core::v1::PodStatus;
     use stackable_spark_common::constants::SPARK_DEFAULTS_MASTER_PORT;
     use stackable_spark_test_utils::cluster::{Data, TestSparkCluster};
 
@@ -527,10 +1600,8 @@ mod tests {
 
         let spark_defaults = master_1_config.get_spark_defaults_conf(&spark_cluster.spec);
 
-        assert_eq!(
-            spark_defaults.get(SPARK_DEFAULTS_MASTER_PORT),
-            Some(&TestSparkCluster::MASTER_1_CONFIG_PORT.to_string())
-        );
+        // the override of the managed master port is rejected and not written
+        assert_eq!(spark_defaults.get(SPARK_DEFAULTS_MASTER_PORT), None);
 
         assert_eq!(
             spark_defaults.get(SPARK_DEFAULTS_EVENT_LOG_DIR),
@@ -561,11 +1632,12 @@ mod tests {
             )
             .unwrap();
 
-        let spark_env = master_1_config.get_spark_env_sh();
+        let spark_env = master_1_config.get_spark_env_sh(&spark_cluster.spec);
 
+        // the override of the managed master port is rejected, the dedicated field is used
         assert_eq!(
             spark_env.get(SPARK_ENV_MASTER_PORT),
-            Some(&TestSparkCluster::MASTER_1_ENV_PORT.to_string())
+            Some(&TestSparkCluster::MASTER_1_PORT.to_string())
         );
 
         assert_eq!(
@@ -587,7 +1659,7 @@ mod tests {
             )
             .unwrap();
 
-        let spark_env = master_1_config.get_spark_env_sh();
+        let spark_env = master_1_config.get_spark_env_sh(&spark_cluster.spec);
 
         assert_eq!(
             spark_env.get(SPARK_ENV_WORKER_PORT),
@@ -601,7 +1673,7 @@ mod tests {
 
         assert_eq!(
             spark_env.get(SPARK_ENV_WORKER_MEMORY),
-            Some(&TestSparkCluster::WORKER_1_ENV_MEMORY.to_string())
+            Some(&TestSparkCluster::WORKER_1_MEMORY.to_string())
         );
 
         assert_eq!(
@@ -617,13 +1689,280 @@ mod tests {
         let version = &spark_cluster.spec.version;
 
         assert_eq!(
-            SparkNodeType::Master.get_command(&version.to_string()),
+            SparkNodeType::Master.get_command(&version.to_string(), &Runtime::StackableAgent),
             format!(
                 "spark-{}-bin-hadoop2.7/sbin/start-{}.sh",
                 &version.to_string(),
                 SparkNodeType::Master.to_string()
             )
         );
+
+        assert_eq!(
+            SparkNodeType::Worker.get_command(&version.to_string(), &Runtime::Kubernetes),
+            format!("{}/sbin/start-slave.sh", SPARK_IMAGE_HOME)
+        );
+    }
+
+    #[test]
+    fn test_runtime() {
+        let spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        assert_eq!(spark_cluster.spec.get_runtime(), Runtime::StackableAgent);
+
+        assert_eq!(
+            Runtime::StackableAgent.get_image_name(&SparkVersion::v3_0_1),
+            "spark:3.0.1"
+        );
+        assert_eq!(
+            Runtime::Kubernetes.get_image_name(&SparkVersion::v3_0_1),
+            format!("{}:3.0.1", SPARK_IMAGE_REPOSITORY)
+        );
+        assert!(Runtime::Kubernetes.get_config_dir().starts_with('/'));
+    }
+
+    #[test]
+    fn test_has_shared_event_log() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        assert!(spark_cluster.spec.has_shared_event_log());
+
+        // every pod has its own event log directory
+        spark_cluster.spec.runtime = Some(Runtime::Kubernetes);
+        assert!(!spark_cluster.spec.has_shared_event_log());
+
+        spark_cluster.spec.event_log_volume = Some(EventLogVolume {
+            persistent_volume_claim: Some("spark-events".to_string()),
+            host_path: None,
+        });
+        assert!(spark_cluster.spec.has_shared_event_log());
+
+        spark_cluster.spec.event_log_volume = Some(EventLogVolume {
+            persistent_volume_claim: Some("spark-events".to_string()),
+            host_path: Some("/data/spark-events".to_string()),
+        });
+        assert!(!spark_cluster.spec.has_shared_event_log());
+
+        // the volume is mounted at the log dir
+        spark_cluster.spec.event_log_volume = Some(EventLogVolume {
+            persistent_volume_claim: Some("spark-events".to_string()),
+            host_path: None,
+        });
+        spark_cluster.spec.log_dir = None;
+        assert!(!spark_cluster.spec.has_shared_event_log());
+    }
+
+    #[test]
+    fn test_get_log4j_properties_log_dir() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        let config = MasterConfig {
+            logging: Some(LoggingConfig {
+                appender: Some(logging::LogAppender::RollingFile),
+                ..LoggingConfig::default()
+            }),
+            ..MasterConfig::default()
+        };
+
+        // the package of the stackable agent
+        assert!(config
+            .get_log4j_properties(&spark_cluster.spec)
+            .contains(&format!(
+            "log4j.appender.file.File={{{{packageroot}}}}/spark-{}-bin-hadoop2.7/logs/spark.log",
+            spark_cluster.spec.version
+        )));
+
+        // the Spark image
+        spark_cluster.spec.runtime = Some(Runtime::Kubernetes);
+        assert!(config
+            .get_log4j_properties(&spark_cluster.spec)
+            .contains("log4j.appender.file.File=/stackable/spark/logs/spark.log"));
+    }
+
+    #[test]
+    fn test_supports_graceful_decommission() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        let worker_decommission = |spark_cluster: &SparkCluster| {
+            spark_cluster
+                .spec
+                .get_config(
+                    &SparkNodeType::Worker,
+                    TestSparkCluster::WORKER_1_ROLE_GROUP,
+                )
+                .unwrap()
+                .get_spark_defaults_conf(&spark_cluster.spec)
+                .get(SPARK_DEFAULTS_DECOMMISSION_ENABLED)
+                .cloned()
+        };
+
+        spark_cluster.spec.version = SparkVersion::v3_1_1;
+        assert!(!spark_cluster.spec.supports_graceful_decommission());
+        assert_eq!(worker_decommission(&spark_cluster), None);
+
+        spark_cluster.spec.runtime = Some(Runtime::Kubernetes);
+        assert!(spark_cluster.spec.supports_graceful_decommission());
+        assert_eq!(
+            worker_decommission(&spark_cluster),
+            Some("true".to_string())
+        );
+
+        spark_cluster.spec.version = SparkVersion::v3_0_2;
+        assert!(!spark_cluster.spec.supports_graceful_decommission());
+        assert_eq!(worker_decommission(&spark_cluster), None);
+
+        assert_eq!(
+            spark_cluster
+                .spec
+                .get_decommission_timeout(TestSparkCluster::WORKER_1_ROLE_GROUP),
+            Duration::from_secs(DEFAULT_DECOMMISSION_TIMEOUT_SECONDS)
+        );
+    }
+
+    fn config_option(name: &str, value: &str) -> ConfigOption {
+        ConfigOption {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_config_layering() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.spec.config = Some(CommonConfig {
+            spark_defaults: Some(vec![
+                config_option("spark.network.timeout", "100s"),
+                config_option("spark.ssl.enabled", "true"),
+                config_option("spark.deploy.spreadOut", "false"),
+            ]),
+            spark_env_sh: Some(vec![
+                config_option("SPARK_DAEMON_MEMORY", "1g"),
+                config_option("SPARK_LOCAL_DIRS", "/tmp/cluster"),
+            ]),
+        });
+        spark_cluster.spec.masters.config = Some(MasterConfig {
+            master_web_ui_port: Some(9090),
+            spark_defaults: Some(vec![
+                config_option("spark.network.timeout", "200s"),
+                config_option("spark.deploy.spreadOut", "true"),
+            ]),
+            spark_env_sh: Some(vec![config_option("SPARK_DAEMON_MEMORY", "2g")]),
+            ..MasterConfig::default()
+        });
+        let role_group_config = spark_cluster
+            .spec
+            .masters
+            .selectors
+            .get_mut(TestSparkCluster::MASTER_2_ROLE_GROUP)
+            .unwrap();
+        role_group_config.config = Some(MasterConfig {
+            master_port: Some(7079),
+            spark_defaults: Some(vec![config_option("spark.network.timeout", "300s")]),
+            ..MasterConfig::default()
+        });
+
+        let spec = &spark_cluster.spec;
+
+        // role group with config
+        let config = spec
+            .get_config(
+                &SparkNodeType::Master,
+                TestSparkCluster::MASTER_2_ROLE_GROUP,
+            )
+            .unwrap();
+        let spark_defaults = config.get_spark_defaults_conf(spec);
+        let spark_env = config.get_spark_env_sh(spec);
+        // cluster level only
+        assert_eq!(
+            spark_defaults.get("spark.ssl.enabled"),
+            Some(&"true".to_string())
+        );
+        assert_eq!(
+            spark_env.get("SPARK_LOCAL_DIRS"),
+            Some(&"/tmp/cluster".to_string())
+        );
+        // role level overrides cluster level
+        assert_eq!(
+            spark_defaults.get("spark.deploy.spreadOut"),
+            Some(&"true".to_string())
+        );
+        assert_eq!(
+            spark_env.get("SPARK_DAEMON_MEMORY"),
+            Some(&"2g".to_string())
+        );
+        assert_eq!(
+            spark_env.get(SPARK_ENV_MASTER_WEBUI_PORT),
+            Some(&"9090".to_string())
+        );
+        // role group level overrides role level
+        assert_eq!(
+            spark_defaults.get("spark.network.timeout"),
+            Some(&"300s".to_string())
+        );
+        assert_eq!(
+            spark_env.get(SPARK_ENV_MASTER_PORT),
+            Some(&"7079".to_string())
+        );
+
+        // role group without config only gets the cluster and role config
+        let config = spec
+            .get_config(
+                &SparkNodeType::Master,
+                TestSparkCluster::MASTER_3_ROLE_GROUP,
+            )
+            .unwrap();
+        let spark_defaults = config.get_spark_defaults_conf(spec);
+        assert_eq!(
+            spark_defaults.get("spark.network.timeout"),
+            Some(&"200s".to_string())
+        );
+        assert_eq!(
+            config.get_spark_env_sh(spec).get(SPARK_ENV_MASTER_PORT),
+            None
+        );
+
+        // other roles only get the cluster config
+        let config = spec
+            .get_config(
+                &SparkNodeType::Worker,
+                TestSparkCluster::WORKER_2_ROLE_GROUP,
+            )
+            .unwrap();
+        let spark_defaults = config.get_spark_defaults_conf(spec);
+        assert_eq!(
+            spark_defaults.get("spark.network.timeout"),
+            Some(&"100s".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_instances() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        assert_eq!(
+            spark_cluster.spec.get_instances(
+                &SparkNodeType::Worker,
+                TestSparkCluster::WORKER_1_ROLE_GROUP
+            ),
+            Some(1)
+        );
+
+        // replicas are distributed over the role groups "1core1g" and "2core3g"
+        spark_cluster.spec.workers.replicas = Some(5);
+        assert_eq!(
+            spark_cluster.spec.get_instances(
+                &SparkNodeType::Worker,
+                TestSparkCluster::WORKER_1_ROLE_GROUP
+            ),
+            Some(3)
+        );
+        assert_eq!(
+            spark_cluster.spec.get_instances(
+                &SparkNodeType::Worker,
+                TestSparkCluster::WORKER_2_ROLE_GROUP
+            ),
+            Some(2)
+        );
+        assert_eq!(
+            spark_cluster
+                .spec
+                .get_instances(&SparkNodeType::Worker, "unknown"),
+            None
+        );
     }
 
     #[test]
@@ -657,12 +1996,12 @@ mod tests {
         let master_pods = stackable_spark_test_utils::create_master_pods();
         let master_urls = get_master_urls(master_pods.as_slice(), &spark_cluster.spec);
         assert!(!master_urls.is_empty());
-        // For master_1 we expect the config port
+        // For master_1 we expect the field port, the config and env overrides are rejected
         assert!(master_urls.contains(&create_master_url(
             TestSparkCluster::MASTER_1_NODE_NAME,
-            &TestSparkCluster::MASTER_1_CONFIG_PORT.to_string()
+            &TestSparkCluster::MASTER_1_PORT.to_string()
         )));
-        // For master_2 we expect the env port
+        // For master_2 we expect the masterPort field port
         assert!(master_urls.contains(&create_master_url(
             TestSparkCluster::MASTER_2_NODE_NAME,
             &TestSparkCluster::MASTER_2_PORT.to_string()
@@ -674,6 +2013,74 @@ mod tests {
         )));
     }
 
+    #[test]
+    fn test_get_master_urls_kubernetes_runtime() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.spec.runtime = Some(Runtime::Kubernetes);
+
+        let mut master_pods = stackable_spark_test_utils::create_master_pods();
+        // the first master has no pod IP yet and must be skipped
+        for (index, pod) in master_pods.iter_mut().enumerate().skip(1) {
+            pod.status = Some(PodStatus {
+                pod_ip: Some(format!("10.0.0.{}", index)),
+                ..PodStatus::default()
+            });
+        }
+
+        let master_urls = get_master_urls(master_pods.as_slice(), &spark_cluster.spec);
+        assert_eq!(
+            master_urls,
+            vec![
+                create_master_url("10.0.0.1", &TestSparkCluster::MASTER_2_PORT.to_string()),
+                create_master_url(
+                    "10.0.0.2",
+                    &stackable_spark_test_utils::MASTER_DEFAULT_PORT.to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_master_web_ui_urls() {
+        let spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+
+        let master_pods = stackable_spark_test_utils::create_master_pods();
+        let web_ui_urls = get_master_web_ui_urls(master_pods.as_slice(), &spark_cluster.spec);
+        assert_eq!(web_ui_urls.len(), 3);
+        // For master_1 we expect the masterWebUiPort field port
+        assert!(web_ui_urls.contains(&create_master_url(
+            TestSparkCluster::MASTER_1_NODE_NAME,
+            &TestSparkCluster::MASTER_1_WEB_UI_PORT.to_string()
+        )));
+        // For master_3 we expect the default web ui port
+        assert!(web_ui_urls.contains(&create_master_url(
+            TestSparkCluster::MASTER_3_NODE_NAME,
+            "8080"
+        )));
+    }
+
+    #[test]
+    fn test_get_worker_web_ui_port() {
+        let spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+
+        let worker_config = spark_cluster
+            .spec
+            .get_config(
+                &SparkNodeType::Worker,
+                TestSparkCluster::WORKER_1_ROLE_GROUP,
+            )
+            .unwrap();
+        assert_eq!(
+            worker_config.get_web_ui_port(&spark_cluster.spec),
+            TestSparkCluster::WORKER_1_WEBUI_PORT.to_string()
+        );
+
+        assert_eq!(
+            WorkerConfig::default().get_web_ui_port(&spark_cluster.spec),
+            DEFAULT_WORKER_WEB_UI_PORT
+        );
+    }
+
     #[test]
     fn test_create_master_url() {
         assert_eq!(
@@ -688,4 +2095,66 @@ mod tests {
             )
         );
     }
+
+    fn env_var(name: &str, value: &str) -> EnvVar {
+        EnvVar {
+            name: name.to_string(),
+            value: Some(value.to_string()),
+            ..EnvVar::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_chained() {
+        let role_options = Some(vec![config_option("a", "role"), config_option("b", "role")]);
+        let role_group_options = Some(vec![config_option("b", "role_group")]);
+        assert_eq!(
+            merge_chained(&role_options, &role_group_options),
+            Some(vec![
+                config_option("a", "role"),
+                config_option("b", "role"),
+                config_option("b", "role_group")
+            ])
+        );
+        assert_eq!(
+            merge_chained(&None, &role_group_options),
+            role_group_options
+        );
+        assert_eq!(merge_chained::<Vec<ConfigOption>>(&None, &None), None);
+
+        // the role group wins for maps
+        let mut role_properties = BTreeMap::new();
+        role_properties.insert("a".to_string(), "role".to_string());
+        role_properties.insert("b".to_string(), "role".to_string());
+        let mut role_group_properties = BTreeMap::new();
+        role_group_properties.insert("b".to_string(), "role_group".to_string());
+        let merged = merge_chained(&Some(role_properties), &Some(role_group_properties)).unwrap();
+        assert_eq!(merged.get("a"), Some(&"role".to_string()));
+        assert_eq!(merged.get("b"), Some(&"role_group".to_string()));
+    }
+
+    #[test]
+    fn test_merge_env() {
+        let role_config = WorkerConfig {
+            env: Some(vec![env_var("A", "role"), env_var("B", "role")]),
+            ..WorkerConfig::default()
+        };
+        let role_group_config = WorkerConfig {
+            env: Some(vec![env_var("B", "role_group"), env_var("C", "role_group")]),
+            ..WorkerConfig::default()
+        };
+
+        assert_eq!(
+            role_group_config.merge(&role_config).env,
+            Some(vec![
+                env_var("A", "role"),
+                env_var("B", "role_group"),
+                env_var("C", "role_group")
+            ])
+        );
+        assert_eq!(
+            WorkerConfig::default().merge(&WorkerConfig::default()).env,
+            None
+        );
+    }
 }
@@ -0,0 +1,196 @@
+//! This module provides the logging configuration of the Spark daemons and renders it into
+//! the log4j (1.x) properties file used by all supported Spark versions.
+use crate::merge_chained;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Name of the log file of the RollingFile appender in the Spark log directory
+const DEFAULT_LOG_FILE_NAME: &str = "spark.log";
+const DEFAULT_MAX_FILE_SIZE: &str = "10MB";
+const DEFAULT_MAX_BACKUP_INDEX: u16 = 5;
+const LOG_PATTERN: &str = "%d{yy/MM/dd HH:mm:ss} %p %c{1}: %m%n";
+
+/// Logging of the Spark daemons of a role (group)
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggingConfig {
+    /// Level of the root logger (default: INFO)
+    pub root_level: Option<LogLevel>,
+    /// Levels of single loggers (e.g. org.apache.spark.deploy: DEBUG)
+    pub loggers: Option<BTreeMap<String, LogLevel>>,
+    /// Where the logs are written to (default: Console)
+    pub appender: Option<LogAppender>,
+    /// Absolute path of the log file of the RollingFile appender
+    /// (default: spark.log in the Spark log directory of the runtime)
+    pub file: Option<String>,
+    /// Size of a log file before it is rolled over (default: 10MB)
+    pub max_file_size: Option<String>,
+    /// Number of rolled over log files that are kept (default: 5)
+    pub max_backup_index: Option<u16>,
+}
+
+impl LoggingConfig {
+    /// Merge this role group logging config with the role logging config. Fields set on the
+    /// role group take precedence, the logger levels of both levels are combined.
+    ///
+    /// # Arguments
+    /// * `role_logging` - The logging config of the role
+    ///
+    pub fn merge(&self, role_logging: &Self) -> Self {
+        LoggingConfig {
+            root_level: self
+                .root_level
+                .clone()
+                .or_else(|| role_logging.root_level.clone()),
+            loggers: merge_chained(&role_logging.loggers, &self.loggers),
+            appender: self
+                .appender
+                .clone()
+                .or_else(|| role_logging.appender.clone()),
+            file: self.file.clone().or_else(|| role_logging.file.clone()),
+            max_file_size: self
+                .max_file_size
+                .clone()
+                .or_else(|| role_logging.max_file_size.clone()),
+            max_backup_index: self.max_backup_index.or(role_logging.max_backup_index),
+        }
+    }
+}
+
+#[derive(
+    Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize, strum_macros::Display,
+)]
+#[serde(rename_all = "UPPERCASE")]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+    Off,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum LogAppender {
+    /// Write to stderr of the container / process
+    Console,
+    /// Write to a file that is rolled over when it reaches the maximum size
+    RollingFile,
+}
+
+/// Render the logging config into the log4j properties file.
+///
+/// # Arguments
+/// * `logging` - The (merged) logging config of the role group
+/// * `log_dir` - The Spark log directory of the runtime for the default log file
+///
+pub fn render_log4j_properties(logging: &LoggingConfig, log_dir: &str) -> String {
+    let root_level = logging.root_level.clone().unwrap_or(LogLevel::Info);
+    let appender = logging.appender.clone().unwrap_or(LogAppender::Console);
+    let file = logging
+        .file
+        .clone()
+        .unwrap_or_else(|| format!("{}/{}", log_dir, DEFAULT_LOG_FILE_NAME));
+    let max_file_size = logging
+        .max_file_size
+        .as_deref()
+        .unwrap_or(DEFAULT_MAX_FILE_SIZE);
+    let max_backup_index = logging.max_backup_index.unwrap_or(DEFAULT_MAX_BACKUP_INDEX);
+    let loggers = logging.loggers.clone().unwrap_or_default();
+
+    let mut lines = vec![];
+
+    match appender {
+        LogAppender::Console => {
+            lines.push(format!("log4j.rootLogger={}, console", root_level));
+            lines.push("log4j.appender.console=org.apache.log4j.ConsoleAppender".to_string());
+            lines.push("log4j.appender.console.target=System.err".to_string());
+            lines.push("log4j.appender.console.layout=org.apache.log4j.PatternLayout".to_string());
+            lines.push(format!(
+                "log4j.appender.console.layout.ConversionPattern={}",
+                LOG_PATTERN
+            ));
+        }
+        LogAppender::RollingFile => {
+            lines.push(format!("log4j.rootLogger={}, file", root_level));
+            lines.push("log4j.appender.file=org.apache.log4j.RollingFileAppender".to_string());
+            lines.push(format!("log4j.appender.file.File={}", file));
+            lines.push(format!("log4j.appender.file.MaxFileSize={}", max_file_size));
+            lines.push(format!(
+                "log4j.appender.file.MaxBackupIndex={}",
+                max_backup_index
+            ));
+            lines.push("log4j.appender.file.layout=org.apache.log4j.PatternLayout".to_string());
+            lines.push(format!(
+                "log4j.appender.file.layout.ConversionPattern={}",
+                LOG_PATTERN
+            ));
+        }
+    }
+
+    for (name, level) in &loggers {
+        lines.push(format!("log4j.logger.{}={}", name, level));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_log4j_properties() {
+        let mut loggers = BTreeMap::new();
+        loggers.insert("org.apache.spark.deploy".to_string(), LogLevel::Debug);
+        let logging = LoggingConfig {
+            root_level: Some(LogLevel::Warn),
+            loggers: Some(loggers),
+            appender: Some(LogAppender::RollingFile),
+            max_file_size: Some("50MB".to_string()),
+            ..LoggingConfig::default()
+        };
+
+        let log4j = render_log4j_properties(&logging, "/stackable/spark/logs");
+        assert!(log4j.contains("log4j.rootLogger=WARN, file"));
+        assert!(log4j.contains("log4j.appender.file.File=/stackable/spark/logs/spark.log"));
+        assert!(log4j.contains("log4j.appender.file.MaxFileSize=50MB"));
+        assert!(log4j.contains("log4j.appender.file.MaxBackupIndex=5"));
+        assert!(log4j.contains("log4j.logger.org.apache.spark.deploy=DEBUG"));
+
+        let default = render_log4j_properties(&LoggingConfig::default(), "/stackable/spark/logs");
+        assert!(default.contains("log4j.rootLogger=INFO, console"));
+    }
+
+    #[test]
+    fn test_merge_logging() {
+        let mut role_loggers = BTreeMap::new();
+        role_loggers.insert("a".to_string(), LogLevel::Debug);
+        role_loggers.insert("b".to_string(), LogLevel::Debug);
+        let role_logging = LoggingConfig {
+            root_level: Some(LogLevel::Warn),
+            loggers: Some(role_loggers),
+            appender: Some(LogAppender::RollingFile),
+            ..LoggingConfig::default()
+        };
+
+        let mut role_group_loggers = BTreeMap::new();
+        role_group_loggers.insert("b".to_string(), LogLevel::Error);
+        let role_group_logging = LoggingConfig {
+            root_level: Some(LogLevel::Info),
+            loggers: Some(role_group_loggers),
+            ..LoggingConfig::default()
+        };
+
+        let merged = role_group_logging.merge(&role_logging);
+        assert_eq!(merged.root_level, Some(LogLevel::Info));
+        assert_eq!(merged.appender, Some(LogAppender::RollingFile));
+
+        let loggers = merged.loggers.unwrap();
+        assert_eq!(loggers.get("a"), Some(&LogLevel::Debug));
+        assert_eq!(loggers.get("b"), Some(&LogLevel::Error));
+    }
+}
@@ -0,0 +1,146 @@
+//! This module provides the liveness and readiness probe settings of the Spark daemons. The
+//! probes check the web ui of the daemon via HTTP.
+use crate::merge_section;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Timings of a probe
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeTimings {
+    /// Seconds after the container started before the first probe
+    pub initial_delay_seconds: Option<i32>,
+    /// Seconds between two probes
+    pub period_seconds: Option<i32>,
+    /// Seconds after which a probe times out
+    pub timeout_seconds: Option<i32>,
+    /// Consecutive failed probes before the container is restarted (liveness) or not ready
+    /// anymore (readiness)
+    pub failure_threshold: Option<i32>,
+}
+
+impl ProbeTimings {
+    /// Merge these timings with the given timings. Fields set here take precedence.
+    ///
+    /// # Arguments
+    /// * `other` - The timings to fall back to
+    ///
+    pub fn merge(&self, other: &Self) -> Self {
+        ProbeTimings {
+            initial_delay_seconds: self.initial_delay_seconds.or(other.initial_delay_seconds),
+            period_seconds: self.period_seconds.or(other.period_seconds),
+            timeout_seconds: self.timeout_seconds.or(other.timeout_seconds),
+            failure_threshold: self.failure_threshold.or(other.failure_threshold),
+        }
+    }
+
+    /// Returns the default timings of the liveness probe
+    pub fn default_liveness() -> Self {
+        ProbeTimings {
+            initial_delay_seconds: Some(30),
+            period_seconds: Some(10),
+            timeout_seconds: Some(5),
+            failure_threshold: Some(6),
+        }
+    }
+
+    /// Returns the default timings of the readiness probe
+    pub fn default_readiness() -> Self {
+        ProbeTimings {
+            initial_delay_seconds: Some(10),
+            period_seconds: Some(10),
+            timeout_seconds: Some(5),
+            failure_threshold: Some(3),
+        }
+    }
+}
+
+/// Liveness and readiness probes of the Spark daemons (only applied for the kubernetes runtime)
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbesConfig {
+    /// Set to false to run the pods without probes (default: true)
+    pub enabled: Option<bool>,
+    /// Timings of the liveness probe (default: 30s delay, 10s period, 5s timeout, 6 failures)
+    pub liveness: Option<ProbeTimings>,
+    /// Timings of the readiness probe (default: 10s delay, 10s period, 5s timeout, 3 failures)
+    pub readiness: Option<ProbeTimings>,
+}
+
+impl ProbesConfig {
+    /// Merge this role group probes config with the role probes config. Fields set on the
+    /// role group take precedence.
+    ///
+    /// # Arguments
+    /// * `role_probes` - The probes config of the role
+    ///
+    pub fn merge(&self, role_probes: &Self) -> Self {
+        ProbesConfig {
+            enabled: self.enabled.or(role_probes.enabled),
+            liveness: merge_section(&role_probes.liveness, &self.liveness, ProbeTimings::merge),
+            readiness: merge_section(&role_probes.readiness, &self.readiness, ProbeTimings::merge),
+        }
+    }
+
+    /// Returns whether the probes are applied (default: true)
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// Returns the liveness timings, unset fields are filled with the defaults
+    pub fn get_liveness(&self) -> ProbeTimings {
+        self.liveness
+            .clone()
+            .unwrap_or_default()
+            .merge(&ProbeTimings::default_liveness())
+    }
+
+    /// Returns the readiness timings, unset fields are filled with the defaults
+    pub fn get_readiness(&self) -> ProbeTimings {
+        self.readiness
+            .clone()
+            .unwrap_or_default()
+            .merge(&ProbeTimings::default_readiness())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_probes() {
+        let role_probes = ProbesConfig {
+            enabled: Some(false),
+            liveness: Some(ProbeTimings {
+                period_seconds: Some(20),
+                failure_threshold: Some(10),
+                ..ProbeTimings::default()
+            }),
+            readiness: None,
+        };
+        let role_group_probes = ProbesConfig {
+            enabled: Some(true),
+            liveness: Some(ProbeTimings {
+                failure_threshold: Some(2),
+                ..ProbeTimings::default()
+            }),
+            readiness: None,
+        };
+
+        let merged = role_group_probes.merge(&role_probes);
+        assert!(merged.is_enabled());
+        assert_eq!(
+            merged.get_liveness(),
+            ProbeTimings {
+                initial_delay_seconds: Some(30),
+                period_seconds: Some(20),
+                timeout_seconds: Some(5),
+                failure_threshold: Some(2),
+            }
+        );
+        assert_eq!(merged.get_readiness(), ProbeTimings::default_readiness());
+
+        assert!(!ProbesConfig::default().merge(&role_probes).is_enabled());
+    }
+}
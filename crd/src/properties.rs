@@ -0,0 +1,802 @@
+//! This module provides a catalog of the known Spark configuration properties of
+//! spark-defaults.conf and spark-env.sh and the validation of user defined config options
+//! (`sparkDefaults` and `sparkEnvSh`) against it.
+//!
+//! Unknown properties and properties that are not used by a role or the Spark version are
+//! reported as warnings. Values that do not match the property type and overrides of
+//! properties managed by the operator (ports, secret, directories) are reported as errors
+//! and are not written to the config files (see `is_rejected`).
+//!
+//! Settings of the cluster spec which prevent a role from working (e.g. a history server
+//! without a shared event log) or are rejected by the operator (e.g. running as root) are
+//! reported as errors as well.
+use crate::{
+    ConfigOption, NodeGroup, RoleConfig, Runtime, SparkClusterSpec, SparkNodeType, SparkVersion,
+};
+use semver::Version;
+use stackable_spark_common::constants::*;
+use std::fmt;
+
+const ALL_ROLES: &[SparkNodeType] = &[
+    SparkNodeType::Master,
+    SparkNodeType::Worker,
+    SparkNodeType::HistoryServer,
+];
+const MASTER: &[SparkNodeType] = &[SparkNodeType::Master];
+const WORKER: &[SparkNodeType] = &[SparkNodeType::Worker];
+const HISTORY_SERVER: &[SparkNodeType] = &[SparkNodeType::HistoryServer];
+const MASTER_AND_WORKER: &[SparkNodeType] = &[SparkNodeType::Master, SparkNodeType::Worker];
+
+/// The file a property is written to
+#[derive(Clone, Debug, Eq, PartialEq, strum_macros::Display)]
+pub enum PropertyFile {
+    #[strum(serialize = "spark-defaults.conf")]
+    SparkDefaults,
+    #[strum(serialize = "spark-env.sh")]
+    SparkEnvSh,
+    /// The cluster spec itself, only used for issues and not part of the catalog
+    #[strum(serialize = "spec")]
+    Spec,
+}
+
+/// The type of a property value
+#[derive(Clone, Debug, Eq, PartialEq, strum_macros::Display)]
+pub enum PropertyType {
+    /// "true" or "false"
+    Boolean,
+    /// A non negative integer
+    Integer,
+    /// A port between 1 and 65535
+    Port,
+    /// A size with optional unit (e.g. 512m, 2g)
+    Memory,
+    /// A time span with optional unit (e.g. 500ms, 10s, 1h)
+    Duration,
+    /// Any value
+    String,
+}
+
+impl PropertyType {
+    /// Returns true if the value can be parsed as this type
+    ///
+    /// # Arguments
+    /// * `value` - The user defined value
+    ///
+    pub fn is_valid(&self, value: &str) -> bool {
+        let value = value.trim();
+        match self {
+            PropertyType::Boolean => value == "true" || value == "false",
+            PropertyType::Integer => value.parse::<u64>().is_ok(),
+            PropertyType::Port => matches!(value.parse::<u16>(), Ok(port) if port > 0),
+            PropertyType::Memory => has_number_with_unit(
+                value,
+                &["", "b", "k", "kb", "m", "mb", "g", "gb", "t", "tb"],
+            ),
+            PropertyType::Duration => {
+                has_number_with_unit(value, &["", "us", "ms", "s", "m", "min", "h", "d"])
+            }
+            PropertyType::String => true,
+        }
+    }
+}
+
+/// Returns true if the value consists of digits followed by one of the given units
+/// (case insensitive).
+fn has_number_with_unit(value: &str, units: &[&str]) -> bool {
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(unit_start);
+
+    !number.is_empty() && units.contains(&unit.to_lowercase().as_str())
+}
+
+/// A known Spark configuration property
+#[derive(Clone, Debug)]
+pub struct PropertyDefinition {
+    pub name: &'static str,
+    pub file: PropertyFile,
+    pub property_type: PropertyType,
+    /// The roles that use the property
+    pub roles: &'static [SparkNodeType],
+    /// The first Spark version that supports the property (None if supported by all)
+    pub since_version: Option<&'static str>,
+    /// The Spark default value (None if there is no default)
+    pub default: Option<&'static str>,
+    /// Whether the operator sets the property (from dedicated fields of the custom resource)
+    pub managed: bool,
+}
+
+impl PropertyDefinition {
+    const fn new(
+        name: &'static str,
+        file: PropertyFile,
+        property_type: PropertyType,
+        roles: &'static [SparkNodeType],
+    ) -> Self {
+        PropertyDefinition {
+            name,
+            file,
+            property_type,
+            roles,
+            since_version: None,
+            default: None,
+            managed: false,
+        }
+    }
+
+    const fn since(mut self, version: &'static str) -> Self {
+        self.since_version = Some(version);
+        self
+    }
+
+    const fn with_default(mut self, default: &'static str) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    const fn managed(mut self) -> Self {
+        self.managed = true;
+        self
+    }
+
+    /// Returns true if the property is supported by the given Spark version
+    ///
+    /// # Arguments
+    /// * `version` - The Spark version of the cluster
+    ///
+    pub fn is_supported_by(&self, version: &SparkVersion) -> bool {
+        match (
+            self.since_version.map(Version::parse),
+            Version::parse(&version.to_string()),
+        ) {
+            (Some(Ok(since)), Ok(version)) => version >= since,
+            _ => true,
+        }
+    }
+}
+
+/// Shorthand for a property of spark-defaults.conf
+const fn conf(
+    name: &'static str,
+    property_type: PropertyType,
+    roles: &'static [SparkNodeType],
+) -> PropertyDefinition {
+    PropertyDefinition::new(name, PropertyFile::SparkDefaults, property_type, roles)
+}
+
+/// Shorthand for a property of spark-env.sh
+const fn env(
+    name: &'static str,
+    property_type: PropertyType,
+    roles: &'static [SparkNodeType],
+) -> PropertyDefinition {
+    PropertyDefinition::new(name, PropertyFile::SparkEnvSh, property_type, roles)
+}
+
+/// All known properties
+pub const SPARK_PROPERTIES: &[PropertyDefinition] = &[
+    // spark-defaults.conf: common
+    conf(
+        SPARK_DEFAULTS_AUTHENTICATE,
+        PropertyType::Boolean,
+        ALL_ROLES,
+    )
+    .with_default("false"),
+    conf(
+        SPARK_DEFAULTS_AUTHENTICATE_SECRET,
+        PropertyType::String,
+        ALL_ROLES,
+    )
+    .managed(),
+    conf(
+        SPARK_DEFAULTS_PORT_MAX_RETRIES,
+        PropertyType::Integer,
+        ALL_ROLES,
+    )
+    .with_default("16")
+    .managed(),
+    conf("spark.network.timeout", PropertyType::Duration, ALL_ROLES).with_default("120s"),
+    conf("spark.ssl.enabled", PropertyType::Boolean, ALL_ROLES).with_default("false"),
+    conf(
+        SPARK_DEFAULTS_EVENT_LOG_ENABLED,
+        PropertyType::Boolean,
+        ALL_ROLES,
+    )
+    .with_default("false"),
+    conf(
+        SPARK_DEFAULTS_EVENT_LOG_DIR,
+        PropertyType::String,
+        MASTER_AND_WORKER,
+    )
+    .managed(),
+    conf(
+        "spark.eventLog.compress",
+        PropertyType::Boolean,
+        MASTER_AND_WORKER,
+    )
+    .with_default("false"),
+    conf(
+        "spark.eventLog.rolling.enabled",
+        PropertyType::Boolean,
+        MASTER_AND_WORKER,
+    )
+    .since("3.0.0")
+    .with_default("false"),
+    conf(
+        "spark.eventLog.rolling.maxFileSize",
+        PropertyType::Memory,
+        MASTER_AND_WORKER,
+    )
+    .since("3.0.0")
+    .with_default("128m"),
+    conf(
+        "spark.ui.reverseProxy",
+        PropertyType::Boolean,
+        MASTER_AND_WORKER,
+    )
+    .with_default("false"),
+    conf(
+        "spark.ui.reverseProxyUrl",
+        PropertyType::String,
+        MASTER_AND_WORKER,
+    ),
+    // spark-defaults.conf: master
+    conf(SPARK_DEFAULTS_MASTER_PORT, PropertyType::Port, MASTER)
+        .with_default("7077")
+        .managed(),
+    conf(SPARK_DEFAULTS_MASTER_WEBUI_PORT, PropertyType::Port, MASTER)
+        .with_default("8080")
+        .managed(),
+    conf("spark.master.rest.enabled", PropertyType::Boolean, MASTER).with_default("false"),
+    conf("spark.master.rest.port", PropertyType::Port, MASTER).with_default("6066"),
+    conf("spark.deploy.recoveryMode", PropertyType::String, MASTER).with_default("NONE"),
+    conf("spark.deploy.zookeeper.url", PropertyType::String, MASTER),
+    conf("spark.deploy.zookeeper.dir", PropertyType::String, MASTER),
+    conf("spark.deploy.defaultCores", PropertyType::Integer, MASTER),
+    conf("spark.deploy.spreadOut", PropertyType::Boolean, MASTER).with_default("true"),
+    conf(
+        "spark.deploy.retainedApplications",
+        PropertyType::Integer,
+        MASTER,
+    )
+    .with_default("200"),
+    conf("spark.worker.timeout", PropertyType::Integer, MASTER).with_default("60"),
+    // spark-defaults.conf: worker
+    conf(SPARK_DEFAULTS_WORKER_WEBUI_PORT, PropertyType::Port, WORKER)
+        .with_default("8081")
+        .managed(),
+    conf(
+        "spark.worker.cleanup.enabled",
+        PropertyType::Boolean,
+        WORKER,
+    )
+    .with_default("false"),
+    conf(
+        "spark.worker.cleanup.interval",
+        PropertyType::Integer,
+        WORKER,
+    )
+    .with_default("1800"),
+    conf(
+        "spark.worker.cleanup.appDataTtl",
+        PropertyType::Integer,
+        WORKER,
+    )
+    .with_default("604800"),
+    conf(
+        SPARK_DEFAULTS_DECOMMISSION_ENABLED,
+        PropertyType::Boolean,
+        WORKER,
+    )
+    .since("3.1.0")
+    .with_default("false")
+    .managed(),
+    // spark-defaults.conf: history server
+    conf(
+        SPARK_DEFAULTS_HISTORY_FS_LOG_DIRECTORY,
+        PropertyType::String,
+        HISTORY_SERVER,
+    )
+    .managed(),
+    conf(
+        SPARK_DEFAULTS_HISTORY_STORE_PATH,
+        PropertyType::String,
+        HISTORY_SERVER,
+    )
+    .managed(),
+    conf(
+        SPARK_DEFAULTS_HISTORY_WEBUI_PORT,
+        PropertyType::Port,
+        HISTORY_SERVER,
+    )
+    .with_default("18080")
+    .managed(),
+    conf(
+        "spark.history.fs.update.interval",
+        PropertyType::Duration,
+        HISTORY_SERVER,
+    )
+    .with_default("10s"),
+    conf(
+        "spark.history.fs.cleaner.enabled",
+        PropertyType::Boolean,
+        HISTORY_SERVER,
+    )
+    .with_default("false"),
+    conf(
+        "spark.history.fs.cleaner.interval",
+        PropertyType::Duration,
+        HISTORY_SERVER,
+    )
+    .with_default("1d"),
+    conf(
+        "spark.history.fs.cleaner.maxAge",
+        PropertyType::Duration,
+        HISTORY_SERVER,
+    )
+    .with_default("7d"),
+    conf(
+        "spark.history.fs.driverlog.cleaner.enabled",
+        PropertyType::Boolean,
+        HISTORY_SERVER,
+    )
+    .since("3.0.0"),
+    conf(
+        "spark.history.retainedApplications",
+        PropertyType::Integer,
+        HISTORY_SERVER,
+    )
+    .with_default("50"),
+    conf(
+        "spark.history.store.maxDiskUsage",
+        PropertyType::Memory,
+        HISTORY_SERVER,
+    )
+    .with_default("10g"),
+    // spark-env.sh: common
+    env(SPARK_NO_DAEMONIZE, PropertyType::Boolean, ALL_ROLES).managed(),
+    env(SPARK_CONF_DIR, PropertyType::String, ALL_ROLES).managed(),
+    env("SPARK_DAEMON_MEMORY", PropertyType::Memory, ALL_ROLES).with_default("1g"),
+    env("SPARK_DAEMON_JAVA_OPTS", PropertyType::String, ALL_ROLES),
+    env("SPARK_LOCAL_DIRS", PropertyType::String, ALL_ROLES),
+    env("SPARK_PUBLIC_DNS", PropertyType::String, ALL_ROLES),
+    // spark-env.sh: master
+    env(SPARK_ENV_MASTER_HOST, PropertyType::String, MASTER),
+    env(SPARK_ENV_MASTER_PORT, PropertyType::Port, MASTER)
+        .with_default("7077")
+        .managed(),
+    env(SPARK_ENV_MASTER_WEBUI_PORT, PropertyType::Port, MASTER)
+        .with_default("8080")
+        .managed(),
+    env("SPARK_MASTER_OPTS", PropertyType::String, MASTER),
+    // spark-env.sh: worker
+    env(SPARK_ENV_WORKER_CORES, PropertyType::Integer, WORKER).managed(),
+    env(SPARK_ENV_WORKER_MEMORY, PropertyType::Memory, WORKER).managed(),
+    env(SPARK_ENV_WORKER_PORT, PropertyType::Port, WORKER).managed(),
+    env(SPARK_ENV_WORKER_WEBUI_PORT, PropertyType::Port, WORKER)
+        .with_default("8081")
+        .managed(),
+    env("SPARK_WORKER_DIR", PropertyType::String, WORKER),
+    env("SPARK_WORKER_OPTS", PropertyType::String, WORKER),
+    // spark-env.sh: history server
+    env("SPARK_HISTORY_OPTS", PropertyType::String, HISTORY_SERVER),
+];
+
+/// Returns the definition of a known property
+///
+/// # Arguments
+/// * `file` - The file the property is written to
+/// * `name` - The name of the property
+///
+pub fn find_property(file: &PropertyFile, name: &str) -> Option<&'static PropertyDefinition> {
+    SPARK_PROPERTIES
+        .iter()
+        .find(|property| &property.file == file && property.name == name)
+}
+
+/// Returns true if the user defined option is reported as an error: it overrides a property
+/// managed by the operator or its value does not match the property type. Rejected options
+/// are not written to the config files.
+///
+/// # Arguments
+/// * `file` - The file the option is written to
+/// * `option` - The user defined config option
+///
+pub fn is_rejected(file: &PropertyFile, option: &ConfigOption) -> bool {
+    find_property(file, &option.name)
+        .map(|property| property.managed || !property.property_type.is_valid(&option.value))
+        .unwrap_or(false)
+}
+
+/// The severity of a validation issue
+#[derive(Clone, Debug, Eq, PartialEq, strum_macros::Display)]
+pub enum Severity {
+    /// The option is written but probably has no effect
+    Warning,
+    /// The option has an invalid value or overrides a property managed by the operator and
+    /// is not written
+    Error,
+}
+
+/// A problem found in a user defined config option
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PropertyIssue {
+    pub severity: Severity,
+    /// Where the option is defined: "cluster", the role or role/role group
+    pub location: String,
+    pub file: PropertyFile,
+    pub name: String,
+    pub message: String,
+}
+
+impl fmt::Display for PropertyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} [{}]: {}",
+            self.location, self.file, self.name, self.message
+        )
+    }
+}
+
+/// Validate user defined config options against the property catalog.
+///
+/// # Arguments
+/// * `location` - Where the options are defined (reported in the issues)
+/// * `node_type` - The node type the options apply to (None for all roles)
+/// * `version` - The Spark version of the cluster
+/// * `file` - The file the options are written to
+/// * `options` - The user defined config options
+///
+pub fn validate_config_options(
+    location: &str,
+    node_type: Option<&SparkNodeType>,
+    version: &SparkVersion,
+    file: &PropertyFile,
+    options: &[ConfigOption],
+) -> Vec<PropertyIssue> {
+    let issue = |severity: Severity, option: &ConfigOption, message: String| PropertyIssue {
+        severity,
+        location: location.to_string(),
+        file: file.clone(),
+        name: option.name.clone(),
+        message,
+    };
+
+    let mut issues = vec![];
+
+    for option in options {
+        let property = match find_property(file, &option.name) {
+            Some(property) => property,
+            None => {
+                issues.push(issue(
+                    Severity::Warning,
+                    option,
+                    "unknown property (check for typos)".to_string(),
+                ));
+                continue;
+            }
+        };
+
+        if property.managed {
+            issues.push(issue(
+                Severity::Error,
+                option,
+                "is managed by the operator and must be set via the dedicated field of the custom resource".to_string(),
+            ));
+        } else if !property.property_type.is_valid(&option.value) {
+            issues.push(issue(
+                Severity::Error,
+                option,
+                format!(
+                    "invalid value [{}], expected {}",
+                    option.value, property.property_type
+                ),
+            ));
+        }
+
+        if let Some(node_type) = node_type {
+            if !property.roles.contains(node_type) {
+                issues.push(issue(
+                    Severity::Warning,
+                    option,
+                    format!("is not used by the {} role", node_type),
+                ));
+            }
+        }
+
+        if !property.is_supported_by(version) {
+            issues.push(issue(
+                Severity::Warning,
+                option,
+                format!(
+                    "requires Spark {} or newer (cluster version is {})",
+                    property.since_version.unwrap_or_default(),
+                    version
+                ),
+            ));
+        }
+    }
+
+    issues
+}
+
+/// Validate the user defined config options of the cluster, all roles and all role groups.
+/// The issues are ordered by level, role and role group.
+///
+/// # Arguments
+/// * `spec` - The spark cluster spec
+///
+pub fn validate_cluster_config(spec: &SparkClusterSpec) -> Vec<PropertyIssue> {
+    let mut issues = vec![];
+
+    if let Some(config) = &spec.config {
+        issues.extend(validate_role_config("cluster", None, &spec.version, config));
+    }
+
+    issues.extend(validate_node_group(
+        &SparkNodeType::Master,
+        &spec.masters,
+        &spec.version,
+    ));
+    issues.extend(validate_node_group(
+        &SparkNodeType::Worker,
+        &spec.workers,
+        &spec.version,
+    ));
+    if let Some(history_servers) = &spec.history_servers {
+        issues.extend(validate_node_group(
+            &SparkNodeType::HistoryServer,
+            history_servers,
+            &spec.version,
+        ));
+
+        if !spec.has_shared_event_log() {
+            issues.push(PropertyIssue {
+                severity: Severity::Error,
+                location: SparkNodeType::HistoryServer.to_string(),
+                file: PropertyFile::Spec,
+                name: "eventLogVolume".to_string(),
+                message: "the kubernetes runtime requires logDir and exactly one of \
+                    persistentVolumeClaim or hostPath to share the event logs, no history \
+                    server is started"
+                    .to_string(),
+            });
+        }
+    }
+
+    // the security context is only applied for the kubernetes runtime
+    let security = spec.get_security();
+    if spec.get_runtime() == Runtime::Kubernetes && security.is_enabled() {
+        for name in security.get_root_id_fields() {
+            issues.push(PropertyIssue {
+                severity: Severity::Error,
+                location: "security".to_string(),
+                file: PropertyFile::Spec,
+                name: name.to_string(),
+                message: "the Spark processes must not run as root (0), the default 1000 is used"
+                    .to_string(),
+            });
+        }
+    }
+
+    issues
+}
+
+/// Validate the config options of a role and all its role groups.
+fn validate_node_group<T: RoleConfig>(
+    node_type: &SparkNodeType,
+    node_group: &NodeGroup<T>,
+    version: &SparkVersion,
+) -> Vec<PropertyIssue> {
+    let mut issues = vec![];
+
+    if let Some(config) = &node_group.config {
+        issues.extend(validate_role_config(
+            &node_type.to_string(),
+            Some(node_type),
+            version,
+            config,
+        ));
+    }
+
+    let mut role_groups = node_group.selectors.iter().collect::<Vec<_>>();
+    role_groups.sort_by_key(|(role_group, _)| *role_group);
+
+    for (role_group, selector) in role_groups {
+        if let Some(config) = &selector.config {
+            issues.extend(validate_role_config(
+                &format!("{}/{}", node_type, role_group),
+                Some(node_type),
+                version,
+                config,
+            ));
+        }
+    }
+
+    issues
+}
+
+/// Validate the spark-defaults.conf and spark-env.sh options of a single config.
+fn validate_role_config<T: RoleConfig>(
+    location: &str,
+    node_type: Option<&SparkNodeType>,
+    version: &SparkVersion,
+    config: &T,
+) -> Vec<PropertyIssue> {
+    let mut issues = validate_config_options(
+        location,
+        node_type,
+        version,
+        &PropertyFile::SparkDefaults,
+        config
+            .get_spark_defaults()
+            .map(Vec::as_slice)
+            .unwrap_or_default(),
+    );
+    issues.extend(validate_config_options(
+        location,
+        node_type,
+        version,
+        &PropertyFile::SparkEnvSh,
+        config
+            .get_spark_env()
+            .map(Vec::as_slice)
+            .unwrap_or_default(),
+    ));
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(name: &str, value: &str) -> ConfigOption {
+        ConfigOption {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_property_type_is_valid() {
+        assert!(PropertyType::Boolean.is_valid("true"));
+        assert!(!PropertyType::Boolean.is_valid("yes"));
+        assert!(PropertyType::Integer.is_valid("42"));
+        assert!(!PropertyType::Integer.is_valid("-1"));
+        assert!(PropertyType::Port.is_valid("7077"));
+        assert!(!PropertyType::Port.is_valid("0"));
+        assert!(!PropertyType::Port.is_valid("70000"));
+        assert!(PropertyType::Memory.is_valid("512m"));
+        assert!(PropertyType::Memory.is_valid("2G"));
+        assert!(!PropertyType::Memory.is_valid("2 gigabytes"));
+        assert!(PropertyType::Duration.is_valid("10s"));
+        assert!(PropertyType::Duration.is_valid("120"));
+        assert!(!PropertyType::Duration.is_valid("s"));
+    }
+
+    #[test]
+    fn test_validate_config_options() {
+        let options = vec![
+            option("spark.eventlog.dir", "/tmp"),
+            option("spark.deploy.spreadOut", "maybe"),
+            option("spark.authenticate.secret", "secret"),
+            option("spark.worker.cleanup.enabled", "true"),
+            option("spark.decommission.enabled", "true"),
+            option("spark.deploy.defaultCores", "4"),
+        ];
+
+        let issues = validate_config_options(
+            "master/default",
+            Some(&SparkNodeType::Master),
+            &SparkVersion::v3_0_1,
+            &PropertyFile::SparkDefaults,
+            &options,
+        );
+
+        let summary = issues
+            .iter()
+            .map(|issue| (issue.name.as_str(), issue.severity.clone()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("spark.eventlog.dir", Severity::Warning),
+                ("spark.deploy.spreadOut", Severity::Error),
+                ("spark.authenticate.secret", Severity::Error),
+                // not used by masters
+                ("spark.worker.cleanup.enabled", Severity::Warning),
+                // managed, not used by masters and requires 3.1.0
+                ("spark.decommission.enabled", Severity::Error),
+                ("spark.decommission.enabled", Severity::Warning),
+                ("spark.decommission.enabled", Severity::Warning),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_cluster_config() {
+        let spark_cluster: crate::SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+
+        let issues = validate_cluster_config(&spark_cluster.spec);
+
+        // the test cluster overrides the managed master port and worker memory
+        let names = issues
+            .iter()
+            .map(|issue| (issue.location.as_str(), issue.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                ("master/master_1", SPARK_DEFAULTS_MASTER_PORT),
+                ("master/master_1", SPARK_ENV_MASTER_PORT),
+                ("slave/1core1g", SPARK_ENV_WORKER_MEMORY),
+            ]
+        );
+        assert!(issues.iter().all(|issue| issue.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_validate_cluster_config_event_log_volume() {
+        let mut spark_cluster: crate::SparkCluster =
+            stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.spec.runtime = Some(crate::Runtime::Kubernetes);
+
+        let issues = validate_cluster_config(&spark_cluster.spec);
+        let issue = issues
+            .iter()
+            .find(|issue| issue.file == PropertyFile::Spec)
+            .unwrap();
+        assert_eq!(issue.severity, Severity::Error);
+        assert_eq!(issue.name, "eventLogVolume");
+
+        spark_cluster.spec.event_log_volume = Some(crate::volumes::EventLogVolume {
+            persistent_volume_claim: Some("spark-events".to_string()),
+            host_path: None,
+        });
+        assert!(validate_cluster_config(&spark_cluster.spec)
+            .iter()
+            .all(|issue| issue.file != PropertyFile::Spec));
+    }
+
+    #[test]
+    fn test_validate_cluster_config_root_ids() {
+        let mut spark_cluster: crate::SparkCluster =
+            stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.spec.security = Some(crate::security::SecurityConfig {
+            run_as_user: Some(0),
+            fs_group: Some(0),
+            ..crate::security::SecurityConfig::default()
+        });
+
+        // not applied for the stackableAgent runtime
+        assert!(validate_cluster_config(&spark_cluster.spec)
+            .iter()
+            .all(|issue| issue.location != "security"));
+
+        spark_cluster.spec.runtime = Some(Runtime::Kubernetes);
+        spark_cluster.spec.event_log_volume = Some(crate::volumes::EventLogVolume {
+            persistent_volume_claim: Some("spark-events".to_string()),
+            host_path: None,
+        });
+        let names = validate_cluster_config(&spark_cluster.spec)
+            .into_iter()
+            .filter(|issue| issue.location == "security")
+            .map(|issue| (issue.severity, issue.name))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                (Severity::Error, "runAsUser".to_string()),
+                (Severity::Error, "fsGroup".to_string()),
+            ]
+        );
+
+        // the root ids are not applied
+        let security = spark_cluster.spec.get_security();
+        assert_eq!(security.get_run_as_user(), 1000);
+        assert_eq!(security.get_fs_group(), 1000);
+    }
+}
@@ -0,0 +1,156 @@
+//! This module provides the SparkScheduledApplication CRD which creates SparkApplications
+//! from a template on a cron schedule.
+use crate::application::{ApplicationState, SparkApplicationSpec};
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use stackable_operator::Crd;
+
+/// Default number of finished runs that are kept
+const DEFAULT_SUCCESSFUL_RUNS_HISTORY_LIMIT: usize = 3;
+/// Default number of failed runs that are kept
+const DEFAULT_FAILED_RUNS_HISTORY_LIMIT: usize = 1;
+
+#[derive(Clone, CustomResource, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[kube(
+    group = "spark.stackable.tech",
+    version = "v1",
+    kind = "SparkScheduledApplication",
+    shortname = "ssa",
+    namespaced
+)]
+#[kube(status = "SparkScheduledApplicationStatus")]
+#[kube(
+    printcolumn = r#"{"name":"Schedule", "type":"string", "description":"The cron schedule", "jsonPath":".spec.schedule"}"#,
+    printcolumn = r#"{"name":"Suspend", "type":"boolean", "description":"Whether new runs are suspended", "jsonPath":".spec.suspend"}"#,
+    printcolumn = r#"{"name":"Last Schedule", "type":"string", "description":"The time of the last scheduled run", "jsonPath":".status.lastScheduleTime"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#
+)]
+#[serde(rename_all = "camelCase")]
+pub struct SparkScheduledApplicationSpec {
+    /// Cron expression in UTC (minute hour day-of-month month day-of-week)
+    pub schedule: String,
+    /// Do not create new runs while set. Existing runs are not affected
+    pub suspend: Option<bool>,
+    /// How to treat a run that is due while a previous run is still active. Default: Allow
+    pub concurrency_policy: Option<ConcurrencyPolicy>,
+    /// Runs that could not be created within this many seconds after their schedule are skipped
+    pub starting_deadline_seconds: Option<u64>,
+    /// Number of finished runs to keep. Default: 3
+    pub successful_runs_history_limit: Option<usize>,
+    /// Number of failed runs to keep. Default: 1
+    pub failed_runs_history_limit: Option<usize>,
+    /// The SparkApplication created for every run
+    pub template: SparkApplicationSpec,
+}
+
+#[derive(
+    Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize, strum_macros::Display,
+)]
+pub enum ConcurrencyPolicy {
+    /// Runs may overlap
+    Allow,
+    /// Skip a run while a previous run is still active
+    Forbid,
+    /// Delete active runs before a new run is created
+    Replace,
+}
+
+impl Default for ConcurrencyPolicy {
+    fn default() -> Self {
+        ConcurrencyPolicy::Allow
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SparkScheduledApplicationStatus {
+    /// The time of the last run that was created or skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_schedule_time: Option<String>,
+    /// The time of the last run that finished successfully
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_successful_time: Option<String>,
+    /// All existing runs ordered by their schedule time
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub runs: Vec<ScheduledRun>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// A SparkApplication created for a scheduled run.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledRun {
+    /// Name of the SparkApplication
+    pub name: String,
+    pub state: ApplicationState,
+    pub scheduled_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+impl Crd for SparkScheduledApplication {
+    const RESOURCE_NAME: &'static str = "sparkscheduledapplications.spark.stackable.tech";
+    const CRD_DEFINITION: &'static str =
+        include_str!("../../deploy/crd/sparkscheduledapplication.crd.yaml");
+}
+
+impl SparkScheduledApplication {
+    /// Returns true if the creation of new runs is suspended
+    pub fn is_suspended(&self) -> bool {
+        self.spec.suspend.unwrap_or(false)
+    }
+
+    /// Returns the configured concurrency policy or the default (Allow)
+    pub fn get_concurrency_policy(&self) -> ConcurrencyPolicy {
+        self.spec.concurrency_policy.clone().unwrap_or_default()
+    }
+
+    /// Returns the configured number of finished runs to keep or the default
+    pub fn get_successful_runs_history_limit(&self) -> usize {
+        self.spec
+            .successful_runs_history_limit
+            .unwrap_or(DEFAULT_SUCCESSFUL_RUNS_HISTORY_LIMIT)
+    }
+
+    /// Returns the configured number of failed runs to keep or the default
+    pub fn get_failed_runs_history_limit(&self) -> usize {
+        self.spec
+            .failed_runs_history_limit
+            .unwrap_or(DEFAULT_FAILED_RUNS_HISTORY_LIMIT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let scheduled: SparkScheduledApplication = serde_yaml::from_str(
+            r#"
+apiVersion: spark.stackable.tech/v1
+kind: SparkScheduledApplication
+metadata:
+  name: nightly-etl
+spec:
+  schedule: "0 2 * * *"
+  template:
+    sparkCluster: spark-cluster
+    mainApplicationFile: etl.jar
+"#,
+        )
+        .unwrap();
+
+        assert!(!scheduled.is_suspended());
+        assert_eq!(scheduled.get_concurrency_policy(), ConcurrencyPolicy::Allow);
+        assert_eq!(
+            scheduled.get_successful_runs_history_limit(),
+            DEFAULT_SUCCESSFUL_RUNS_HISTORY_LIMIT
+        );
+        assert_eq!(
+            scheduled.get_failed_runs_history_limit(),
+            DEFAULT_FAILED_RUNS_HISTORY_LIMIT
+        );
+    }
+}
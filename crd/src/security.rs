@@ -0,0 +1,94 @@
+//! This module provides the security settings of the Spark pods (kubernetes runtime). The
+//! defaults comply with the "restricted" Pod Security Standard.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// UID / GID of the "stackable" user in the Spark images
+const DEFAULT_USER_ID: i64 = 1000;
+/// UID / GID of root which the Spark processes must not run as
+const ROOT_ID: i64 = 0;
+
+/// Security context of all Spark pods of the cluster (only applied for the kubernetes runtime)
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityConfig {
+    /// Set to false to run the pods with the image defaults (default: true)
+    pub enabled: Option<bool>,
+    /// UID of the Spark processes, must not be 0 (default: 1000)
+    pub run_as_user: Option<i64>,
+    /// GID of the Spark processes, must not be 0 (default: 1000)
+    pub run_as_group: Option<i64>,
+    /// GID that owns the mounted volumes, must not be 0 (default: 1000)
+    pub fs_group: Option<i64>,
+    /// Mount the root filesystem read only. Spark writes to emptyDir scratch volumes instead
+    /// (default: true)
+    pub read_only_root_filesystem: Option<bool>,
+    /// Seccomp profile of the pods (default: RuntimeDefault)
+    pub seccomp_profile: Option<SeccompProfile>,
+}
+
+impl SecurityConfig {
+    /// Returns whether the security context is applied (default: true)
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// Returns the configured UID or the default (1000). The root UID is rejected.
+    pub fn get_run_as_user(&self) -> i64 {
+        non_root_or_default(self.run_as_user)
+    }
+
+    /// Returns the configured GID or the default (1000). The root GID is rejected.
+    pub fn get_run_as_group(&self) -> i64 {
+        non_root_or_default(self.run_as_group)
+    }
+
+    /// Returns the configured fsGroup or the default (1000). The root GID is rejected.
+    pub fn get_fs_group(&self) -> i64 {
+        non_root_or_default(self.fs_group)
+    }
+
+    /// Returns the names of the UID / GID fields that are set to root (0)
+    pub fn get_root_id_fields(&self) -> Vec<&'static str> {
+        vec![
+            ("runAsUser", self.run_as_user),
+            ("runAsGroup", self.run_as_group),
+            ("fsGroup", self.fs_group),
+        ]
+        .into_iter()
+        .filter(|(_, id)| *id == Some(ROOT_ID))
+        .map(|(name, _)| name)
+        .collect()
+    }
+
+    /// Returns whether the root filesystem is read only (default: true)
+    pub fn is_read_only_root_filesystem(&self) -> bool {
+        self.read_only_root_filesystem.unwrap_or(true)
+    }
+
+    /// Returns the configured seccomp profile or the default (RuntimeDefault)
+    pub fn get_seccomp_profile(&self) -> SeccompProfile {
+        self.seccomp_profile.clone().unwrap_or_default()
+    }
+}
+
+/// Returns the given UID / GID unless it is root, the default otherwise
+fn non_root_or_default(id: Option<i64>) -> i64 {
+    id.filter(|id| *id != ROOT_ID).unwrap_or(DEFAULT_USER_ID)
+}
+
+#[derive(
+    Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize, strum_macros::Display,
+)]
+pub enum SeccompProfile {
+    /// The default profile of the container runtime (required by the "restricted" standard)
+    RuntimeDefault,
+    /// No seccomp filtering
+    Unconfined,
+}
+
+impl Default for SeccompProfile {
+    fn default() -> Self {
+        SeccompProfile::RuntimeDefault
+    }
+}
@@ -0,0 +1,109 @@
+//! This module provides additional volumes (ConfigMaps or Secrets) that users mount into the
+//! Spark pods of a role group, e.g. for extra jars or certificates, and the volume that holds
+//! the event logs of a cluster.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Prefix of the pod volume names of extra volumes, so they do not clash with the volumes
+/// created by the operator
+const EXTRA_VOLUME_PREFIX: &str = "extra-";
+
+/// A ConfigMap or Secret that is mounted into the Spark container
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtraVolume {
+    /// Name of the volume, unique within the role group
+    pub name: String,
+    /// Path the volume is mounted to in the Spark container
+    pub mount_path: String,
+    /// Name of the ConfigMap to mount (exclusive with secret)
+    pub config_map: Option<String>,
+    /// Name of the Secret to mount (exclusive with configMap)
+    pub secret: Option<String>,
+}
+
+impl ExtraVolume {
+    /// Returns the name of the volume in the pod
+    pub fn volume_name(&self) -> String {
+        format!("{}{}", EXTRA_VOLUME_PREFIX, self.name)
+    }
+}
+
+/// The volume of the event log directory for the kubernetes runtime. Masters and workers write
+/// the event logs which the history server reads, so all pods must mount the same storage.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventLogVolume {
+    /// Name of a PersistentVolumeClaim with access mode ReadWriteMany (exclusive with hostPath)
+    pub persistent_volume_claim: Option<String>,
+    /// Directory on the nodes, only shared if it is backed by a shared file system or all pods
+    /// run on the same node (exclusive with persistentVolumeClaim)
+    pub host_path: Option<String>,
+}
+
+impl EventLogVolume {
+    /// Returns whether exactly one volume source is set
+    pub fn is_valid(&self) -> bool {
+        self.persistent_volume_claim.is_some() != self.host_path.is_some()
+    }
+}
+
+/// Combine the extra volumes of the role and the role group. Role group volumes replace role
+/// volumes of the same name.
+///
+/// # Arguments
+/// * `role_volumes` - The extra volumes of the role
+/// * `role_group_volumes` - The extra volumes of the role group
+///
+pub fn merge_volumes(
+    role_volumes: &Option<Vec<ExtraVolume>>,
+    role_group_volumes: &Option<Vec<ExtraVolume>>,
+) -> Option<Vec<ExtraVolume>> {
+    match (role_volumes, role_group_volumes) {
+        (None, None) => None,
+        _ => {
+            let role_group_volumes = role_group_volumes.clone().unwrap_or_default();
+            let mut volumes = role_volumes
+                .iter()
+                .flatten()
+                .filter(|volume| {
+                    !role_group_volumes
+                        .iter()
+                        .any(|role_group_volume| role_group_volume.name == volume.name)
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            volumes.extend(role_group_volumes);
+            Some(volumes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume(name: &str, config_map: &str) -> ExtraVolume {
+        ExtraVolume {
+            name: name.to_string(),
+            mount_path: format!("/stackable/{}", name),
+            config_map: Some(config_map.to_string()),
+            secret: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_volumes() {
+        let role_volumes = Some(vec![volume("jars", "role-jars"), volume("certs", "certs")]);
+        let role_group_volumes = Some(vec![volume("jars", "role-group-jars")]);
+
+        let merged = merge_volumes(&role_volumes, &role_group_volumes).unwrap();
+
+        assert_eq!(
+            merged,
+            vec![volume("certs", "certs"), volume("jars", "role-group-jars")]
+        );
+        assert_eq!(merge_volumes(&None, &None), None);
+        assert_eq!(merged[0].volume_name(), "extra-certs");
+    }
+}
@@ -0,0 +1,756 @@
+//! This module contains the controller for SparkApplications. For every application a
+//! submitter pod runs spark-submit against the masters of the referenced SparkCluster.
+//! The state of the application is tracked via the JSON API of the alive master and
+//! written to the application status.
+use crate::command_utils::get_current_timestamp;
+use crate::config::POD_IP_FIELD_PATH;
+use crate::error::Error;
+use crate::events::{EventReason, EventType};
+use crate::master_api::MasterState;
+use crate::{command_utils, events, master_api, pod_utils, status};
+use async_trait::async_trait;
+use k8s_openapi::api::core::v1::{
+    Container, EnvVar, EnvVarSource, ObjectFieldSelector, Pod, PodSpec, Secret, SecretKeySelector,
+};
+use kube::api::ListParams;
+use kube::{Api, Resource};
+use serde_json::json;
+use stackable_operator::client::Client;
+use stackable_operator::controller::{Controller, ControllerStrategy, ReconciliationState};
+use stackable_operator::labels::{
+    APP_COMPONENT_LABEL, APP_INSTANCE_LABEL, APP_NAME_LABEL, APP_VERSION_LABEL,
+};
+use stackable_operator::metadata;
+use stackable_operator::reconcile::{
+    ReconcileFunctionAction, ReconcileResult, ReconciliationContext,
+};
+use stackable_spark_common::constants::{SPARK_ENV_AUTH_SECRET, SPARK_ENV_DRIVER_HOST};
+use stackable_spark_crd::application::DeployMode;
+use stackable_spark_crd::{
+    ApplicationState, Runtime, SparkApplication, SparkCluster, SparkNodeType,
+};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tracing::{info, warn};
+
+type SparkApplicationReconcileResult = ReconcileResult<Error>;
+
+/// Value of the APP_COMPONENT_LABEL for submitter pods
+const SUBMITTER_COMPONENT: &str = "submitter";
+/// Requeue interval while waiting for the cluster or tracking a submitted application
+const REQUEUE_INTERVAL: Duration = Duration::from_secs(10);
+/// Polls after which an application the master does not list is considered failed once its
+/// submitter pod succeeded (e.g. the master dropped it from its completed applications)
+const MAX_UNLISTED_POLLS: u32 = 30;
+/// Key of the authentication secret in the Secret of the submitter pod
+const AUTH_SECRET_KEY: &str = "secret";
+
+struct SparkApplicationState {
+    context: ReconciliationContext<SparkApplication>,
+    http_client: reqwest::Client,
+    cluster: Option<SparkCluster>,
+    /// All running and ready master pods of the referenced cluster
+    master_pods: Vec<Pod>,
+}
+
+impl SparkApplicationState {
+    /// Publish an event for the SparkApplication
+    async fn publish_event(&self, event_type: EventType, reason: EventReason, message: &str) {
+        events::publish_event(
+            &self.context.client,
+            &self.context.resource,
+            event_type,
+            reason,
+            message,
+        )
+        .await;
+    }
+
+    /// Merge the given fields into the application status.
+    async fn patch_status(&mut self, patch: serde_json::Value) -> Result<(), Error> {
+        self.context.resource =
+            status::patch_status(&self.context.client, &self.context.resource, patch).await?;
+        Ok(())
+    }
+
+    /// Set the state to Pending if no status exists yet.
+    async fn init_status(&mut self) -> SparkApplicationReconcileResult {
+        if self.context.resource.status.is_none() {
+            self.patch_status(json!({ "state": ApplicationState::Pending }))
+                .await?;
+        }
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Finished and failed applications are not processed anymore.
+    async fn check_finished(&self) -> SparkApplicationReconcileResult {
+        if self.context.resource.get_state().is_terminal() {
+            return Ok(ReconcileFunctionAction::Done);
+        }
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Retrieve the referenced SparkCluster and its running and ready masters. Requeue with a
+    /// message in the status if the cluster can not be retrieved.
+    async fn get_cluster(&mut self) -> SparkApplicationReconcileResult {
+        let cluster_name = self.context.resource.spec.spark_cluster.clone();
+        let namespace = self.context.resource.namespace();
+
+        let cluster = match self
+            .context
+            .client
+            .get::<SparkCluster>(&cluster_name, namespace.as_deref())
+            .await
+        {
+            Ok(cluster) => cluster,
+            Err(err) => {
+                warn!(
+                    "Could not retrieve SparkCluster [{}]: {}",
+                    cluster_name, err
+                );
+                self.set_message(&format!(
+                    "Could not retrieve SparkCluster [{}]",
+                    cluster_name
+                ))
+                .await?;
+                return Ok(ReconcileFunctionAction::Requeue(REQUEUE_INTERVAL));
+            }
+        };
+
+        let pods_api: Api<Pod> = self.context.client.get_api(namespace.as_deref());
+        let master_selector = pod_utils::build_role_selector(&cluster_name, &SparkNodeType::Master);
+        self.master_pods = pods_api
+            .list(&ListParams::default().labels(&master_selector))
+            .await?
+            .items
+            .into_iter()
+            .filter(|pod| pod_utils::is_pod_running_and_ready(pod))
+            .collect();
+
+        self.cluster = Some(cluster);
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Create the submitter pod for pending applications as soon as a master is ready.
+    async fn submit(&mut self) -> SparkApplicationReconcileResult {
+        if self.context.resource.get_state() != ApplicationState::Pending {
+            return Ok(ReconcileFunctionAction::Continue);
+        }
+
+        let cluster = match &self.cluster {
+            Some(cluster) => cluster,
+            None => return Ok(ReconcileFunctionAction::Requeue(REQUEUE_INTERVAL)),
+        };
+
+        let master_urls = stackable_spark_crd::get_master_urls(&self.master_pods, &cluster.spec);
+        if master_urls.is_empty() {
+            let message = format!(
+                "Waiting for a ready master of SparkCluster [{}]",
+                cluster.name()
+            );
+            self.set_message(&message).await?;
+            return Ok(ReconcileFunctionAction::Requeue(REQUEUE_INTERVAL));
+        }
+
+        if let Some(secret) = &cluster.spec.secret {
+            self.create_auth_secret(secret).await?;
+        }
+
+        let pod = match self.get_submitter_pod().await? {
+            // the pod was created but the status could not be updated
+            Some(pod) => pod,
+            None => {
+                let pod = build_submitter_pod(
+                    &self.context.resource,
+                    cluster,
+                    &master_urls,
+                    self.master_pods.first(),
+                )?;
+                self.context.client.create(&pod).await?
+            }
+        };
+
+        info!(
+            "Submitted application [{}] via pod [{}]",
+            self.context.resource.name(),
+            pod.name()
+        );
+        self.publish_event(
+            EventType::Normal,
+            EventReason::ApplicationSubmitted,
+            &format!(
+                "Submitting application to SparkCluster [{}] via pod [{}]",
+                cluster.name(),
+                pod.name()
+            ),
+        )
+        .await;
+
+        self.patch_status(json!({
+            "state": ApplicationState::Submitted,
+            "submitterPod": pod.name(),
+            "submittedAt": get_current_timestamp(),
+            "message": null
+        }))
+        .await?;
+
+        Ok(ReconcileFunctionAction::Requeue(REQUEUE_INTERVAL))
+    }
+
+    /// Track submitted and running applications via the submitter pod and the alive master.
+    async fn track(&mut self) -> SparkApplicationReconcileResult {
+        let submitter_pod = self.get_submitter_pod().await?;
+        let submitter_phase = submitter_pod
+            .as_ref()
+            .and_then(|pod| pod.status.as_ref())
+            .and_then(|status| status.phase.clone());
+
+        if submitter_pod.is_none() || submitter_phase.as_deref() == Some("Failed") {
+            let message = match submitter_pod {
+                Some(pod) => format!("Submitter pod [{}] failed", pod.name()),
+                None => "Submitter pod was deleted".to_string(),
+            };
+            self.finish(ApplicationState::Failed, None, &message)
+                .await?;
+            return Ok(ReconcileFunctionAction::Done);
+        }
+
+        let alive_master = self.get_alive_master_state().await;
+        let application = alive_master.as_ref().and_then(|master_state| {
+            let status = self.context.resource.status.as_ref();
+            master_api::find_application(
+                master_state,
+                &self.context.resource.get_app_name(),
+                status.and_then(|status| status.app_id.as_deref()),
+            )
+        });
+
+        let application = match application {
+            Some(application) => application,
+            None => {
+                // a succeeded submitter pod never changes again, so give up after a while
+                // if the master does not list the application
+                if alive_master.is_some() && submitter_phase.as_deref() == Some("Succeeded") {
+                    return self.count_unlisted_poll().await;
+                }
+                return Ok(ReconcileFunctionAction::Requeue(REQUEUE_INTERVAL));
+            }
+        };
+
+        let state = convert_application_state(&application.state);
+        let app_id = application.id.clone();
+
+        if state.is_terminal() {
+            let message = format!(
+                "Application [{}] reported state [{}]",
+                app_id, application.state
+            );
+            self.finish(state, Some(app_id), &message).await?;
+            return Ok(ReconcileFunctionAction::Done);
+        }
+
+        let status = self.context.resource.status.clone().unwrap_or_default();
+        if status.state != Some(state.clone()) || status.app_id.as_deref() != Some(&app_id) {
+            self.patch_status(json!({ "state": state, "appId": app_id }))
+                .await?;
+        }
+
+        Ok(ReconcileFunctionAction::Requeue(REQUEUE_INTERVAL))
+    }
+
+    /// Count a poll in which the alive master did not list the application although its
+    /// submitter pod succeeded. The application fails after MAX_UNLISTED_POLLS polls.
+    async fn count_unlisted_poll(&mut self) -> SparkApplicationReconcileResult {
+        let unlisted_polls = self
+            .context
+            .resource
+            .status
+            .as_ref()
+            .and_then(|status| status.unlisted_polls)
+            .unwrap_or_default()
+            + 1;
+
+        if unlisted_polls >= MAX_UNLISTED_POLLS {
+            let message = format!(
+                "Submitter pod succeeded but the master did not list the application within {} polls",
+                MAX_UNLISTED_POLLS
+            );
+            self.finish(ApplicationState::Failed, None, &message)
+                .await?;
+            return Ok(ReconcileFunctionAction::Done);
+        }
+
+        self.patch_status(json!({ "unlistedPolls": unlisted_polls }))
+            .await?;
+        Ok(ReconcileFunctionAction::Requeue(REQUEUE_INTERVAL))
+    }
+
+    /// Create the Secret with the authentication secret of the cluster for the submitter pod
+    /// if it does not exist yet.
+    ///
+    /// # Arguments
+    /// * `secret` - The authentication secret of the cluster
+    ///
+    async fn create_auth_secret(&self, secret: &str) -> Result<(), Error> {
+        let auth_secret = build_auth_secret(&self.context.resource, secret)?;
+        let name = auth_secret.name();
+
+        match self
+            .context
+            .client
+            .get::<Secret>(&name, self.context.resource.namespace().as_deref())
+            .await
+        {
+            Ok(_) => {}
+            Err(err) if command_utils::is_not_found(&err) => {
+                info!("Secret [{}] does not exist, creating it!", name);
+                if let Err(err) = self.context.client.create(&auth_secret).await {
+                    // already exists: created by someone else since we read it
+                    if !command_utils::is_conflict(&err) {
+                        return Err(err.into());
+                    }
+                }
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(())
+    }
+
+    /// Write the final state of the application and publish an event.
+    async fn finish(
+        &mut self,
+        state: ApplicationState,
+        app_id: Option<String>,
+        message: &str,
+    ) -> Result<(), Error> {
+        let (event_type, reason) = match state {
+            ApplicationState::Finished => (EventType::Normal, EventReason::ApplicationFinished),
+            _ => (EventType::Warning, EventReason::ApplicationFailed),
+        };
+
+        info!(
+            "Application [{}] is {}: {}",
+            self.context.resource.name(),
+            state,
+            message
+        );
+        self.publish_event(event_type, reason, message).await;
+
+        let mut patch = json!({
+            "state": state,
+            "finishedAt": get_current_timestamp(),
+            "message": message
+        });
+        if let Some(app_id) = app_id {
+            patch["appId"] = json!(app_id);
+        }
+
+        self.patch_status(patch).await
+    }
+
+    /// Set a human readable message in the status (e.g. what the application waits for).
+    async fn set_message(&mut self, message: &str) -> Result<(), Error> {
+        let current = self
+            .context
+            .resource
+            .status
+            .as_ref()
+            .and_then(|status| status.message.as_deref());
+
+        if current != Some(message) {
+            self.patch_status(json!({ "message": message })).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Return the submitter pod of this application if it exists.
+    async fn get_submitter_pod(&self) -> Result<Option<Pod>, Error> {
+        let pods_api: Api<Pod> = self
+            .context
+            .client
+            .get_api(self.context.resource.namespace().as_deref());
+        let selector = format!(
+            "{}={},{}={}",
+            APP_INSTANCE_LABEL,
+            self.context.resource.name(),
+            APP_COMPONENT_LABEL,
+            SUBMITTER_COMPONENT
+        );
+
+        Ok(pods_api
+            .list(&ListParams::default().labels(&selector))
+            .await?
+            .items
+            .into_iter()
+            .next())
+    }
+
+    /// Query all ready masters of the cluster and return the state of the alive one.
+    async fn get_alive_master_state(&self) -> Option<MasterState> {
+        let cluster = self.cluster.as_ref()?;
+        let web_ui_urls =
+            stackable_spark_crd::get_master_web_ui_urls(&self.master_pods, &cluster.spec);
+
+        let mut master_states = vec![];
+        for web_ui_url in web_ui_urls {
+            match master_api::get_master_state(&self.http_client, &web_ui_url).await {
+                Ok(master_state) => master_states.push((web_ui_url, Some(master_state))),
+                Err(err) => warn!("{}", err),
+            }
+        }
+
+        master_api::find_alive_master(&master_states)
+            .and_then(|(_, master_state)| master_state.clone())
+    }
+}
+
+impl ReconciliationState for SparkApplicationState {
+    type Error = Error;
+
+    fn reconcile(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<ReconcileFunctionAction, Self::Error>> + Send + '_>>
+    {
+        Box::pin(async move {
+            self.init_status()
+                .await?
+                .then(self.check_finished())
+                .await?
+                .then(self.get_cluster())
+                .await?
+                .then(self.submit())
+                .await?
+                .then(self.track())
+                .await
+        })
+    }
+}
+
+/// Convert the application state reported by the master (e.g. WAITING, RUNNING, FINISHED,
+/// FAILED, KILLED) into the application state of the custom resource.
+///
+/// # Arguments
+/// * `master_app_state` - The state reported by the master JSON API
+///
+fn convert_application_state(master_app_state: &str) -> ApplicationState {
+    match master_app_state {
+        "WAITING" | "RUNNING" => ApplicationState::Running,
+        "FINISHED" => ApplicationState::Finished,
+        _ => ApplicationState::Failed,
+    }
+}
+
+/// Returns the name of the Secret that holds the authentication secret for the submitter pod
+///
+/// # Arguments
+/// * `application` - The SparkApplication to submit
+///
+fn create_auth_secret_name(application: &SparkApplication) -> String {
+    format!("{}-auth", application.name())
+}
+
+/// Build the Secret with the authentication secret of the cluster. It is owned by the
+/// application and therefore deleted together with it.
+///
+/// # Arguments
+/// * `application` - The SparkApplication to submit
+/// * `secret` - The authentication secret of the cluster
+///
+fn build_auth_secret(application: &SparkApplication, secret: &str) -> Result<Secret, Error> {
+    let mut labels = BTreeMap::new();
+    labels.insert(APP_NAME_LABEL.to_string(), pod_utils::APP_NAME.to_string());
+    labels.insert(APP_INSTANCE_LABEL.to_string(), application.name());
+
+    let mut string_data = BTreeMap::new();
+    string_data.insert(AUTH_SECRET_KEY.to_string(), secret.to_string());
+
+    Ok(Secret {
+        metadata: metadata::build_metadata(
+            create_auth_secret_name(application),
+            Some(labels),
+            application,
+            true,
+        )?,
+        string_data: Some(string_data),
+        ..Secret::default()
+    })
+}
+
+/// Build the env vars of the submitter pod: the authentication secret from the Secret of the
+/// application and, for the kubernetes runtime in client mode, the pod IP the executors use
+/// to connect to the driver.
+///
+/// # Arguments
+/// * `application` - The SparkApplication to submit
+/// * `cluster` - The SparkCluster the application is submitted to
+///
+fn build_submitter_env(application: &SparkApplication, cluster: &SparkCluster) -> Vec<EnvVar> {
+    let mut env = vec![];
+
+    if cluster.spec.secret.is_some() {
+        env.push(EnvVar {
+            name: SPARK_ENV_AUTH_SECRET.to_string(),
+            value_from: Some(EnvVarSource {
+                secret_key_ref: Some(SecretKeySelector {
+                    name: Some(create_auth_secret_name(application)),
+                    key: AUTH_SECRET_KEY.to_string(),
+                    optional: None,
+                }),
+                ..EnvVarSource::default()
+            }),
+            ..EnvVar::default()
+        });
+    }
+
+    if cluster.spec.get_runtime() == Runtime::Kubernetes
+        && application.get_deploy_mode() == DeployMode::Client
+    {
+        env.push(EnvVar {
+            name: SPARK_ENV_DRIVER_HOST.to_string(),
+            value_from: Some(EnvVarSource {
+                field_ref: Some(ObjectFieldSelector {
+                    field_path: POD_IP_FIELD_PATH.to_string(),
+                    api_version: None,
+                }),
+                ..EnvVarSource::default()
+            }),
+            ..EnvVar::default()
+        });
+    }
+
+    env
+}
+
+/// Build the pod that runs spark-submit for the application. For the stackableAgent runtime
+/// the pod is placed on the node of a ready master, otherwise the scheduler places it.
+///
+/// # Arguments
+/// * `application` - The SparkApplication to submit
+/// * `cluster` - The SparkCluster the application is submitted to
+/// * `master_urls` - Slice of all known master urls
+/// * `master_pod` - A ready master pod of the cluster
+///
+fn build_submitter_pod(
+    application: &SparkApplication,
+    cluster: &SparkCluster,
+    master_urls: &[String],
+    master_pod: Option<&Pod>,
+) -> Result<Pod, Error> {
+    let runtime = cluster.spec.get_runtime();
+    let version = &cluster.spec.version;
+
+    let mut labels = BTreeMap::new();
+    labels.insert(APP_NAME_LABEL.to_string(), pod_utils::APP_NAME.to_string());
+    labels.insert(APP_INSTANCE_LABEL.to_string(), application.name());
+    labels.insert(
+        APP_COMPONENT_LABEL.to_string(),
+        SUBMITTER_COMPONENT.to_string(),
+    );
+    labels.insert(APP_VERSION_LABEL.to_string(), version.to_string());
+
+    let node_name = match runtime {
+        Runtime::StackableAgent => master_pod
+            .and_then(|pod| pod.spec.as_ref())
+            .and_then(|spec| spec.node_name.clone()),
+        Runtime::Kubernetes => None,
+    };
+
+    Ok(Pod {
+        metadata: metadata::build_metadata(
+            format!("{}-submitter", application.name()),
+            Some(labels),
+            application,
+            true,
+        )?,
+        spec: Some(PodSpec {
+            node_name,
+            tolerations: pod_utils::get_tolerations(&cluster.spec),
+            restart_policy: Some("Never".to_string()),
+            containers: vec![Container {
+                image: Some(runtime.get_image_name(version)),
+                name: SUBMITTER_COMPONENT.to_string(),
+                command: Some(application.build_submit_command(
+                    master_urls,
+                    &runtime,
+                    version,
+                    cluster.spec.secret.is_some(),
+                )),
+                env: Some(build_submitter_env(application, cluster)),
+                ..Container::default()
+            }],
+            ..PodSpec::default()
+        }),
+        ..Pod::default()
+    })
+}
+
+#[derive(Debug)]
+struct SparkApplicationStrategy {}
+
+impl SparkApplicationStrategy {
+    pub fn new() -> SparkApplicationStrategy {
+        SparkApplicationStrategy {}
+    }
+}
+
+#[async_trait]
+impl ControllerStrategy for SparkApplicationStrategy {
+    type Item = SparkApplication;
+    type State = SparkApplicationState;
+    type Error = Error;
+
+    async fn init_reconcile_state(
+        &self,
+        context: ReconciliationContext<Self::Item>,
+    ) -> Result<Self::State, Self::Error> {
+        Ok(SparkApplicationState {
+            context,
+            http_client: master_api::build_http_client()?,
+            cluster: None,
+            master_pods: vec![],
+        })
+    }
+}
+
+/// This creates an instance of a [`Controller`] which waits for incoming events and reconciles
+/// SparkApplications.
+///
+/// This is an async method and the returned future needs to be consumed to make progress.
+pub async fn create_application_controller(client: Client) {
+    let application_api: Api<SparkApplication> = client.get_all_api();
+    let pods_api: Api<Pod> = client.get_all_api();
+
+    let controller = Controller::new(application_api).owns(pods_api, ListParams::default());
+
+    let strategy = SparkApplicationStrategy::new();
+
+    controller
+        .run(client, strategy, Duration::from_secs(10))
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_application_state() {
+        assert_eq!(
+            convert_application_state("WAITING"),
+            ApplicationState::Running
+        );
+        assert_eq!(
+            convert_application_state("FINISHED"),
+            ApplicationState::Finished
+        );
+        assert_eq!(
+            convert_application_state("KILLED"),
+            ApplicationState::Failed
+        );
+    }
+
+    #[test]
+    fn test_build_submitter_pod() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+        let mut application: SparkApplication = serde_json::from_value(json!({
+            "apiVersion": "spark.stackable.tech/v1",
+            "kind": "SparkApplication",
+            "metadata": { "name": "pi", "namespace": "default" },
+            "spec": {
+                "sparkCluster": "spark-cluster",
+                "mainApplicationFile": "examples/jars/spark-examples.jar",
+                "mainClass": "org.apache.spark.examples.SparkPi"
+            }
+        }))
+        .unwrap();
+        application.metadata.uid = Some("67890".to_string());
+
+        let master_pods = stackable_spark_test_utils::create_master_pods();
+        let master_urls = stackable_spark_test_utils::create_master_urls();
+
+        let pod = build_submitter_pod(
+            &application,
+            &spark_cluster,
+            &master_urls,
+            master_pods.first(),
+        )
+        .unwrap();
+
+        assert_eq!(pod.metadata.name, Some("pi-submitter".to_string()));
+        let labels = pod.metadata.labels.unwrap();
+        assert_eq!(
+            labels.get(APP_COMPONENT_LABEL),
+            Some(&SUBMITTER_COMPONENT.to_string())
+        );
+
+        let spec = pod.spec.unwrap();
+        assert_eq!(spec.restart_policy, Some("Never".to_string()));
+        // the stackable agent runtime places the submitter on a master node
+        assert_eq!(
+            spec.node_name,
+            master_pods[0].spec.as_ref().unwrap().node_name
+        );
+        assert!(spec.containers[0]
+            .command
+            .as_ref()
+            .unwrap()
+            .contains(&"org.apache.spark.examples.SparkPi".to_string()));
+    }
+
+    #[test]
+    fn test_build_submitter_env() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        let application: SparkApplication = serde_json::from_value(json!({
+            "apiVersion": "spark.stackable.tech/v1",
+            "kind": "SparkApplication",
+            "metadata": { "name": "pi", "namespace": "default" },
+            "spec": {
+                "sparkCluster": "spark-cluster",
+                "mainApplicationFile": "examples/jars/spark-examples.jar"
+            }
+        }))
+        .unwrap();
+
+        spark_cluster.spec.secret = Some("secret".to_string());
+        spark_cluster.spec.runtime = Some(Runtime::Kubernetes);
+        let env = build_submitter_env(&application, &spark_cluster);
+
+        // the secret is only referenced, never passed as value
+        let auth_secret = env
+            .iter()
+            .find(|env_var| env_var.name == SPARK_ENV_AUTH_SECRET)
+            .unwrap();
+        assert_eq!(auth_secret.value, None);
+        let secret_key_ref = auth_secret
+            .value_from
+            .as_ref()
+            .and_then(|source| source.secret_key_ref.as_ref())
+            .unwrap();
+        assert_eq!(secret_key_ref.name, Some("pi-auth".to_string()));
+
+        let driver_host = env
+            .iter()
+            .find(|env_var| env_var.name == SPARK_ENV_DRIVER_HOST)
+            .unwrap();
+        assert_eq!(
+            driver_host
+                .value_from
+                .as_ref()
+                .and_then(|source| source.field_ref.as_ref())
+                .map(|field_ref| field_ref.field_path.as_str()),
+            Some(POD_IP_FIELD_PATH)
+        );
+
+        spark_cluster.spec.secret = None;
+        spark_cluster.spec.runtime = Some(Runtime::StackableAgent);
+        assert!(build_submitter_env(&application, &spark_cluster).is_empty());
+    }
+}
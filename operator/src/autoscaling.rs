@@ -0,0 +1,288 @@
+//! This module contains the autoscaling of worker role groups. The desired instances are
+//! derived from the core utilization and the waiting applications reported by the alive
+//! master. Scaling down requires the graceful decommission of workers (Spark 3.1 and the
+//! kubernetes runtime) and is rejected otherwise. It only removes workers that were idle at the
+//! last master poll, busy workers postpone the scale down. The preStop hook of a removed worker
+//! decommissions it and waits for executors started after the poll to exit.
+//! With Node placement the scale up is limited to the nodes matching the role group.
+use crate::master_api;
+use crate::master_api::MasterState;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::Pod;
+use stackable_spark_crd::{Autoscaling, ClusterInfo};
+use std::convert::TryFrom;
+
+/// The desired instances of a role group and the reason for them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScalingDecision {
+    pub desired_instances: u16,
+    pub message: String,
+}
+
+/// Return the percentage of used worker cores.
+///
+/// # Arguments
+/// * `cluster_info` - The cluster info derived from the alive master
+///
+pub fn get_core_utilization(cluster_info: &ClusterInfo) -> usize {
+    if cluster_info.cores == 0 {
+        return 0;
+    }
+
+    cluster_info.cores_used * 100 / cluster_info.cores
+}
+
+/// Compute the desired instances of a role group. The instances are scaled proportionally
+/// to the ratio of core utilization and target utilization. Waiting applications add at
+/// least one instance. The result is limited to the min and max instances.
+///
+/// # Arguments
+/// * `autoscaling` - The autoscaling settings of the role group
+/// * `current_instances` - The current instances of the role group
+/// * `cluster_info` - The cluster info derived from the alive master
+///
+pub fn compute_desired_instances(
+    autoscaling: &Autoscaling,
+    current_instances: u16,
+    cluster_info: &ClusterInfo,
+) -> ScalingDecision {
+    let utilization = get_core_utilization(cluster_info);
+    let target = usize::from(autoscaling.get_target_core_utilization().max(1));
+    let current = usize::from(current_instances);
+    let proportional = (current * utilization + target - 1) / target;
+
+    let (desired, reason) = if cluster_info.waiting_applications > 0 {
+        (
+            proportional.max(current + 1),
+            format!(
+                "{} application(s) waiting for resources",
+                cluster_info.waiting_applications
+            ),
+        )
+    } else if utilization > target {
+        (
+            proportional,
+            format!("Core utilization {}% above target {}%", utilization, target),
+        )
+    } else if utilization < target {
+        (
+            proportional,
+            format!("Core utilization {}% below target {}%", utilization, target),
+        )
+    } else {
+        (current, format!("Core utilization at target {}%", target))
+    };
+
+    let min = usize::from(autoscaling.min_instances);
+    let max = usize::from(autoscaling.max_instances.max(autoscaling.min_instances));
+    let desired = desired.max(min).min(max) as u16;
+
+    ScalingDecision {
+        desired_instances: desired,
+        message: format!("{} ({} -> {})", reason, current_instances, desired),
+    }
+}
+
+/// Check if the role group was scaled too recently to be scaled (up or down) again.
+///
+/// # Arguments
+/// * `autoscaling` - The autoscaling settings of the role group
+/// * `scale_up` - Whether the role group would be scaled up or down
+/// * `last_scale_time` - The time of the last scaling (RFC3339)
+/// * `now` - The current time
+///
+pub fn is_in_cooldown(
+    autoscaling: &Autoscaling,
+    scale_up: bool,
+    last_scale_time: Option<&str>,
+    now: DateTime<Utc>,
+) -> bool {
+    let cooldown = if scale_up {
+        autoscaling.get_scale_up_cooldown()
+    } else {
+        autoscaling.get_scale_down_cooldown()
+    };
+
+    last_scale_time
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map(|last| {
+            (now - last.with_timezone(&Utc))
+                .to_std()
+                .map(|elapsed| elapsed < cooldown)
+                .unwrap_or(true)
+        })
+        .unwrap_or(false)
+}
+
+/// Limit a scale down to idle workers. Pods at the highest positions are removed first, so
+/// the instances are only decreased as long as these workers ran no executors at the last
+/// master poll.
+///
+/// # Arguments
+/// * `current_instances` - The current instances of the role group
+/// * `desired_instances` - The desired (lower) instances of the role group
+/// * `pods_by_position` - The pod at every position of the role group (None if missing)
+/// * `alive_master` - The state of the alive master
+///
+pub fn limit_scale_down(
+    current_instances: u16,
+    desired_instances: u16,
+    pods_by_position: &[Option<Pod>],
+    alive_master: &MasterState,
+) -> u16 {
+    let mut instances = current_instances;
+
+    while instances > desired_instances {
+        match pods_by_position.get(usize::from(instances) - 1) {
+            Some(Some(pod)) if !master_api::is_worker_idle(pod, alive_master) => break,
+            _ => instances -= 1,
+        }
+    }
+
+    instances
+}
+
+/// Limit a scale up to the nodes matching the selector of the role group. With Node placement
+/// every pod needs its own node, more instances would never be created.
+///
+/// # Arguments
+/// * `current_instances` - The current instances of the role group
+/// * `desired_instances` - The desired (higher) instances of the role group
+/// * `eligible_node_count` - The number of nodes matching the selector of the role group
+///
+pub fn limit_scale_up(
+    current_instances: u16,
+    desired_instances: u16,
+    eligible_node_count: usize,
+) -> u16 {
+    let max_instances = u16::try_from(eligible_node_count).unwrap_or(u16::MAX);
+    desired_instances.min(max_instances.max(current_instances))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::master_api::WorkerState;
+    use crate::pod_utils;
+    use stackable_spark_crd::{SparkCluster, SparkNodeType};
+    use stackable_spark_test_utils::cluster::{Data, TestSparkCluster};
+
+    fn build_autoscaling() -> Autoscaling {
+        Autoscaling {
+            min_instances: 1,
+            max_instances: 5,
+            target_core_utilization: Some(80),
+            scale_up_cooldown_seconds: Some(60),
+            scale_down_cooldown_seconds: None,
+            decommission_timeout_seconds: None,
+        }
+    }
+
+    fn build_cluster_info(cores: usize, cores_used: usize, waiting: usize) -> ClusterInfo {
+        ClusterInfo {
+            cores,
+            cores_used,
+            waiting_applications: waiting,
+            ..ClusterInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_compute_desired_instances() {
+        let autoscaling = build_autoscaling();
+
+        // 100% > 80% -> scale up proportionally
+        let decision = compute_desired_instances(&autoscaling, 2, &build_cluster_info(8, 8, 0));
+        assert_eq!(decision.desired_instances, 3);
+
+        // waiting applications add at least one instance
+        let decision = compute_desired_instances(&autoscaling, 2, &build_cluster_info(8, 4, 1));
+        assert_eq!(decision.desired_instances, 3);
+        assert!(decision.message.starts_with("1 application(s) waiting"));
+
+        // 25% < 80% -> scale down proportionally
+        let decision = compute_desired_instances(&autoscaling, 4, &build_cluster_info(16, 4, 0));
+        assert_eq!(decision.desired_instances, 2);
+
+        // idle clusters are limited to the min instances, busy ones to the max instances
+        let decision = compute_desired_instances(&autoscaling, 4, &build_cluster_info(16, 0, 0));
+        assert_eq!(decision.desired_instances, 1);
+        let decision = compute_desired_instances(&autoscaling, 5, &build_cluster_info(20, 20, 3));
+        assert_eq!(decision.desired_instances, 5);
+    }
+
+    #[test]
+    fn test_is_in_cooldown() {
+        let autoscaling = build_autoscaling();
+        let now = DateTime::parse_from_rfc3339("2021-06-01T12:02:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert!(!is_in_cooldown(&autoscaling, true, None, now));
+        assert!(!is_in_cooldown(
+            &autoscaling,
+            true,
+            Some("2021-06-01T12:00:00Z"),
+            now
+        ));
+        // the default scale down cooldown is 5 minutes
+        assert!(is_in_cooldown(
+            &autoscaling,
+            false,
+            Some("2021-06-01T12:00:00Z"),
+            now
+        ));
+    }
+
+    #[test]
+    fn test_limit_scale_down() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+        let master_urls = stackable_spark_test_utils::create_master_urls();
+
+        let build_worker = |node_name: &str| {
+            pod_utils::build_pod(
+                &spark_cluster,
+                node_name,
+                TestSparkCluster::WORKER_1_ROLE_GROUP,
+                &SparkNodeType::Worker,
+                &master_urls,
+            )
+            .unwrap()
+        };
+        let pods_by_position = vec![
+            Some(build_worker("node_a")),
+            Some(build_worker("node_b")),
+            None,
+            Some(build_worker("node_d")),
+        ];
+
+        let alive_master = MasterState {
+            workers: vec![
+                WorkerState {
+                    host: "node_b".to_string(),
+                    state: "ALIVE".to_string(),
+                    coresused: 2,
+                },
+                WorkerState {
+                    host: "node_d".to_string(),
+                    state: "ALIVE".to_string(),
+                    coresused: 0,
+                },
+            ],
+            ..MasterState::default()
+        };
+
+        // node_d is idle and position 2 is missing, node_b is busy
+        assert_eq!(limit_scale_down(4, 1, &pods_by_position, &alive_master), 2);
+        assert_eq!(limit_scale_down(4, 3, &pods_by_position, &alive_master), 3);
+    }
+
+    #[test]
+    fn test_limit_scale_up() {
+        assert_eq!(limit_scale_up(2, 5, 3), 3);
+        assert_eq!(limit_scale_up(2, 3, 5), 3);
+        // never scale down to the node count
+        assert_eq!(limit_scale_up(4, 5, 3), 4);
+    }
+}
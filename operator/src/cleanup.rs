@@ -0,0 +1,212 @@
+//! This module contains the orderly teardown of a deleted SparkCluster. The controller adds
+//! the `spark.stackable.tech/cleanup` finalizer to every cluster, so Kubernetes keeps a
+//! deleted cluster until the operator removed its pods (workers first, masters last), its
+//! PersistentVolumeClaims (depending on the retention policy) and its ConfigMaps.
+use crate::pod_utils::APP_NAME;
+use k8s_openapi::api::core::v1::{ConfigMap, PersistentVolumeClaim};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::ListParams;
+use kube::{Api, Resource};
+use serde_json::json;
+use stackable_operator::client::Client;
+use stackable_operator::error::OperatorResult;
+use stackable_operator::labels::{APP_INSTANCE_LABEL, APP_NAME_LABEL};
+use stackable_spark_crd::{SparkCluster, SparkNodeType};
+
+/// Finalizer that blocks the deletion of a SparkCluster until its teardown finished
+pub const FINALIZER_NAME: &str = "spark.stackable.tech/cleanup";
+
+/// The order in which the pods of a deleted cluster are removed. Workers are removed first
+/// so their executors are stopped while the masters are still available.
+pub const TEARDOWN_ORDER: [SparkNodeType; 3] = [
+    SparkNodeType::Worker,
+    SparkNodeType::HistoryServer,
+    SparkNodeType::Master,
+];
+
+/// Returns true if the cluster was deleted and waits for its finalizers.
+///
+/// # Arguments
+/// * `cluster` - Spark cluster custom resource
+///
+pub fn is_being_deleted(cluster: &SparkCluster) -> bool {
+    cluster.metadata.deletion_timestamp.is_some()
+}
+
+/// Returns true if the cleanup finalizer is set on the cluster.
+///
+/// # Arguments
+/// * `cluster` - Spark cluster custom resource
+///
+pub fn has_finalizer(cluster: &SparkCluster) -> bool {
+    cluster
+        .metadata
+        .finalizers
+        .as_ref()
+        .map(|finalizers| finalizers.iter().any(|name| name == FINALIZER_NAME))
+        .unwrap_or(false)
+}
+
+/// Add or remove the cleanup finalizer in the given finalizers. Other finalizers are kept.
+///
+/// # Arguments
+/// * `finalizers` - The current finalizers of the cluster
+/// * `present` - Whether the cleanup finalizer should be present
+///
+pub fn build_finalizers(finalizers: &[String], present: bool) -> Vec<String> {
+    let mut finalizers = finalizers
+        .iter()
+        .filter(|name| *name != FINALIZER_NAME)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if present {
+        finalizers.push(FINALIZER_NAME.to_string());
+    }
+
+    finalizers
+}
+
+/// Add the cleanup finalizer to the cluster.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `cluster` - Spark cluster custom resource
+///
+pub async fn add_finalizer(
+    client: &Client,
+    cluster: &SparkCluster,
+) -> OperatorResult<SparkCluster> {
+    patch_finalizers(client, cluster, true).await
+}
+
+/// Remove the cleanup finalizer from the cluster. Kubernetes deletes the cluster afterwards
+/// if no other finalizers are left.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `cluster` - Spark cluster custom resource
+///
+pub async fn remove_finalizer(
+    client: &Client,
+    cluster: &SparkCluster,
+) -> OperatorResult<SparkCluster> {
+    patch_finalizers(client, cluster, false).await
+}
+
+/// Write the finalizers with the resourceVersion as precondition, so finalizers added by
+/// others in the meantime are not overwritten (the patch fails with a conflict instead).
+async fn patch_finalizers(
+    client: &Client,
+    cluster: &SparkCluster,
+    present: bool,
+) -> OperatorResult<SparkCluster> {
+    let finalizers = build_finalizers(
+        cluster.metadata.finalizers.as_deref().unwrap_or_default(),
+        present,
+    );
+
+    client
+        .merge_patch(
+            cluster,
+            json!({
+                "metadata": {
+                    "finalizers": finalizers,
+                    "resourceVersion": cluster.metadata.resource_version
+                }
+            }),
+        )
+        .await
+}
+
+/// Returns true if the object has an owner reference to the cluster.
+///
+/// # Arguments
+/// * `metadata` - The metadata of the object
+/// * `cluster` - Spark cluster custom resource
+///
+pub fn is_owned_by(metadata: &ObjectMeta, cluster: &SparkCluster) -> bool {
+    match (&metadata.owner_references, &cluster.metadata.uid) {
+        (Some(owner_references), Some(uid)) => {
+            owner_references.iter().any(|owner| &owner.uid == uid)
+        }
+        _ => false,
+    }
+}
+
+/// List all ConfigMaps in the namespace of the cluster that are owned by the cluster.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `cluster` - Spark cluster custom resource
+///
+pub async fn list_owned_config_maps(
+    client: &Client,
+    cluster: &SparkCluster,
+) -> OperatorResult<Vec<ConfigMap>> {
+    let api: Api<ConfigMap> = client.get_api(cluster.namespace().as_deref());
+    let config_maps = api.list(&ListParams::default()).await?;
+
+    Ok(config_maps
+        .items
+        .into_iter()
+        .filter(|config_map| is_owned_by(&config_map.metadata, cluster))
+        .collect())
+}
+
+/// List all PersistentVolumeClaims that carry the name and instance labels of the cluster.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `cluster` - Spark cluster custom resource
+///
+pub async fn list_persistent_volume_claims(
+    client: &Client,
+    cluster: &SparkCluster,
+) -> OperatorResult<Vec<PersistentVolumeClaim>> {
+    let api: Api<PersistentVolumeClaim> = client.get_api(cluster.namespace().as_deref());
+    let label_selector = format!(
+        "{}={},{}={}",
+        APP_NAME_LABEL,
+        APP_NAME,
+        APP_INSTANCE_LABEL,
+        cluster.name()
+    );
+    let pvcs = api
+        .list(&ListParams::default().labels(&label_selector))
+        .await?;
+
+    Ok(pvcs.items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+
+    #[test]
+    fn test_build_finalizers() {
+        let finalizers = vec!["other".to_string()];
+
+        let added = build_finalizers(&finalizers, true);
+        assert_eq!(added, vec!["other".to_string(), FINALIZER_NAME.to_string()]);
+        // adding twice does not duplicate the finalizer
+        assert_eq!(build_finalizers(&added, true), added);
+        assert_eq!(build_finalizers(&added, false), finalizers);
+    }
+
+    #[test]
+    fn test_is_owned_by() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+
+        let mut metadata = ObjectMeta::default();
+        assert!(!is_owned_by(&metadata, &spark_cluster));
+
+        metadata.owner_references = Some(vec![OwnerReference {
+            uid: "12345".to_string(),
+            ..OwnerReference::default()
+        }]);
+        assert!(is_owned_by(&metadata, &spark_cluster));
+    }
+}
@@ -1,3 +1,7 @@
+use crate::events;
+use crate::events::{EventReason, EventType};
+use crate::status;
+use chrono::{DateTime, Utc};
 use k8s_openapi::api::core::v1::Pod;
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
 use kube::Api;
@@ -5,20 +9,25 @@ use kube::Resource;
 use serde::de::DeserializeOwned;
 use serde_json::json;
 use stackable_operator::client::Client;
+use stackable_operator::command_controller::Command;
 use stackable_operator::error::OperatorResult;
 use stackable_spark_crd::{
-    ClusterExecutionStatus, CurrentCommand, Restart, SparkCluster, Start, Stop,
+    Cancel, ClusterExecutionStatus, CommandRetention, CommandStatus, CommandStatusMessage,
+    CurrentCommand, Pause, Restart, Resume, Scale, SparkCluster, Start, Stop,
 };
 use std::collections::HashMap;
 use std::fmt::Debug;
 
-use kube::api::PostParams;
+use kube::api::ListParams;
+use stackable_operator::command_controller::Command;
 use stackable_operator::reconcile::ReconcileFunctionAction;
 use std::time::Duration;
-use tracing::info;
+use tracing::{info, warn};
 
 const COMMAND_STATUS_LABEL: &str = "spark.stackable.tech/status";
 const COMMAND_STATUS_VALUE: &str = "done";
+/// Interval of the cleanup of finished commands if no TTL expires earlier
+const COMMAND_CLEANUP_INTERVAL_SECONDS: i64 = 600;
 
 /// Collection of all required commands defined in the crd crate.
 /// CommandType can easily be stored in a vector to access all available commands.
@@ -29,6 +38,9 @@ pub enum CommandType {
     Restart(Restart),
     Start(Start),
     Stop(Stop),
+    Scale(Scale),
+    Pause(Pause),
+    Resume(Resume),
 }
 
 impl CommandType {
@@ -38,6 +50,9 @@ impl CommandType {
             CommandType::Restart(restart) => restart.name(),
             CommandType::Start(start) => start.name(),
             CommandType::Stop(stop) => stop.name(),
+            CommandType::Scale(scale) => scale.name(),
+            CommandType::Pause(pause) => pause.name(),
+            CommandType::Resume(resume) => resume.name(),
         }
     }
 
@@ -47,6 +62,9 @@ impl CommandType {
             CommandType::Restart(_) => Restart::kind(&()).to_string(),
             CommandType::Start(_) => Start::kind(&()).to_string(),
             CommandType::Stop(_) => Stop::kind(&()).to_string(),
+            CommandType::Scale(_) => Scale::kind(&()).to_string(),
+            CommandType::Pause(_) => Pause::kind(&()).to_string(),
+            CommandType::Resume(_) => Resume::kind(&()).to_string(),
         }
     }
 
@@ -56,9 +74,97 @@ impl CommandType {
             CommandType::Restart(restart) => restart.meta().creation_timestamp.clone(),
             CommandType::Start(start) => start.meta().creation_timestamp.clone(),
             CommandType::Stop(stop) => stop.meta().creation_timestamp.clone(),
+            CommandType::Scale(scale) => scale.meta().creation_timestamp.clone(),
+            CommandType::Pause(pause) => pause.meta().creation_timestamp.clone(),
+            CommandType::Resume(resume) => resume.meta().creation_timestamp.clone(),
         }
     }
 
+    /// Return the timeout after which the running command is retried or fails
+    pub fn get_timeout(&self) -> Option<Duration> {
+        let timeout_seconds = match self {
+            CommandType::Restart(restart) => restart.spec.timeout_seconds,
+            CommandType::Start(start) => start.spec.timeout_seconds,
+            CommandType::Stop(stop) => stop.spec.timeout_seconds,
+            CommandType::Scale(scale) => scale.spec.timeout_seconds,
+            CommandType::Pause(pause) => pause.spec.timeout_seconds,
+            CommandType::Resume(resume) => resume.spec.timeout_seconds,
+        };
+        timeout_seconds.map(Duration::from_secs)
+    }
+
+    /// Return the number of retries after a timeout before the command fails
+    pub fn get_max_retries(&self) -> u16 {
+        let retries = match self {
+            CommandType::Restart(restart) => restart.spec.retries,
+            CommandType::Start(start) => start.spec.retries,
+            CommandType::Stop(stop) => stop.spec.retries,
+            CommandType::Scale(scale) => scale.spec.retries,
+            CommandType::Pause(pause) => pause.spec.retries,
+            CommandType::Resume(resume) => resume.spec.retries,
+        };
+        retries.unwrap_or(0)
+    }
+
+    /// Publish an event for the command custom resource
+    ///
+    /// # Arguments
+    /// * `client` - Kubernetes client
+    /// * `event_type` - Normal or Warning
+    /// * `reason` - Machine readable reason for the event
+    /// * `message` - Human readable description of the event
+    ///
+    pub async fn publish_event(
+        &self,
+        client: &Client,
+        event_type: EventType,
+        reason: EventReason,
+        message: &str,
+    ) {
+        match self {
+            CommandType::Restart(restart) => {
+                events::publish_event(client, restart, event_type, reason, message).await
+            }
+            CommandType::Start(start) => {
+                events::publish_event(client, start, event_type, reason, message).await
+            }
+            CommandType::Stop(stop) => {
+                events::publish_event(client, stop, event_type, reason, message).await
+            }
+            CommandType::Scale(scale) => {
+                events::publish_event(client, scale, event_type, reason, message).await
+            }
+            CommandType::Pause(pause) => {
+                events::publish_event(client, pause, event_type, reason, message).await
+            }
+            CommandType::Resume(resume) => {
+                events::publish_event(client, resume, event_type, reason, message).await
+            }
+        }
+    }
+
+    /// Publish the same event for the command custom resource and the spark cluster
+    ///
+    /// # Arguments
+    /// * `client` - Kubernetes client
+    /// * `cluster` - Spark cluster custom resource
+    /// * `event_type` - Normal or Warning
+    /// * `reason` - Machine readable reason for the event
+    /// * `message` - Human readable description of the event
+    ///
+    async fn publish_command_and_cluster_event(
+        &self,
+        client: &Client,
+        cluster: &SparkCluster,
+        event_type: EventType,
+        reason: EventReason,
+        message: &str,
+    ) {
+        self.publish_event(client, event_type.clone(), reason.clone(), message)
+            .await;
+        events::publish_event(client, cluster, event_type, reason, message).await;
+    }
+
     /// Implementation of command behavior when starting to execute the command
     /// e.g. delete pods for restart
     ///
@@ -81,6 +187,17 @@ impl CommandType {
         );
         // set the current_command in the cluster custom resource status
         let updated_cluster = update_current_command(client, cluster, current_command).await?;
+        self.publish_command_and_cluster_event(
+            client,
+            cluster,
+            EventType::Normal,
+            EventReason::CommandStarted,
+            &format!(
+                "Started [{}] command [{}]",
+                current_command.command_type, current_command.command_ref
+            ),
+        )
+        .await;
         // apply command specific action
         return match self {
             CommandType::Restart(_) => {
@@ -116,6 +233,30 @@ impl CommandType {
                 .await?;
                 Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(10)))
             }
+            CommandType::Scale(scale) => {
+                scale_role_group(client, &updated_cluster, scale).await?;
+                // the changed instances are picked up by the next reconcile
+                Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(10)))
+            }
+            CommandType::Pause(_) => {
+                update_cluster_execution_status(
+                    client,
+                    &updated_cluster,
+                    &ClusterExecutionStatus::Paused,
+                )
+                .await?;
+                Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(10)))
+            }
+            CommandType::Resume(_) => {
+                update_cluster_execution_status(
+                    client,
+                    &updated_cluster,
+                    &ClusterExecutionStatus::Running,
+                )
+                .await?;
+                // the next reconcile runs without the pause restrictions
+                Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(10)))
+            }
         };
     }
 
@@ -132,13 +273,35 @@ impl CommandType {
         cluster: &mut SparkCluster,
         current_command: &CurrentCommand,
     ) -> OperatorResult<ReconcileFunctionAction> {
-        // the "Stop" command requires special treatment here. We do not want to finalize that
-        // command after the reconcile for pods (which would result in pods being created again
-        // even though the cluster should be stopped). So we finish the "Stop" command here after
-        // all pods are terminated and update the status label in the command.
-        if let CommandType::Stop(stop) = self {
-            finalize_current_command(client, cluster, &ClusterExecutionStatus::Stopped).await?;
-            update_command_label(client, stop).await?;
+        // the "Stop" and "Pause" commands require special treatment here. We do not want to
+        // finalize these commands after the reconcile for pods (which would result in pods being
+        // created or deleted even though the cluster should be stopped or paused). So we finish
+        // them here and update the status label in the command.
+        let final_status = match self {
+            CommandType::Stop(stop) => {
+                finish_command(client, stop).await?;
+                Some(ClusterExecutionStatus::Stopped)
+            }
+            CommandType::Pause(pause) => {
+                finish_command(client, pause).await?;
+                Some(ClusterExecutionStatus::Paused)
+            }
+            _ => None,
+        };
+
+        if let Some(final_status) = final_status {
+            finalize_current_command(client, cluster, &final_status).await?;
+            self.publish_command_and_cluster_event(
+                client,
+                cluster,
+                EventType::Normal,
+                EventReason::CommandFinished,
+                &format!(
+                    "Finished [{}] command [{}]",
+                    current_command.command_type, current_command.command_ref
+                ),
+            )
+            .await;
 
             info!(
                 "Finished [{}] command '{}'",
@@ -175,25 +338,288 @@ impl CommandType {
 
         finalize_current_command(client, cluster, &ClusterExecutionStatus::Running).await?;
 
-        // TODO: set label "done" to command to avoid retrieving it via list_commands
-        // (for now label selector is not available in list_commands so we need to check
-        // the label when retrieving all commands ourselves)
         match self {
             // TODO: better to requeue to avoid status conflicts?
             CommandType::Restart(restart) => {
-                update_command_label(client, restart).await?;
+                finish_command(client, restart).await?;
             }
             CommandType::Start(start) => {
-                update_command_label(client, start).await?;
+                finish_command(client, start).await?;
+            }
+            CommandType::Scale(scale) => {
+                finish_command(client, scale).await?;
+            }
+            CommandType::Resume(resume) => {
+                finish_command(client, resume).await?;
             }
             _ => {}
         }
 
+        self.publish_command_and_cluster_event(
+            client,
+            cluster,
+            EventType::Normal,
+            EventReason::CommandFinished,
+            &format!(
+                "Finished [{}] command [{}]",
+                self.get_type(),
+                self.get_name()
+            ),
+        )
+        .await;
+
         Ok(ReconcileFunctionAction::Continue)
     }
+
+    /// Implementation of behavior when the command timed out or was canceled. The command
+    /// ends in state `Error` and the current_command is removed so the next command can be
+    /// processed. The cluster execution status is not changed.
+    ///
+    /// # Arguments
+    /// * `client` - Kubernetes client
+    /// * `cluster` - Spark cluster custom resource
+    /// * `reason` - Reason of the failure
+    ///
+    pub async fn process_command_failed(
+        &self,
+        client: &Client,
+        cluster: &mut SparkCluster,
+        reason: &str,
+    ) -> OperatorResult<ReconcileFunctionAction> {
+        warn!(
+            "Aborting [{}] command '{}': {}",
+            self.get_type(),
+            self.get_name(),
+            reason
+        );
+
+        match self {
+            CommandType::Restart(restart) => fail_command(client, restart, reason).await?,
+            CommandType::Start(start) => fail_command(client, start, reason).await?,
+            CommandType::Stop(stop) => fail_command(client, stop, reason).await?,
+            CommandType::Scale(scale) => fail_command(client, scale, reason).await?,
+            CommandType::Pause(pause) => fail_command(client, pause, reason).await?,
+            CommandType::Resume(resume) => fail_command(client, resume, reason).await?,
+        }
+
+        clear_current_command(client, cluster).await?;
+
+        self.publish_command_and_cluster_event(
+            client,
+            cluster,
+            EventType::Warning,
+            EventReason::CommandFailed,
+            &format!(
+                "Aborted [{}] command [{}]: {}",
+                self.get_type(),
+                self.get_name(),
+                reason
+            ),
+        )
+        .await;
+
+        Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(10)))
+    }
+}
+
+/// What to do with the current command depending on its timeout.
+#[derive(Debug, PartialEq)]
+pub enum TimeoutAction {
+    /// The command did not time out (or has no timeout)
+    Wait,
+    /// The command timed out and is executed again
+    Retry,
+    /// The command timed out and no retries are left
+    Fail,
 }
 
-/// Finalize a finished command. Delete current_command and set the cluster_status.
+/// Check if the current command timed out and whether it should be retried.
+///
+/// # Arguments
+/// * `current_command` - The current command from the cluster status
+/// * `timeout` - The timeout of the command
+/// * `max_retries` - The number of retries after a timeout
+/// * `now` - The current time
+///
+pub fn check_timeout(
+    current_command: &CurrentCommand,
+    timeout: Option<Duration>,
+    max_retries: u16,
+    now: DateTime<Utc>,
+) -> TimeoutAction {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return TimeoutAction::Wait,
+    };
+
+    let started_at = match DateTime::parse_from_rfc3339(&current_command.started_at) {
+        Ok(started_at) => started_at.with_timezone(&Utc),
+        Err(err) => {
+            warn!(
+                "Could not parse the start time [{}] of [{}] command '{}': {}",
+                current_command.started_at,
+                current_command.command_type,
+                current_command.command_ref,
+                err
+            );
+            return TimeoutAction::Wait;
+        }
+    };
+
+    match (now - started_at).to_std() {
+        Ok(elapsed) if elapsed >= timeout => {
+            if current_command.retries < max_retries {
+                TimeoutAction::Retry
+            } else {
+                TimeoutAction::Fail
+            }
+        }
+        _ => TimeoutAction::Wait,
+    }
+}
+
+/// Check if an operator error was caused by a resource that does not exist (anymore).
+///
+/// # Arguments
+/// * `error` - The error returned by the Kubernetes client
+///
+pub fn is_not_found(error: &stackable_operator::error::Error) -> bool {
+    matches!(
+        error,
+        stackable_operator::error::Error::KubeError {
+            source: kube::Error::Api(response)
+        } if response.code == 404
+    )
+}
+
+/// Check if an operator error was caused by a conflicting write, i.e. the resource was
+/// changed or created by someone else in the meantime.
+///
+/// # Arguments
+/// * `error` - The error returned by the Kubernetes client
+///
+pub fn is_conflict(error: &stackable_operator::error::Error) -> bool {
+    matches!(
+        error,
+        stackable_operator::error::Error::KubeError {
+            source: kube::Error::Api(response)
+        } if response.code == 409
+    )
+}
+
+/// Remove the current_command from the cluster status without changing the cluster
+/// execution status. Used if the current command was aborted or deleted.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `cluster` - Spark cluster custom resource
+///
+pub async fn clear_current_command(
+    client: &Client,
+    cluster: &mut SparkCluster,
+) -> OperatorResult<SparkCluster> {
+    let cluster_execution_status = cluster
+        .status
+        .as_ref()
+        .and_then(|status| status.cluster_execution_status.clone())
+        .unwrap_or(ClusterExecutionStatus::Running);
+
+    finalize_current_command(client, cluster, &cluster_execution_status).await
+}
+
+/// Set the status of a command to `Error` with the given reason and mark it as done.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `command` - Command custom resource
+/// * `reason` - Reason of the failure
+///
+async fn fail_command<T>(client: &Client, command: &T, reason: &str) -> OperatorResult<()>
+where
+    T: Resource + Clone + Debug + DeserializeOwned,
+    <T as kube::Resource>::DynamicType: Default,
+{
+    let status = CommandStatus {
+        started_at: None,
+        finished_at: Some(get_current_timestamp()),
+        message: Some(CommandStatusMessage::Error),
+        reason: Some(reason.to_string()),
+    };
+
+    status::patch_status(client, command, json!(status)).await?;
+    update_command_label(client, command).await?;
+    Ok(())
+}
+
+/// Return the oldest Cancel command of the cluster that was not processed yet.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `cluster` - Spark cluster custom resource
+///
+pub async fn get_next_cancel_command(
+    client: &Client,
+    cluster: &SparkCluster,
+) -> OperatorResult<Option<Cancel>> {
+    let cancel_commands: Vec<Cancel> =
+        list_commands(client, cluster, &pending_commands_selector()).await?;
+
+    Ok(select_next_cancel_command(cancel_commands, cluster))
+}
+
+/// Return the oldest Cancel command that belongs to the cluster. Cancel commands of other
+/// clusters (by name or namespace) are ignored.
+///
+/// # Arguments
+/// * `cancel_commands` - The pending Cancel commands
+/// * `cluster` - Spark cluster custom resource
+///
+fn select_next_cancel_command(
+    cancel_commands: Vec<Cancel>,
+    cluster: &SparkCluster,
+) -> Option<Cancel> {
+    let mut cancel_commands: Vec<Cancel> = cancel_commands
+        .into_iter()
+        .filter(|cancel| cancel.get_owner_name() == cluster.name())
+        .filter(|cancel| cancel.namespace() == cluster.namespace())
+        .collect();
+
+    cancel_commands.sort_by_key(|cancel| cancel.meta().creation_timestamp.clone());
+
+    cancel_commands.into_iter().next()
+}
+
+/// Mark a Cancel command as done and publish an event for it.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `cancel` - The processed Cancel command
+/// * `message` - Human readable description of what was canceled
+///
+pub async fn finalize_cancel_command(
+    client: &Client,
+    cancel: &Cancel,
+    message: &str,
+) -> OperatorResult<()> {
+    info!(
+        "Finished [{}] command '{}'",
+        Cancel::kind(&()),
+        cancel.name()
+    );
+    finish_command(client, cancel).await?;
+    events::publish_event(
+        client,
+        cancel,
+        EventType::Normal,
+        EventReason::CommandCanceled,
+        message,
+    )
+    .await;
+    Ok(())
+}
+
+/// Finalize a finished command. Delete current_command and set the cluster_status. Only
+/// these two status fields are patched, all other fields are left untouched.
 ///
 /// # Arguments
 /// * `client` - Kubernetes client
@@ -205,56 +631,97 @@ async fn finalize_current_command(
     cluster: &mut SparkCluster,
     cluster_execution_status: &ClusterExecutionStatus,
 ) -> OperatorResult<SparkCluster> {
-    // let patch = json_patch::Patch(vec![PatchOperation::Remove(RemoveOperation {
-    //     path: "/status/currentCommand".to_string(),
-    // })]);
-    //
-    // client.json_patch(cluster, patch).await
-
-    // TODO: Now we replace the whole status just to remove the current_command.
-    // json_patch delete / replace did not work (check commented code above). If i used it no
-    // errors appeared but nothing was deleted / replaced either. Needs investigation.
-    if let Some(status) = &mut cluster.status {
-        status.current_command = None;
-        status.cluster_execution_status = Some(cluster_execution_status.clone());
-
-        let api: Api<SparkCluster> = client.get_api(cluster.namespace().as_deref());
-
-        return Ok(api
-            .replace_status(
-                &cluster.name(),
-                &PostParams {
-                    dry_run: false,
-                    field_manager: None,
-                },
-                serde_json::to_vec(&cluster)?,
-            )
-            .await?);
-    }
+    *cluster = status::patch_status(
+        client,
+        cluster,
+        json!({
+            "currentCommand": null,
+            "clusterExecutionStatus": cluster_execution_status
+        }),
+    )
+    .await?;
 
     Ok(cluster.clone())
 }
 
-/// Set/Update the cluster status of the main custom resource.
+/// Set the instances of the worker role group referenced by the scale command. Only the
+/// instances of that role group are patched. The command is ignored while
+/// `spec.workers.replicas` is set because the replicas override the instances of all role
+/// groups.
 ///
 /// # Arguments
 /// * `client` - Kubernetes client
 /// * `cluster` - Spark cluster custom resource
-/// * `cluster_status` - Desired cluster status to be set
+/// * `scale` - The scale command
 ///
-async fn update_cluster_execution_status(
+async fn scale_role_group(
     client: &Client,
     cluster: &SparkCluster,
-    cluster_execution_status: &ClusterExecutionStatus,
+    scale: &Scale,
 ) -> OperatorResult<SparkCluster> {
+    let role_group = &scale.spec.role_group;
+
+    if !cluster.spec.workers.selectors.contains_key(role_group) {
+        warn!(
+            "Ignoring [{}] command '{}': worker role group [{}] does not exist",
+            Scale::kind(&()),
+            scale.name(),
+            role_group
+        );
+        return Ok(cluster.clone());
+    }
+
+    if cluster.spec.workers.replicas.is_some() {
+        warn!(
+            "Ignoring [{}] command '{}': spec.workers.replicas is set and overrides the instances of role group [{}]",
+            Scale::kind(&()),
+            scale.name(),
+            role_group
+        );
+        return Ok(cluster.clone());
+    }
+
+    info!(
+        "Scaling worker role group [{}] to [{}] instances",
+        role_group, scale.spec.instances
+    );
+
     client
-        .merge_patch_status(
+        .merge_patch(
             cluster,
-            &json!({ "clusterExecutionStatus": cluster_execution_status }),
+            json!({
+                "spec": {
+                    "workers": {
+                        "selectors": {
+                            role_group: { "instances": scale.spec.instances }
+                        }
+                    }
+                }
+            }),
         )
         .await
 }
 
+/// Set/Update the cluster status of the main custom resource.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `cluster` - Spark cluster custom resource
+/// * `cluster_status` - Desired cluster status to be set
+///
+async fn update_cluster_execution_status(
+    client: &Client,
+    cluster: &SparkCluster,
+    cluster_execution_status: &ClusterExecutionStatus,
+) -> OperatorResult<SparkCluster> {
+    status::patch_status(
+        client,
+        cluster,
+        json!({ "clusterExecutionStatus": cluster_execution_status }),
+    )
+    .await
+}
+
 /// Set/Update the status of the main custom resource with the current command status.
 ///
 /// # Arguments
@@ -267,12 +734,40 @@ async fn update_current_command(
     cluster: &SparkCluster,
     current_command: &CurrentCommand,
 ) -> OperatorResult<SparkCluster> {
-    client
-        .merge_patch_status(cluster, &json!({ "currentCommand": current_command }))
-        .await
+    status::patch_status(
+        client,
+        cluster,
+        json!({ "currentCommand": current_command }),
+    )
+    .await
+}
+
+/// Set the status of a command to `Finished` and mark it as done.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `command` - Command custom resource
+///
+async fn finish_command<T>(client: &Client, command: &T) -> OperatorResult<()>
+where
+    T: Resource + Clone + Debug + DeserializeOwned,
+    <T as kube::Resource>::DynamicType: Default,
+{
+    status::patch_status(
+        client,
+        command,
+        json!({
+            "finishedAt": get_current_timestamp(),
+            "message": CommandStatusMessage::Finished
+        }),
+    )
+    .await?;
+    update_command_label(client, command).await?;
+    Ok(())
 }
 
 /// Update the labels of the command custom resource to finalize and ignore it in the future.
+/// Finished commands are excluded from all command listings via a label selector.
 ///
 /// # Arguments
 /// * `client` - Kubernetes client
@@ -283,7 +778,6 @@ where
     T: Resource + Clone + Debug + DeserializeOwned,
     <T as kube::Resource>::DynamicType: Default,
 {
-    // TODO: check if label exists first? Will be obsolete with label selector in list_commands
     let mut labels = HashMap::new();
     labels.insert(
         COMMAND_STATUS_LABEL.to_string(),
@@ -331,6 +825,24 @@ pub async fn get_command_from_ref(
                 .get::<stackable_spark_crd::Stop>(command, namespace)
                 .await?,
         ))
+    } else if command_type == Scale::kind(&()) {
+        Ok(CommandType::Scale(
+            client
+                .get::<stackable_spark_crd::Scale>(command, namespace)
+                .await?,
+        ))
+    } else if command_type == Pause::kind(&()) {
+        Ok(CommandType::Pause(
+            client
+                .get::<stackable_spark_crd::Pause>(command, namespace)
+                .await?,
+        ))
+    } else if command_type == Resume::kind(&()) {
+        Ok(CommandType::Resume(
+            client
+                .get::<stackable_spark_crd::Resume>(command, namespace)
+                .await?,
+        ))
     } else {
         Err(stackable_operator::error::Error::MissingCustomResource {
             name: command.to_string(),
@@ -339,78 +851,312 @@ pub async fn get_command_from_ref(
 }
 
 /// Collect and sort all available commands and return the first (the one with
-/// the oldest creation timestamp) element.
+/// the oldest creation timestamp) element. While the cluster is paused only Resume
+/// commands are considered, all other commands wait until the cluster is resumed.
 ///
 /// # Arguments
 /// * `client` - Kubernetes client
+/// * `cluster` - Spark cluster custom resource
+/// * `paused` - Whether the cluster is paused
 ///
-pub async fn get_next_command(client: &Client) -> OperatorResult<Option<CommandType>> {
-    let mut all_commands = collect_commands(client).await?;
+pub async fn get_next_command(
+    client: &Client,
+    cluster: &SparkCluster,
+    paused: bool,
+) -> OperatorResult<Option<CommandType>> {
+    let mut all_commands = collect_commands(client, cluster).await?;
+
+    if paused {
+        all_commands.retain(|command| matches!(command, CommandType::Resume(_)));
+    }
 
     all_commands.sort_by_key(|a| a.get_creation_timestamp());
 
     Ok(all_commands.into_iter().next())
 }
 
-/// Collect all different commands in one vector.
+/// Collect all pending commands of the cluster in one vector.
 ///
 /// # Arguments
 /// * `client` - Kubernetes client
+/// * `cluster` - Spark cluster custom resource
 ///
-async fn collect_commands(client: &Client) -> OperatorResult<Vec<CommandType>> {
+async fn collect_commands(
+    client: &Client,
+    cluster: &SparkCluster,
+) -> OperatorResult<Vec<CommandType>> {
+    let selector = pending_commands_selector();
     let mut all_commands = vec![];
-    let mut restart_commands: Vec<Restart> =
-        stackable_operator::command_controller::list_commands::<Restart>(client).await?;
-
-    let mut start_commands: Vec<Start> =
-        stackable_operator::command_controller::list_commands::<Start>(client).await?;
-
-    let mut stop_commands: Vec<Stop> =
-        stackable_operator::command_controller::list_commands::<Stop>(client).await?;
 
-    // TODO: We check for commands that are done here.
-    // will become obsolete with label selector in list_commands
-    while let Some(cmd) = restart_commands.pop() {
-        if is_command_done(&cmd) {
-            continue;
-        }
+    for cmd in list_commands::<Restart>(client, cluster, &selector).await? {
         all_commands.push(CommandType::Restart(cmd));
     }
 
-    while let Some(cmd) = start_commands.pop() {
-        if is_command_done(&cmd) {
-            continue;
-        }
+    for cmd in list_commands::<Start>(client, cluster, &selector).await? {
         all_commands.push(CommandType::Start(cmd));
     }
 
-    while let Some(cmd) = stop_commands.pop() {
-        if is_command_done(&cmd) {
-            continue;
-        }
+    for cmd in list_commands::<Stop>(client, cluster, &selector).await? {
         all_commands.push(CommandType::Stop(cmd));
     }
 
+    for cmd in list_commands::<Scale>(client, cluster, &selector).await? {
+        all_commands.push(CommandType::Scale(cmd));
+    }
+
+    for cmd in list_commands::<Pause>(client, cluster, &selector).await? {
+        all_commands.push(CommandType::Pause(cmd));
+    }
+
+    for cmd in list_commands::<Resume>(client, cluster, &selector).await? {
+        all_commands.push(CommandType::Resume(cmd));
+    }
+
     Ok(all_commands)
 }
 
-/// Check the label of a command custom resource for the "status" set to "done"
-/// That means the command was already processed.
+/// Label selector for commands that were not processed yet.
+fn pending_commands_selector() -> String {
+    format!("{}!={}", COMMAND_STATUS_LABEL, COMMAND_STATUS_VALUE)
+}
+
+/// Label selector for commands that finished or failed.
+fn finished_commands_selector() -> String {
+    format!("{}={}", COMMAND_STATUS_LABEL, COMMAND_STATUS_VALUE)
+}
+
+/// List the commands of one kind in the namespace of the cluster that match the label
+/// selector and belong to the cluster.
+///
 /// # Arguments
-/// * `command` - Command custom resource
+/// * `client` - Kubernetes client
+/// * `cluster` - Spark cluster custom resource
+/// * `label_selector` - Label selector the commands have to match
 ///
-// TODO: will be obsolete with label selector in list_commands
-fn is_command_done<T>(command: &T) -> bool
+async fn list_commands<T>(
+    client: &Client,
+    cluster: &SparkCluster,
+    label_selector: &str,
+) -> OperatorResult<Vec<T>>
 where
-    T: Resource,
+    T: Resource + Command + Clone + Debug + DeserializeOwned,
+    <T as kube::Resource>::DynamicType: Default,
 {
-    if let Some(labels) = &command.meta().labels {
-        if labels.get(COMMAND_STATUS_LABEL) == Some(&COMMAND_STATUS_VALUE.to_string()) {
-            return true;
+    let api: Api<T> = client.get_api(cluster.namespace().as_deref());
+    let commands = api
+        .list(&ListParams::default().labels(label_selector))
+        .await?;
+
+    Ok(commands
+        .items
+        .into_iter()
+        .filter(|command| command.get_owner_name() == cluster.name())
+        .collect())
+}
+
+/// Schedule of the cleanup of finished commands of one cluster. Listing the finished commands
+/// of every kind is expensive, so the cleanup only runs if a command started or finished (the
+/// current command of the cluster changed), the history limit changed or the earliest TTL of
+/// the remaining commands expired.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandCleanupSchedule {
+    current_command: Option<String>,
+    history_limit: usize,
+    next_run: DateTime<Utc>,
+}
+
+impl CommandCleanupSchedule {
+    /// Create the schedule after a cleanup. The next cleanup runs at the earliest expiry of the
+    /// remaining commands, at the latest after `COMMAND_CLEANUP_INTERVAL_SECONDS`.
+    ///
+    /// # Arguments
+    /// * `cluster` - Spark cluster custom resource
+    /// * `next_expiry` - The earliest TTL expiry of the remaining finished commands
+    /// * `now` - The current time
+    ///
+    pub fn new(
+        cluster: &SparkCluster,
+        next_expiry: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let interval_end = now + chrono::Duration::seconds(COMMAND_CLEANUP_INTERVAL_SECONDS);
+
+        CommandCleanupSchedule {
+            current_command: get_current_command_ref(cluster),
+            history_limit: cluster.spec.get_command_history_limit(),
+            next_run: next_expiry
+                .map(|expiry| expiry.min(interval_end))
+                .unwrap_or(interval_end),
         }
     }
 
-    false
+    /// Check if the finished commands of the cluster must be cleaned up.
+    ///
+    /// # Arguments
+    /// * `cluster` - Spark cluster custom resource
+    /// * `now` - The current time
+    ///
+    pub fn is_due(&self, cluster: &SparkCluster, now: DateTime<Utc>) -> bool {
+        self.current_command != get_current_command_ref(cluster)
+            || self.history_limit != cluster.spec.get_command_history_limit()
+            || now >= self.next_run
+    }
+}
+
+fn get_current_command_ref(cluster: &SparkCluster) -> Option<String> {
+    cluster
+        .status
+        .as_ref()
+        .and_then(|status| status.current_command.as_ref())
+        .map(|current_command| current_command.command_ref.clone())
+}
+
+/// Delete the finished commands of the cluster whose TTL expired or that exceed the
+/// history limit of the cluster. Returns the number of deleted commands and the earliest
+/// TTL expiry of the remaining commands.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `cluster` - Spark cluster custom resource
+/// * `now` - The current time
+///
+pub async fn delete_expired_commands(
+    client: &Client,
+    cluster: &SparkCluster,
+    now: DateTime<Utc>,
+) -> OperatorResult<(usize, Option<DateTime<Utc>>)> {
+    let results = vec![
+        delete_expired_commands_of_kind::<Restart>(client, cluster, now).await?,
+        delete_expired_commands_of_kind::<Start>(client, cluster, now).await?,
+        delete_expired_commands_of_kind::<Stop>(client, cluster, now).await?,
+        delete_expired_commands_of_kind::<Scale>(client, cluster, now).await?,
+        delete_expired_commands_of_kind::<Pause>(client, cluster, now).await?,
+        delete_expired_commands_of_kind::<Resume>(client, cluster, now).await?,
+        delete_expired_commands_of_kind::<Cancel>(client, cluster, now).await?,
+    ];
+
+    Ok((
+        results.iter().map(|(deleted, _)| deleted).sum(),
+        results
+            .iter()
+            .filter_map(|(_, next_expiry)| *next_expiry)
+            .min(),
+    ))
+}
+
+/// Delete the expired finished commands of one kind. Returns the number of deleted commands
+/// and the earliest TTL expiry of the remaining commands.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `cluster` - Spark cluster custom resource
+/// * `now` - The current time
+///
+async fn delete_expired_commands_of_kind<T>(
+    client: &Client,
+    cluster: &SparkCluster,
+    now: DateTime<Utc>,
+) -> OperatorResult<(usize, Option<DateTime<Utc>>)>
+where
+    T: Resource + Command + CommandRetention + Clone + Debug + DeserializeOwned,
+    <T as kube::Resource>::DynamicType: Default,
+{
+    let finished_commands: Vec<T> =
+        list_commands(client, cluster, &finished_commands_selector()).await?;
+    let expired_commands = find_expired_commands(
+        &finished_commands,
+        cluster.spec.get_command_history_limit(),
+        now,
+    );
+
+    for command in &expired_commands {
+        info!(
+            "Deleting finished [{}] command '{}'",
+            T::kind(&Default::default()),
+            command.name()
+        );
+        client.delete(*command).await?;
+    }
+
+    Ok((
+        expired_commands.len(),
+        find_next_expiry(&finished_commands, now),
+    ))
+}
+
+/// Find the earliest TTL expiry after `now` of the finished commands.
+///
+/// # Arguments
+/// * `commands` - Finished commands of one kind
+/// * `now` - The current time
+///
+pub fn find_next_expiry<T>(commands: &[T], now: DateTime<Utc>) -> Option<DateTime<Utc>>
+where
+    T: Resource + CommandRetention,
+{
+    commands
+        .iter()
+        .filter_map(|command| {
+            let ttl = command.get_ttl_seconds_after_finished()?;
+            Some(get_finished_at(command)? + chrono::Duration::seconds(ttl as i64))
+        })
+        .filter(|expiry| *expiry > now)
+        .min()
+}
+
+/// Find the finished commands whose TTL expired or that exceed the history limit (the
+/// most recently finished commands are kept).
+///
+/// # Arguments
+/// * `commands` - Finished commands of one kind
+/// * `history_limit` - Number of finished commands to keep
+/// * `now` - The current time
+///
+pub fn find_expired_commands<T>(commands: &[T], history_limit: usize, now: DateTime<Utc>) -> Vec<&T>
+where
+    T: Resource + CommandRetention,
+{
+    let mut finished_commands = commands
+        .iter()
+        .map(|command| (command, get_finished_at(command)))
+        .collect::<Vec<_>>();
+    // most recently finished first, commands without any timestamp last
+    finished_commands.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    finished_commands
+        .into_iter()
+        .enumerate()
+        .filter(|(index, (command, finished_at))| {
+            let ttl_expired = match (command.get_ttl_seconds_after_finished(), finished_at) {
+                (Some(ttl), Some(finished_at)) => {
+                    now - *finished_at >= chrono::Duration::seconds(ttl as i64)
+                }
+                _ => false,
+            };
+            *index >= history_limit || ttl_expired
+        })
+        .map(|(_, (command, _))| command)
+        .collect()
+}
+
+/// Return the time the command finished. Falls back to the creation time for commands
+/// that were finished without a finishedAt status.
+fn get_finished_at<T>(command: &T) -> Option<DateTime<Utc>>
+where
+    T: Resource + CommandRetention,
+{
+    command
+        .get_status()
+        .and_then(|status| status.finished_at.as_deref())
+        .and_then(|finished_at| DateTime::parse_from_rfc3339(finished_at).ok())
+        .map(|finished_at| finished_at.with_timezone(&Utc))
+        .or_else(|| {
+            command
+                .meta()
+                .creation_timestamp
+                .as_ref()
+                .map(|time| time.0)
+        })
 }
 
 /// Retrieve a timestamp in format: "2021-03-23T16:20:19Z".
@@ -418,3 +1164,203 @@ where
 pub fn get_current_timestamp() -> String {
     chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_spark_crd::commands::{CancelCommandSpec, RestartCommandSpec};
+    use stackable_spark_crd::SparkClusterStatus;
+
+    fn build_current_command(retries: u16) -> CurrentCommand {
+        CurrentCommand {
+            command_ref: "spark-cluster-command-restart".to_string(),
+            command_type: "Restart".to_string(),
+            started_at: "2021-06-01T12:00:00Z".to_string(),
+            retries,
+        }
+    }
+
+    fn build_cancel(name: &str, cluster_name: &str, namespace: &str) -> Cancel {
+        let mut cancel = Cancel::new(
+            name,
+            CancelCommandSpec {
+                name: cluster_name.to_string(),
+            },
+        );
+        cancel.metadata.namespace = Some(namespace.to_string());
+        cancel
+    }
+
+    #[test]
+    fn test_select_next_cancel_command() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.namespace = Some("default".to_string());
+        let cluster_name = spark_cluster.name();
+
+        // owned by another cluster or in another namespace
+        let mut cancels = vec![
+            build_cancel("cancel-other", "other-cluster", "default"),
+            build_cancel("cancel-other-ns", &cluster_name, "other-namespace"),
+        ];
+        assert!(select_next_cancel_command(cancels.clone(), &spark_cluster).is_none());
+
+        cancels.push(build_cancel("cancel-own", &cluster_name, "default"));
+        assert_eq!(
+            select_next_cancel_command(cancels, &spark_cluster).map(|cancel| cancel.name()),
+            Some("cancel-own".to_string())
+        );
+    }
+
+    fn build_api_error(code: u16) -> stackable_operator::error::Error {
+        stackable_operator::error::Error::KubeError {
+            source: kube::Error::Api(kube::error::ErrorResponse {
+                status: "Failure".to_string(),
+                message: "error".to_string(),
+                reason: "error".to_string(),
+                code,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_api_error_codes() {
+        assert!(is_not_found(&build_api_error(404)));
+        assert!(!is_not_found(&build_api_error(403)));
+        assert!(is_conflict(&build_api_error(409)));
+        assert!(!is_conflict(&build_api_error(500)));
+    }
+
+    #[test]
+    fn test_check_timeout() {
+        let now = DateTime::parse_from_rfc3339("2021-06-01T12:05:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let timeout = Some(Duration::from_secs(120));
+
+        assert_eq!(
+            check_timeout(&build_current_command(0), None, 1, now),
+            TimeoutAction::Wait
+        );
+        assert_eq!(
+            check_timeout(
+                &build_current_command(0),
+                Some(Duration::from_secs(600)),
+                1,
+                now
+            ),
+            TimeoutAction::Wait
+        );
+        assert_eq!(
+            check_timeout(&build_current_command(0), timeout, 1, now),
+            TimeoutAction::Retry
+        );
+        assert_eq!(
+            check_timeout(&build_current_command(1), timeout, 1, now),
+            TimeoutAction::Fail
+        );
+    }
+
+    fn build_restart(name: &str, finished_at: &str, ttl: Option<u64>) -> Restart {
+        let mut restart = Restart::new(
+            name,
+            RestartCommandSpec {
+                name: "simple".to_string(),
+                timeout_seconds: None,
+                retries: None,
+                ttl_seconds_after_finished: ttl,
+            },
+        );
+        restart.status = Some(CommandStatus {
+            finished_at: Some(finished_at.to_string()),
+            message: Some(CommandStatusMessage::Finished),
+            ..CommandStatus::default()
+        });
+        restart
+    }
+
+    #[test]
+    fn test_find_expired_commands() {
+        let now = DateTime::parse_from_rfc3339("2021-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let commands = vec![
+            build_restart("oldest", "2021-06-01T09:00:00Z", None),
+            build_restart("ttl-expired", "2021-06-01T11:00:00Z", Some(600)),
+            build_restart("ttl-pending", "2021-06-01T11:55:00Z", Some(600)),
+            build_restart("newest", "2021-06-01T11:59:00Z", None),
+        ];
+
+        let expired = find_expired_commands(&commands, 3, now)
+            .into_iter()
+            .map(|command| command.name())
+            .collect::<Vec<_>>();
+        assert_eq!(expired, vec!["ttl-expired", "oldest"]);
+
+        let expired = find_expired_commands(&commands, 1, now)
+            .into_iter()
+            .map(|command| command.name())
+            .collect::<Vec<_>>();
+        assert_eq!(expired, vec!["ttl-pending", "ttl-expired", "oldest"]);
+    }
+
+    #[test]
+    fn test_find_next_expiry() {
+        let now = DateTime::parse_from_rfc3339("2021-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let commands = vec![
+            build_restart("no-ttl", "2021-06-01T09:00:00Z", None),
+            build_restart("ttl-expired", "2021-06-01T11:00:00Z", Some(600)),
+            build_restart("ttl-late", "2021-06-01T11:58:00Z", Some(600)),
+            build_restart("ttl-early", "2021-06-01T11:55:00Z", Some(600)),
+        ];
+
+        assert_eq!(
+            find_next_expiry(&commands, now),
+            Some(
+                DateTime::parse_from_rfc3339("2021-06-01T12:05:00Z")
+                    .unwrap()
+                    .with_timezone(&Utc)
+            )
+        );
+        assert_eq!(find_next_expiry(&commands[..2], now), None);
+    }
+
+    #[test]
+    fn test_command_cleanup_schedule() {
+        let now = DateTime::parse_from_rfc3339("2021-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+
+        // without expiring commands the cleanup runs after the interval
+        let schedule = CommandCleanupSchedule::new(&spark_cluster, None, now);
+        assert!(!schedule.is_due(&spark_cluster, now));
+        assert!(schedule.is_due(
+            &spark_cluster,
+            now + chrono::Duration::seconds(COMMAND_CLEANUP_INTERVAL_SECONDS)
+        ));
+
+        // or when the earliest TTL expired
+        let expiry = now + chrono::Duration::seconds(60);
+        let schedule = CommandCleanupSchedule::new(&spark_cluster, Some(expiry), now);
+        assert!(!schedule.is_due(&spark_cluster, now));
+        assert!(schedule.is_due(&spark_cluster, expiry));
+
+        // or when a command started or finished
+        spark_cluster.status = Some(SparkClusterStatus {
+            current_command: Some(build_current_command(0)),
+            ..SparkClusterStatus::default()
+        });
+        assert!(schedule.is_due(&spark_cluster, now));
+        let schedule = CommandCleanupSchedule::new(&spark_cluster, None, now);
+        assert!(!schedule.is_due(&spark_cluster, now));
+        spark_cluster.status = None;
+        assert!(schedule.is_due(&spark_cluster, now));
+
+        // or when the history limit changed
+        let schedule = CommandCleanupSchedule::new(&spark_cluster, None, now);
+        spark_cluster.spec.command_history_limit = Some(1);
+        assert!(schedule.is_due(&spark_cluster, now));
+    }
+}
@@ -0,0 +1,739 @@
+//! This module contains all methods to compute the role status and the status conditions
+//! of a SparkCluster.
+use crate::pod_utils;
+use k8s_openapi::api::core::v1::{Node, Pod};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, Time};
+use stackable_spark_crd::properties::{PropertyIssue, Severity};
+use stackable_spark_crd::{
+    ClusterExecutionStatus, ClusterInfo, RoleStatus, SparkClusterStatus, SparkNodeType,
+};
+use std::collections::HashMap;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// All condition types the operator writes to the SparkCluster status. Conditions with other
+/// types (e.g. the former "Installing" condition) are removed when conditions are written.
+#[derive(Clone, Debug, EnumIter, PartialEq, strum_macros::Display)]
+pub enum ClusterConditionType {
+    /// A master is alive and enough workers are registered
+    Available,
+    /// The cluster is installing, changing version or waiting for pods to become ready
+    Progressing,
+    /// Pods are crash looping or missing for at least one role group
+    Degraded,
+    /// The cluster is changing its version
+    Upgrading,
+    /// The cluster was stopped via the Stop command
+    Stopped,
+    /// The last reconcile failed
+    ReconcileError,
+    /// The user defined config options are valid (unknown properties are only warnings)
+    ConfigurationValid,
+}
+
+/// A condition computed by the operator before it is converted into a Kubernetes condition.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClusterCondition {
+    pub condition_type: ClusterConditionType,
+    pub status: bool,
+    pub reason: String,
+    pub message: String,
+}
+
+impl ClusterCondition {
+    pub fn new(
+        condition_type: ClusterConditionType,
+        status: bool,
+        reason: &str,
+        message: &str,
+    ) -> Self {
+        ClusterCondition {
+            condition_type,
+            status,
+            reason: reason.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn status_str(&self) -> &'static str {
+        if self.status {
+            "True"
+        } else {
+            "False"
+        }
+    }
+}
+
+/// Count the ready pods and the desired pods (of all role groups) for a role and add the
+/// label selector of the role pods. Returns None if the role is not specified in the cluster.
+///
+/// # Arguments
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+/// * `cluster_name` - The name of the cluster as specified in the custom resource
+/// * `pods` - Slice of all existing pods
+/// * `desired_pods` - Desired number of pods of the role mapped by role group
+///
+pub fn build_role_status(
+    node_type: &SparkNodeType,
+    cluster_name: &str,
+    pods: &[Pod],
+    desired_pods: Option<&HashMap<String, usize>>,
+) -> Option<RoleStatus> {
+    let desired_pods = desired_pods?;
+
+    let desired = desired_pods.values().sum();
+    let ready = pod_utils::filter_pods_for_type(pods, node_type)
+        .iter()
+        .filter(|pod| pod_utils::is_pod_running_and_ready(pod))
+        .count();
+
+    Some(RoleStatus {
+        selector: Some(pod_utils::build_role_selector(cluster_name, node_type)),
+        ..RoleStatus::new(ready, desired)
+    })
+}
+
+/// Collect human readable messages for all role groups of a role that are degraded.
+/// A role group is degraded if no node matches its selector, if one of its pods is crash
+/// looping or (if `check_missing_pods` is set) if pods are missing.
+///
+/// # Arguments
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+/// * `pods` - Slice of all existing pods
+/// * `eligible_nodes` - Eligible nodes of the role mapped by role group
+/// * `desired_pods` - Desired number of pods of the role mapped by role group
+/// * `check_missing_pods` - Whether missing pods count as degraded (not during installation)
+///
+pub fn find_degraded_role_groups(
+    node_type: &SparkNodeType,
+    pods: &[Pod],
+    eligible_nodes: Option<&HashMap<String, Vec<Node>>>,
+    desired_pods: Option<&HashMap<String, usize>>,
+    check_missing_pods: bool,
+) -> Vec<String> {
+    let mut messages = vec![];
+
+    let eligible_nodes = match eligible_nodes {
+        Some(eligible_nodes) => eligible_nodes,
+        None => return messages,
+    };
+
+    let mut role_groups = eligible_nodes.keys().collect::<Vec<_>>();
+    role_groups.sort();
+
+    for role_group in role_groups {
+        let nodes = &eligible_nodes[role_group];
+        let desired = desired_pods
+            .and_then(|desired_pods| desired_pods.get(role_group))
+            .cloned()
+            .unwrap_or_else(|| nodes.len());
+        let group_pods = pod_utils::filter_pods_for_role_group(pods, node_type, role_group);
+
+        if nodes.is_empty() {
+            messages.push(format!(
+                "No eligible nodes found for [{}] role and group [{}]",
+                node_type, role_group
+            ));
+        }
+
+        for pod in group_pods
+            .iter()
+            .filter(|pod| pod_utils::is_pod_crash_looping(pod))
+        {
+            messages.push(format!(
+                "Pod [{}] of [{}] role and group [{}] is crash looping",
+                pod.metadata
+                    .name
+                    .as_deref()
+                    .unwrap_or("<no pod name found>"),
+                node_type,
+                role_group
+            ));
+        }
+
+        if check_missing_pods && group_pods.len() < desired {
+            messages.push(format!(
+                "[{}] of [{}] pods missing for [{}] role and group [{}]",
+                desired - group_pods.len(),
+                desired,
+                node_type,
+                role_group
+            ));
+        }
+    }
+
+    messages
+}
+
+/// Compute the Available, Progressing, Degraded, Upgrading and Stopped conditions.
+///
+/// # Arguments
+/// * `status` - The current SparkCluster status
+/// * `masters` - Ready and desired master pods
+/// * `workers` - Ready and desired worker pods
+/// * `degraded_role_groups` - Messages for all degraded role groups
+/// * `cluster_info` - The cluster info reported by the masters (None if no master was queried)
+///
+pub fn build_cluster_conditions(
+    status: &SparkClusterStatus,
+    masters: &RoleStatus,
+    workers: &RoleStatus,
+    degraded_role_groups: &[String],
+    cluster_info: Option<&ClusterInfo>,
+) -> Vec<ClusterCondition> {
+    let stopped = status.cluster_execution_status == Some(ClusterExecutionStatus::Stopped);
+    let mut conditions = vec![];
+
+    conditions.push(if stopped {
+        ClusterCondition::new(
+            ClusterConditionType::Stopped,
+            true,
+            "StopCommand",
+            "Cluster was stopped via command",
+        )
+    } else if status.cluster_execution_status == Some(ClusterExecutionStatus::Paused) {
+        ClusterCondition::new(
+            ClusterConditionType::Stopped,
+            false,
+            "PauseCommand",
+            "Cluster was paused via command, pods are neither created nor deleted",
+        )
+    } else {
+        ClusterCondition::new(
+            ClusterConditionType::Stopped,
+            false,
+            "Running",
+            "Cluster is running",
+        )
+    });
+
+    conditions.push(match (&status.current_version, &status.target_version) {
+        (Some(current_version), Some(target_version)) => ClusterCondition::new(
+            ClusterConditionType::Upgrading,
+            true,
+            "VersionChange",
+            &format!(
+                "Changing version from [{}] to [{}]",
+                current_version, target_version
+            ),
+        ),
+        _ => ClusterCondition::new(
+            ClusterConditionType::Upgrading,
+            false,
+            "NoVersionChange",
+            "No version change in progress",
+        ),
+    });
+
+    let not_ready_pods =
+        (masters.desired + workers.desired).saturating_sub(masters.ready + workers.ready);
+
+    conditions.push(match (&status.current_version, &status.target_version) {
+        _ if stopped => ClusterCondition::new(
+            ClusterConditionType::Progressing,
+            false,
+            "Stopped",
+            "Cluster is stopped",
+        ),
+        (None, Some(target_version)) => ClusterCondition::new(
+            ClusterConditionType::Progressing,
+            true,
+            "InitialInstallation",
+            &format!("Initial installation to version [{}]", target_version),
+        ),
+        (Some(current_version), Some(target_version)) => ClusterCondition::new(
+            ClusterConditionType::Progressing,
+            true,
+            "VersionChange",
+            &format!(
+                "Changing version from [{}] to [{}]",
+                current_version, target_version
+            ),
+        ),
+        _ if not_ready_pods > 0 => ClusterCondition::new(
+            ClusterConditionType::Progressing,
+            true,
+            "PodsNotReady",
+            &format!("Waiting for [{}] pods to become ready", not_ready_pods),
+        ),
+        _ => ClusterCondition::new(
+            ClusterConditionType::Progressing,
+            false,
+            "ReconcileComplete",
+            "All pods are running and ready",
+        ),
+    });
+
+    conditions.push(if stopped {
+        ClusterCondition::new(
+            ClusterConditionType::Available,
+            false,
+            "Stopped",
+            "Cluster is stopped",
+        )
+    } else if masters.ready == 0 {
+        ClusterCondition::new(
+            ClusterConditionType::Available,
+            false,
+            "NoMasterReady",
+            "No master is running and ready",
+        )
+    } else if cluster_info.map(|info| info.alive_master.is_none()) == Some(true) {
+        ClusterCondition::new(
+            ClusterConditionType::Available,
+            false,
+            "NoAliveMaster",
+            "No master reports the state ALIVE",
+        )
+    } else if workers.ready < workers.desired || workers.desired == 0 {
+        ClusterCondition::new(
+            ClusterConditionType::Available,
+            false,
+            "NotEnoughWorkers",
+            &format!(
+                "[{}] of [{}] workers are running and ready",
+                workers.ready, workers.desired
+            ),
+        )
+    } else if let Some(info) = cluster_info.filter(|info| info.alive_workers < workers.desired) {
+        ClusterCondition::new(
+            ClusterConditionType::Available,
+            false,
+            "NotEnoughWorkersRegistered",
+            &format!(
+                "[{}] of [{}] workers are registered at the alive master",
+                info.alive_workers, workers.desired
+            ),
+        )
+    } else {
+        ClusterCondition::new(
+            ClusterConditionType::Available,
+            true,
+            "MastersAndWorkersReady",
+            &format!(
+                "[{}] masters and [{}] workers are running and ready",
+                masters.ready, workers.ready
+            ),
+        )
+    });
+
+    conditions.push(if stopped {
+        ClusterCondition::new(
+            ClusterConditionType::Degraded,
+            false,
+            "Stopped",
+            "Cluster is stopped",
+        )
+    } else if !degraded_role_groups.is_empty() {
+        ClusterCondition::new(
+            ClusterConditionType::Degraded,
+            true,
+            "RoleGroupsDegraded",
+            &degraded_role_groups.join("; "),
+        )
+    } else {
+        ClusterCondition::new(
+            ClusterConditionType::Degraded,
+            false,
+            "AllRoleGroupsHealthy",
+            "All role groups are healthy",
+        )
+    });
+
+    conditions
+}
+
+/// Compute the ConfigurationValid condition from the issues found in the user defined config
+/// options. Errors make the condition false, warnings are only listed in the message.
+///
+/// # Arguments
+/// * `issues` - The issues found by validating the config options of all role groups
+///
+pub fn build_configuration_condition(issues: &[PropertyIssue]) -> ClusterCondition {
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .map(|issue| issue.to_string())
+        .collect::<Vec<_>>();
+    let warnings = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Warning)
+        .map(|issue| issue.to_string())
+        .collect::<Vec<_>>();
+
+    if !errors.is_empty() {
+        ClusterCondition::new(
+            ClusterConditionType::ConfigurationValid,
+            false,
+            "InvalidProperties",
+            &format!("Errors: {}", errors.join("; ")),
+        )
+    } else if !warnings.is_empty() {
+        ClusterCondition::new(
+            ClusterConditionType::ConfigurationValid,
+            true,
+            "UnknownProperties",
+            &format!("Warnings: {}", warnings.join("; ")),
+        )
+    } else {
+        ClusterCondition::new(
+            ClusterConditionType::ConfigurationValid,
+            true,
+            "PropertiesValid",
+            "All config options are valid",
+        )
+    }
+}
+
+/// Merge new conditions into the current conditions. New conditions replace current ones of
+/// the same type. The last transition time is only updated if the status changed. Current
+/// conditions of types not known to the operator are dropped.
+///
+/// # Arguments
+/// * `current_conditions` - The conditions currently set in the status
+/// * `new_conditions` - The conditions to set
+/// * `observed_generation` - The generation of the resource the conditions are based on
+///
+pub fn merge_conditions(
+    current_conditions: &[Condition],
+    new_conditions: &[ClusterCondition],
+    observed_generation: Option<i64>,
+) -> Vec<Condition> {
+    let mut conditions = vec![];
+
+    for condition_type in ClusterConditionType::iter() {
+        let type_name = condition_type.to_string();
+        let current = current_conditions
+            .iter()
+            .find(|condition| condition.type_ == type_name);
+
+        match new_conditions
+            .iter()
+            .find(|condition| condition.condition_type == condition_type)
+        {
+            Some(new) => {
+                let last_transition_time = match current {
+                    Some(current) if current.status == new.status_str() => {
+                        current.last_transition_time.clone()
+                    }
+                    _ => Time(chrono::Utc::now()),
+                };
+
+                conditions.push(Condition {
+                    last_transition_time,
+                    message: new.message.clone(),
+                    observed_generation,
+                    reason: new.reason.clone(),
+                    status: new.status_str().to_string(),
+                    type_: type_name,
+                });
+            }
+            None => {
+                if let Some(current) = current {
+                    conditions.push(current.clone());
+                }
+            }
+        }
+    }
+
+    conditions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_spark_crd::properties::PropertyFile;
+    use stackable_spark_crd::SparkVersion;
+
+    fn find(
+        conditions: &[ClusterCondition],
+        condition_type: ClusterConditionType,
+    ) -> &ClusterCondition {
+        conditions
+            .iter()
+            .find(|condition| condition.condition_type == condition_type)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_build_configuration_condition() {
+        let issue = |severity: Severity, name: &str| PropertyIssue {
+            severity,
+            location: "master/default".to_string(),
+            file: PropertyFile::SparkDefaults,
+            name: name.to_string(),
+            message: "message".to_string(),
+        };
+
+        let condition = build_configuration_condition(&[]);
+        assert!(condition.status);
+        assert_eq!(condition.reason, "PropertiesValid");
+
+        let condition =
+            build_configuration_condition(&[issue(Severity::Warning, "spark.eventlog.dir")]);
+        assert!(condition.status);
+        assert_eq!(condition.reason, "UnknownProperties");
+
+        let condition = build_configuration_condition(&[
+            issue(Severity::Warning, "spark.eventlog.dir"),
+            issue(Severity::Error, "spark.master.port"),
+        ]);
+        assert!(!condition.status);
+        assert_eq!(condition.reason, "InvalidProperties");
+        assert_eq!(
+            condition.message,
+            "Errors: master/default spark-defaults.conf [spark.master.port]: message"
+        );
+    }
+
+    #[test]
+    fn test_build_cluster_conditions_available() {
+        let status = SparkClusterStatus {
+            current_version: Some(SparkVersion::v3_0_1),
+            ..SparkClusterStatus::default()
+        };
+
+        let conditions = build_cluster_conditions(
+            &status,
+            &RoleStatus::new(1, 1),
+            &RoleStatus::new(2, 2),
+            &[],
+            None,
+        );
+
+        assert!(find(&conditions, ClusterConditionType::Available).status);
+        assert!(!find(&conditions, ClusterConditionType::Progressing).status);
+        assert!(!find(&conditions, ClusterConditionType::Degraded).status);
+        assert!(!find(&conditions, ClusterConditionType::Upgrading).status);
+        assert!(!find(&conditions, ClusterConditionType::Stopped).status);
+    }
+
+    #[test]
+    fn test_build_cluster_conditions_registered_workers() {
+        let status = SparkClusterStatus {
+            current_version: Some(SparkVersion::v3_0_1),
+            ..SparkClusterStatus::default()
+        };
+        let mut cluster_info = ClusterInfo {
+            alive_master: None,
+            alive_workers: 1,
+            ..ClusterInfo::default()
+        };
+
+        let conditions = build_cluster_conditions(
+            &status,
+            &RoleStatus::new(1, 1),
+            &RoleStatus::new(2, 2),
+            &[],
+            Some(&cluster_info),
+        );
+        assert_eq!(
+            find(&conditions, ClusterConditionType::Available).reason,
+            "NoAliveMaster"
+        );
+
+        cluster_info.alive_master = Some("master_node_1:8080".to_string());
+        let conditions = build_cluster_conditions(
+            &status,
+            &RoleStatus::new(1, 1),
+            &RoleStatus::new(2, 2),
+            &[],
+            Some(&cluster_info),
+        );
+        assert_eq!(
+            find(&conditions, ClusterConditionType::Available).reason,
+            "NotEnoughWorkersRegistered"
+        );
+
+        cluster_info.alive_workers = 2;
+        let conditions = build_cluster_conditions(
+            &status,
+            &RoleStatus::new(1, 1),
+            &RoleStatus::new(2, 2),
+            &[],
+            Some(&cluster_info),
+        );
+        assert!(find(&conditions, ClusterConditionType::Available).status);
+    }
+
+    #[test]
+    fn test_build_cluster_conditions_installing() {
+        let status = SparkClusterStatus {
+            target_version: Some(SparkVersion::v3_0_1),
+            ..SparkClusterStatus::default()
+        };
+
+        let conditions = build_cluster_conditions(
+            &status,
+            &RoleStatus::new(0, 1),
+            &RoleStatus::new(0, 2),
+            &[],
+            None,
+        );
+
+        let progressing = find(&conditions, ClusterConditionType::Progressing);
+        assert!(progressing.status);
+        assert_eq!(progressing.reason, "InitialInstallation");
+
+        let available = find(&conditions, ClusterConditionType::Available);
+        assert!(!available.status);
+        assert_eq!(available.reason, "NoMasterReady");
+    }
+
+    #[test]
+    fn test_build_cluster_conditions_upgrading() {
+        let status = SparkClusterStatus {
+            current_version: Some(SparkVersion::v3_0_1),
+            target_version: Some(SparkVersion::v3_1_1),
+            ..SparkClusterStatus::default()
+        };
+
+        let conditions = build_cluster_conditions(
+            &status,
+            &RoleStatus::new(1, 1),
+            &RoleStatus::new(1, 2),
+            &[],
+            None,
+        );
+
+        assert!(find(&conditions, ClusterConditionType::Upgrading).status);
+        assert_eq!(
+            find(&conditions, ClusterConditionType::Available).reason,
+            "NotEnoughWorkers"
+        );
+    }
+
+    #[test]
+    fn test_build_cluster_conditions_stopped_and_degraded() {
+        let mut status = SparkClusterStatus {
+            current_version: Some(SparkVersion::v3_0_1),
+            ..SparkClusterStatus::default()
+        };
+        let degraded = vec!["Pod [a] is crash looping".to_string()];
+
+        let conditions = build_cluster_conditions(
+            &status,
+            &RoleStatus::new(1, 1),
+            &RoleStatus::new(1, 1),
+            &degraded,
+            None,
+        );
+        let degraded_condition = find(&conditions, ClusterConditionType::Degraded);
+        assert!(degraded_condition.status);
+        assert_eq!(degraded_condition.message, degraded[0]);
+
+        status.cluster_execution_status = Some(ClusterExecutionStatus::Stopped);
+        let conditions = build_cluster_conditions(
+            &status,
+            &RoleStatus::new(0, 1),
+            &RoleStatus::new(0, 1),
+            &degraded,
+            None,
+        );
+        assert!(find(&conditions, ClusterConditionType::Stopped).status);
+        assert!(!find(&conditions, ClusterConditionType::Degraded).status);
+        assert!(!find(&conditions, ClusterConditionType::Available).status);
+
+        status.cluster_execution_status = Some(ClusterExecutionStatus::Paused);
+        let conditions = build_cluster_conditions(
+            &status,
+            &RoleStatus::new(1, 1),
+            &RoleStatus::new(1, 1),
+            &[],
+            None,
+        );
+        let stopped_condition = find(&conditions, ClusterConditionType::Stopped);
+        assert!(!stopped_condition.status);
+        assert_eq!(stopped_condition.reason, "PauseCommand");
+    }
+
+    #[test]
+    fn test_find_degraded_role_groups() {
+        let mut pods = stackable_spark_test_utils::create_master_pods();
+        stackable_spark_test_utils::set_pod_crash_looping(pods.get_mut(0).unwrap());
+
+        let mut eligible_nodes = HashMap::new();
+        eligible_nodes.insert("master_1".to_string(), vec![Node::default()]);
+        eligible_nodes.insert("missing".to_string(), vec![Node::default()]);
+        eligible_nodes.insert("empty".to_string(), vec![]);
+
+        let degraded = find_degraded_role_groups(
+            &SparkNodeType::Master,
+            &pods,
+            Some(&eligible_nodes),
+            None,
+            false,
+        );
+        assert_eq!(degraded.len(), 2);
+
+        let degraded = find_degraded_role_groups(
+            &SparkNodeType::Master,
+            &pods,
+            Some(&eligible_nodes),
+            None,
+            true,
+        );
+        assert_eq!(degraded.len(), 3);
+
+        // with Scheduler placement the desired pods do not depend on the eligible nodes
+        let mut desired_pods = HashMap::new();
+        desired_pods.insert("master_1".to_string(), 2);
+        desired_pods.insert("missing".to_string(), 0);
+        desired_pods.insert("empty".to_string(), 0);
+        let degraded = find_degraded_role_groups(
+            &SparkNodeType::Master,
+            &pods,
+            Some(&eligible_nodes),
+            Some(&desired_pods),
+            true,
+        );
+        assert_eq!(degraded.len(), 3);
+        assert!(degraded[2].starts_with("[1] of [2] pods missing"));
+    }
+
+    #[test]
+    fn test_merge_conditions() {
+        let first = merge_conditions(
+            &[Condition {
+                last_transition_time: Time(chrono::Utc::now()),
+                message: "Initial installation".to_string(),
+                observed_generation: None,
+                reason: "InitialInstallation".to_string(),
+                status: "True".to_string(),
+                type_: "Installing".to_string(),
+            }],
+            &[ClusterCondition::new(
+                ClusterConditionType::Available,
+                false,
+                "NoMasterReady",
+                "",
+            )],
+            Some(1),
+        );
+        // legacy conditions are dropped
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].type_, "Available");
+        assert_eq!(first[0].observed_generation, Some(1));
+
+        let second = merge_conditions(
+            &first,
+            &[
+                ClusterCondition::new(
+                    ClusterConditionType::Available,
+                    false,
+                    "NotEnoughWorkers",
+                    "",
+                ),
+                ClusterCondition::new(ClusterConditionType::Stopped, false, "Running", ""),
+            ],
+            Some(2),
+        );
+        assert_eq!(second.len(), 2);
+        // status did not change, so the transition time is kept
+        assert_eq!(
+            second[0].last_transition_time,
+            first[0].last_transition_time
+        );
+        assert_eq!(second[0].reason, "NotEnoughWorkers");
+    }
+}
@@ -1,12 +1,19 @@
 //! This module contains all methods that are responsible for setting / adapting configuration
 //! parameters in the Pods and respective ConfigMaps.
 
-use k8s_openapi::api::core::v1::{ConfigMap, EnvVar};
+use crate::pod_utils::create_pod_name;
+use k8s_openapi::api::core::v1::{ConfigMap, EnvVar, EnvVarSource, ObjectFieldSelector};
+use kube::Resource;
 use stackable_operator::config_map::create_config_map;
 use stackable_operator::error::OperatorResult;
 use stackable_spark_common::constants::*;
-use stackable_spark_crd::{Config, SparkCluster, SparkNodeType};
-use std::collections::BTreeMap;
+use stackable_spark_crd::{Config, Runtime, SparkCluster, SparkNodeType};
+use std::collections::{BTreeMap, BTreeSet};
+use strum::IntoEnumIterator;
+use tracing::warn;
+
+/// Field path of the pod IP for the downward API
+pub const POD_IP_FIELD_PATH: &str = "status.podIP";
 
 /// The worker start command needs to be extended with all known master nodes and ports.
 /// The required URLs for the starting command are in format: '<master-node-name>:<master-port'
@@ -40,8 +47,15 @@ pub fn adapt_worker_command(node_type: &SparkNodeType, master_urls: &[String]) -
 /// SPARK_CONFIG_DIR must be available before the start up of the nodes (master, worker, history-server) to point to our custom configuration.
 /// SPARK_NO_DAEMONIZE stops the node processes to be started in the background, which causes the agent to lose track of the processes.
 /// The Agent then assumes the processes stopped or died and recreates them over and over again.
-pub fn create_required_startup_env() -> Vec<EnvVar> {
-    vec![
+/// On the kubernetes runtime the masters are addressed via their pod IP, so SPARK_MASTER_HOST is
+/// set to the pod IP (the master advertises this host e.g. to the workers after a failover).
+///
+/// # Arguments
+/// * `runtime` - The kind of node agent that runs the pods
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+///
+pub fn create_required_startup_env(runtime: &Runtime, node_type: &SparkNodeType) -> Vec<EnvVar> {
+    let mut env = vec![
         EnvVar {
             name: SPARK_NO_DAEMONIZE.to_string(),
             value: Some("true".to_string()),
@@ -49,10 +63,56 @@ pub fn create_required_startup_env() -> Vec<EnvVar> {
         },
         EnvVar {
             name: SPARK_CONF_DIR.to_string(),
-            value: Some("{{configroot}}/conf".to_string()),
+            value: Some(runtime.get_config_dir().to_string()),
             ..EnvVar::default()
         },
-    ]
+    ];
+
+    if runtime == &Runtime::Kubernetes && node_type == &SparkNodeType::Master {
+        env.push(EnvVar {
+            name: SPARK_ENV_MASTER_HOST.to_string(),
+            value_from: Some(EnvVarSource {
+                field_ref: Some(ObjectFieldSelector {
+                    field_path: POD_IP_FIELD_PATH.to_string(),
+                    ..ObjectFieldSelector::default()
+                }),
+                ..EnvVarSource::default()
+            }),
+            ..EnvVar::default()
+        });
+    }
+
+    env
+}
+
+/// Build the env of the Spark container: the required startup env followed by the user defined
+/// env vars of the role group. User defined env vars must not override the required startup
+/// env, they are skipped with a warning.
+///
+/// # Arguments
+/// * `runtime` - The kind of node agent that runs the pods
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+/// * `user_env` - The user defined env vars of the role group
+///
+pub fn create_container_env(
+    runtime: &Runtime,
+    node_type: &SparkNodeType,
+    user_env: &[EnvVar],
+) -> Vec<EnvVar> {
+    let mut env = create_required_startup_env(runtime, node_type);
+
+    for env_var in user_env {
+        if env.iter().any(|required| required.name == env_var.name) {
+            warn!(
+                "Ignoring env var [{}]: it is required by the operator and must not be overridden",
+                env_var.name
+            );
+        } else {
+            env.push(env_var.clone());
+        }
+    }
+
+    env
 }
 
 /// Unroll a map into a String using a given assignment character (for writing config maps)
@@ -78,6 +138,60 @@ pub fn create_config_map_name(pod_name: &str) -> String {
     format!("{}-config", pod_name)
 }
 
+/// Return the config map names of all role groups of the cluster.
+///
+/// # Arguments
+/// * `resource` - SparkCluster
+///
+pub fn get_role_group_config_map_names(resource: &SparkCluster) -> BTreeSet<String> {
+    let cluster_name = resource.name();
+    let mut names = BTreeSet::new();
+
+    for node_type in SparkNodeType::iter() {
+        for role_group in resource.spec.get_role_groups(&node_type) {
+            names.insert(create_config_map_name(&create_pod_name(
+                &cluster_name,
+                &role_group,
+                &node_type.to_string(),
+                None,
+            )));
+        }
+    }
+
+    names
+}
+
+/// Find the role group config maps that do not belong to a current role group anymore
+/// (e.g. because the role group was removed or renamed). Only config maps following the
+/// role group naming pattern spark-<cluster_name>-<role_group>-<node_type>-config are
+/// considered, the given config maps are expected to be owned by the cluster.
+///
+/// # Arguments
+/// * `resource` - SparkCluster
+/// * `config_maps` - The config maps owned by the cluster
+///
+pub fn find_orphaned_config_maps<'a>(
+    resource: &SparkCluster,
+    config_maps: &'a [ConfigMap],
+) -> Vec<&'a ConfigMap> {
+    let required_names = get_role_group_config_map_names(resource);
+    // see create_pod_name
+    let prefix = format!("spark-{}-", resource.name()).to_lowercase();
+    let suffixes = SparkNodeType::iter()
+        .map(|node_type| create_config_map_name(&format!("-{}", node_type)))
+        .collect::<Vec<_>>();
+
+    config_maps
+        .iter()
+        .filter(|config_map| {
+            let name = config_map.name();
+            name.starts_with(&prefix)
+                && suffixes.iter().any(|suffix| name.ends_with(suffix))
+                && !required_names.contains(&name)
+        })
+        .collect()
+}
+
 /// Create all required config maps and respective config map data.
 ///
 /// # Arguments
@@ -95,10 +209,14 @@ where
 {
     let mut spark_defaults = BTreeMap::new();
     let mut spark_env_sh = BTreeMap::new();
+    let mut log4j_properties = None;
+    let mut hadoop_config_files = BTreeMap::new();
 
     if let Some(conf) = config {
         spark_defaults = conf.get_spark_defaults_conf(&resource.spec);
-        spark_env_sh = conf.get_spark_env_sh();
+        spark_env_sh = conf.get_spark_env_sh(&resource.spec);
+        log4j_properties = Some(conf.get_log4j_properties(&resource.spec));
+        hadoop_config_files = conf.get_hadoop_config_files();
     }
 
     let conf = convert_map_to_string(&spark_defaults, " ");
@@ -107,6 +225,10 @@ where
     let mut data = BTreeMap::new();
     data.insert(SPARK_DEFAULTS_CONF.to_string(), conf);
     data.insert(SPARK_ENV_SH.to_string(), env);
+    if let Some(log4j_properties) = log4j_properties {
+        data.insert(LOG4J_PROPERTIES.to_string(), log4j_properties);
+    }
+    data.extend(hadoop_config_files);
 
     let cm = create_config_map(resource, &cm_name, data)?;
 
@@ -116,7 +238,10 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use k8s_openapi::api::core::v1::SecretKeySelector;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
     use stackable_spark_common::constants;
+    use stackable_spark_crd::ConfigOption;
     use stackable_spark_test_utils::cluster::{Data, TestSparkCluster};
 
     #[test]
@@ -132,7 +257,8 @@ mod tests {
 
     #[test]
     fn test_required_startup_env() {
-        let env_vars = create_required_startup_env();
+        let env_vars =
+            create_required_startup_env(&Runtime::StackableAgent, &SparkNodeType::Master);
         assert!(env_vars.contains(&EnvVar {
             name: SPARK_NO_DAEMONIZE.to_string(),
             value: Some("true".to_string()),
@@ -143,6 +269,66 @@ mod tests {
             value: Some("{{configroot}}/conf".to_string()),
             ..EnvVar::default()
         }));
+
+        assert!(!env_vars
+            .iter()
+            .any(|env_var| env_var.name == SPARK_ENV_MASTER_HOST));
+
+        let env_vars = create_required_startup_env(&Runtime::Kubernetes, &SparkNodeType::Worker);
+        assert!(env_vars.contains(&EnvVar {
+            name: SPARK_CONF_DIR.to_string(),
+            value: Some(SPARK_IMAGE_CONFIG_DIR.to_string()),
+            ..EnvVar::default()
+        }));
+        assert!(!env_vars
+            .iter()
+            .any(|env_var| env_var.name == SPARK_ENV_MASTER_HOST));
+
+        // masters on the kubernetes runtime bind to and advertise their pod IP
+        let env_vars = create_required_startup_env(&Runtime::Kubernetes, &SparkNodeType::Master);
+        let master_host = env_vars
+            .iter()
+            .find(|env_var| env_var.name == SPARK_ENV_MASTER_HOST)
+            .unwrap();
+        assert_eq!(
+            master_host
+                .value_from
+                .as_ref()
+                .and_then(|source| source.field_ref.as_ref())
+                .map(|field_ref| field_ref.field_path.as_str()),
+            Some(POD_IP_FIELD_PATH)
+        );
+    }
+
+    #[test]
+    fn test_create_container_env() {
+        let user_env = vec![
+            EnvVar {
+                name: SPARK_CONF_DIR.to_string(),
+                value: Some("/tmp".to_string()),
+                ..EnvVar::default()
+            },
+            EnvVar {
+                name: "AWS_ACCESS_KEY_ID".to_string(),
+                value_from: Some(EnvVarSource {
+                    secret_key_ref: Some(SecretKeySelector {
+                        name: Some("s3-credentials".to_string()),
+                        key: "accessKey".to_string(),
+                        ..SecretKeySelector::default()
+                    }),
+                    ..EnvVarSource::default()
+                }),
+                ..EnvVar::default()
+            },
+        ];
+
+        let env_vars =
+            create_container_env(&Runtime::Kubernetes, &SparkNodeType::Worker, &user_env);
+
+        let mut expected =
+            create_required_startup_env(&Runtime::Kubernetes, &SparkNodeType::Worker);
+        expected.push(user_env[1].clone());
+        assert_eq!(env_vars, expected);
     }
 
     #[test]
@@ -176,11 +362,8 @@ mod tests {
             Some(&TestSparkCluster::CLUSTER_MAX_PORT_RETRIES.to_string())
         );
 
-        assert!(spark_defaults.contains_key(constants::SPARK_DEFAULTS_MASTER_PORT));
-        assert_eq!(
-            spark_defaults.get(constants::SPARK_DEFAULTS_MASTER_PORT),
-            Some(&TestSparkCluster::MASTER_1_CONFIG_PORT.to_string())
-        );
+        // the override of the managed master port is rejected
+        assert!(!spark_defaults.contains_key(constants::SPARK_DEFAULTS_MASTER_PORT));
     }
 
     #[test]
@@ -194,7 +377,7 @@ mod tests {
                 TestSparkCluster::WORKER_1_ROLE_GROUP,
             )
             .unwrap()
-            .get_spark_env_sh();
+            .get_spark_env_sh(&spark_cluster.spec);
 
         assert!(spark_env_sh.contains_key(constants::SPARK_ENV_WORKER_PORT));
         assert_eq!(
@@ -211,7 +394,7 @@ mod tests {
         assert!(spark_env_sh.contains_key(constants::SPARK_ENV_WORKER_MEMORY));
         assert_eq!(
             spark_env_sh.get(constants::SPARK_ENV_WORKER_MEMORY),
-            Some(&TestSparkCluster::WORKER_1_ENV_MEMORY.to_string())
+            Some(&TestSparkCluster::WORKER_1_MEMORY.to_string())
         );
 
         assert!(spark_env_sh.contains_key(constants::SPARK_ENV_WORKER_CORES));
@@ -260,6 +443,108 @@ mod tests {
             .unwrap()
             .contains(constants::SPARK_ENV_WORKER_PORT));
 
+        assert!(cm_data
+            .get(constants::LOG4J_PROPERTIES)
+            .unwrap()
+            .contains("log4j.rootLogger=INFO, console"));
+
         // TODO: add more asserts
     }
+
+    #[test]
+    fn test_create_config_maps_without_managed_overrides() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+        spark_cluster
+            .spec
+            .masters
+            .selectors
+            .get_mut(TestSparkCluster::MASTER_1_ROLE_GROUP)
+            .unwrap()
+            .config
+            .as_mut()
+            .unwrap()
+            .spark_defaults
+            .as_mut()
+            .unwrap()
+            .push(ConfigOption {
+                name: constants::SPARK_DEFAULTS_AUTHENTICATE_SECRET.to_string(),
+                value: "overridden".to_string(),
+            });
+
+        let config = spark_cluster.spec.get_config(
+            &SparkNodeType::Master,
+            TestSparkCluster::MASTER_1_ROLE_GROUP,
+        );
+        let config_map =
+            create_config_map_with_data(&spark_cluster, config, "my_pod-config").unwrap();
+        let cm_data = config_map.data.unwrap();
+
+        // the managed secret and ports are rendered from the dedicated fields
+        let spark_defaults = cm_data.get(constants::SPARK_DEFAULTS_CONF).unwrap();
+        assert!(spark_defaults.contains(&format!(
+            "{} {}",
+            constants::SPARK_DEFAULTS_AUTHENTICATE_SECRET,
+            TestSparkCluster::CLUSTER_SECRET
+        )));
+        assert!(!spark_defaults.contains("overridden"));
+        assert!(!spark_defaults.contains(&TestSparkCluster::MASTER_1_CONFIG_PORT.to_string()));
+
+        let spark_env_sh = cm_data.get(constants::SPARK_ENV_SH).unwrap();
+        assert!(spark_env_sh.contains(&format!(
+            "{}={}",
+            constants::SPARK_ENV_MASTER_PORT,
+            TestSparkCluster::MASTER_1_PORT
+        )));
+        assert!(!spark_env_sh.contains(&TestSparkCluster::MASTER_1_ENV_PORT.to_string()));
+    }
+
+    #[test]
+    fn test_find_orphaned_config_maps() {
+        let spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        let cluster_name = spark_cluster.name();
+
+        let build_config_map = |name: String| ConfigMap {
+            metadata: ObjectMeta {
+                name: Some(name),
+                ..ObjectMeta::default()
+            },
+            ..ConfigMap::default()
+        };
+        let role_group_config_map = |role_group: &str, node_type: &SparkNodeType| {
+            build_config_map(create_config_map_name(&create_pod_name(
+                &cluster_name,
+                role_group,
+                &node_type.to_string(),
+                None,
+            )))
+        };
+
+        let config_maps = vec![
+            role_group_config_map(
+                TestSparkCluster::MASTER_1_ROLE_GROUP,
+                &SparkNodeType::Master,
+            ),
+            role_group_config_map(
+                TestSparkCluster::WORKER_1_ROLE_GROUP,
+                &SparkNodeType::Worker,
+            ),
+            // removed role group
+            role_group_config_map("removed", &SparkNodeType::Worker),
+            // existing role group of another role
+            role_group_config_map(
+                TestSparkCluster::WORKER_1_ROLE_GROUP,
+                &SparkNodeType::Master,
+            ),
+            // not a role group config map
+            build_config_map(format!("spark-{}-custom", cluster_name)),
+        ];
+
+        let orphaned = find_orphaned_config_maps(&spark_cluster, &config_maps)
+            .into_iter()
+            .map(|config_map| config_map.name())
+            .collect::<Vec<_>>();
+
+        assert_eq!(orphaned, vec![config_maps[2].name(), config_maps[3].name()]);
+    }
 }
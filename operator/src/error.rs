@@ -24,6 +24,50 @@ pub enum Error {
         source: semver::SemVerError,
     },
 
+    #[error("Error from http client: {source}")]
+    HttpError {
+        #[from]
+        source: reqwest::Error,
+    },
+
+    #[error("Could not query the Spark master JSON API [{url}]: {source}")]
+    MasterApiError { url: String, source: reqwest::Error },
+
+    #[error("Invalid cron schedule [{schedule}]: {source}")]
+    InvalidSchedule {
+        schedule: String,
+        source: cron::error::Error,
+    },
+
+    #[error("Could not read ConfigMap [{name}]: {source}")]
+    ConfigMapGetError {
+        name: String,
+        source: stackable_operator::error::Error,
+    },
+
+    #[error("Could not create ConfigMap [{name}]: {source}")]
+    ConfigMapCreateError {
+        name: String,
+        source: stackable_operator::error::Error,
+    },
+
+    #[error("Could not update ConfigMap [{name}]: {source}")]
+    ConfigMapUpdateError {
+        name: String,
+        source: stackable_operator::error::Error,
+    },
+
+    #[error("ConfigMap [{name}] was changed by someone else in the meantime, it will be updated in the next reconcile")]
+    ConfigMapConflict { name: String },
+
+    #[error("Extra volume [{name}] must reference exactly one of configMap or secret")]
+    InvalidExtraVolume { name: String },
+
+    #[error(
+        "The event log volume must reference exactly one of persistentVolumeClaim or hostPath"
+    )]
+    InvalidEventLogVolume,
+
     #[error("Pod contains invalid node type: {source}")]
     InvalidNodeType {
         #[from]
@@ -0,0 +1,164 @@
+//! This module contains all methods to publish Kubernetes events for operator actions on
+//! the SparkCluster, SparkApplication, SparkScheduledApplication and the command custom
+//! resources.
+use k8s_openapi::api::core::v1::{Event, EventSource, ObjectReference};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+use kube::Resource;
+use stackable_operator::client::Client;
+use tracing::warn;
+
+/// Component name that is shown as source of all published events
+const EVENT_SOURCE_COMPONENT: &str = "spark-operator";
+
+/// The event type as expected by Kubernetes
+#[derive(Clone, Debug, PartialEq, strum_macros::Display)]
+pub enum EventType {
+    Normal,
+    Warning,
+}
+
+/// The reason of an event. This is a short, machine readable description of what
+/// action the operator took.
+#[derive(Clone, Debug, PartialEq, strum_macros::Display)]
+pub enum EventReason {
+    PodCreated,
+    IllegalPodDeleted,
+    ExcessPodDeleted,
+    PodDeletionFailed,
+    PodReplaced,
+    WorkerReplaced,
+    WorkerUnregistered,
+    CommandStarted,
+    CommandFinished,
+    CommandFailed,
+    CommandCanceled,
+    InstallationStarted,
+    InstallationFinished,
+    UpgradeStarted,
+    UpgradeFinished,
+    DowngradeStarted,
+    DowngradeFinished,
+    ApplicationSubmitted,
+    ApplicationFinished,
+    ApplicationFailed,
+    RunScheduled,
+    RunSkipped,
+    RunReplaced,
+    WorkersScaledUp,
+    WorkersScaledDown,
+}
+
+/// Build an event for the given resource.
+///
+/// # Arguments
+/// * `resource` - The resource (SparkCluster or command) the event is about
+/// * `event_type` - Normal or Warning
+/// * `reason` - Machine readable reason for the event
+/// * `message` - Human readable description of the event
+///
+pub fn build_event<T>(
+    resource: &T,
+    event_type: &EventType,
+    reason: &EventReason,
+    message: &str,
+) -> Event
+where
+    T: Resource<DynamicType = ()>,
+{
+    let name = resource.name();
+    let namespace = resource.namespace();
+    let now = Time(chrono::Utc::now());
+
+    Event {
+        metadata: ObjectMeta {
+            generate_name: Some(format!("{}-", name)),
+            namespace: namespace.clone(),
+            ..ObjectMeta::default()
+        },
+        involved_object: ObjectReference {
+            api_version: Some(T::api_version(&()).to_string()),
+            kind: Some(T::kind(&()).to_string()),
+            name: Some(name),
+            namespace,
+            resource_version: resource.meta().resource_version.clone(),
+            uid: resource.meta().uid.clone(),
+            ..ObjectReference::default()
+        },
+        reason: Some(reason.to_string()),
+        message: Some(message.to_string()),
+        type_: Some(event_type.to_string()),
+        count: Some(1),
+        first_timestamp: Some(now.clone()),
+        last_timestamp: Some(now),
+        source: Some(EventSource {
+            component: Some(EVENT_SOURCE_COMPONENT.to_string()),
+            host: None,
+        }),
+        ..Event::default()
+    }
+}
+
+/// Publish an event for the given resource. Events are informative only, so failing to
+/// publish an event is logged but does not abort the reconcile.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `resource` - The resource (SparkCluster or command) the event is about
+/// * `event_type` - Normal or Warning
+/// * `reason` - Machine readable reason for the event
+/// * `message` - Human readable description of the event
+///
+pub async fn publish_event<T>(
+    client: &Client,
+    resource: &T,
+    event_type: EventType,
+    reason: EventReason,
+    message: &str,
+) where
+    T: Resource<DynamicType = ()>,
+{
+    let event = build_event(resource, &event_type, &reason, message);
+
+    if let Err(err) = client.create(&event).await {
+        warn!(
+            "Could not publish [{}] event for [{}]: {}",
+            reason,
+            resource.name(),
+            err
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_spark_crd::SparkCluster;
+
+    #[test]
+    fn test_build_event() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+        spark_cluster.metadata.namespace = Some("default".to_string());
+
+        let event = build_event(
+            &spark_cluster,
+            &EventType::Normal,
+            &EventReason::PodCreated,
+            "Created pod",
+        );
+
+        assert_eq!(event.reason, Some("PodCreated".to_string()));
+        assert_eq!(event.type_, Some("Normal".to_string()));
+        assert_eq!(event.message, Some("Created pod".to_string()));
+        assert_eq!(event.metadata.namespace, Some("default".to_string()));
+        assert_eq!(
+            event.metadata.generate_name,
+            Some(format!("{}-", spark_cluster.name()))
+        );
+
+        let involved_object = event.involved_object;
+        assert_eq!(involved_object.kind, Some("SparkCluster".to_string()));
+        assert_eq!(involved_object.name, Some(spark_cluster.name()));
+        assert_eq!(involved_object.uid, Some("12345".to_string()));
+    }
+}
@@ -1,21 +1,37 @@
+mod application;
+mod autoscaling;
+mod cleanup;
 mod command_utils;
+mod conditions;
 mod config;
 mod error;
+mod events;
+mod master_api;
+mod placement;
+mod pod_overrides;
 pub mod pod_utils;
+pub mod rbac;
+mod scheduled_application;
+mod status;
 
 use crate::error::Error;
 
+pub use crate::application::create_application_controller;
+pub use crate::scheduled_application::create_scheduled_application_controller;
+
+use crate::command_utils::{CommandCleanupSchedule, TimeoutAction};
+use crate::conditions::{ClusterCondition, ClusterConditionType};
 use crate::config::{create_config_map_name, create_config_map_with_data};
+use crate::events::{EventReason, EventType};
+use crate::master_api::MasterState;
 use crate::pod_utils::filter_pods_for_type;
 use async_trait::async_trait;
-use k8s_openapi::api::core::v1::{ConfigMap, Node, Pod};
-use k8s_openapi::apimachinery::pkg::apis::meta::v1::Condition;
+use k8s_openapi::api::core::v1::{ConfigMap, Node, Pod, ServiceAccount};
 use kube::api::ListParams;
 use kube::Api;
 use kube::Resource;
 use serde_json::json;
 use stackable_operator::client::Client;
-use stackable_operator::conditions::ConditionStatus;
 use stackable_operator::controller::{Controller, ControllerStrategy, ReconciliationState};
 use stackable_operator::error::OperatorResult;
 use stackable_operator::k8s_utils;
@@ -24,18 +40,20 @@ use stackable_operator::labels::{
     APP_COMPONENT_LABEL, APP_INSTANCE_LABEL, APP_ROLE_GROUP_LABEL, APP_VERSION_LABEL,
 };
 use stackable_operator::reconcile::{
-    ContinuationStrategy, ReconcileFunctionAction, ReconcileResult, ReconciliationContext,
+    ReconcileFunctionAction, ReconcileResult, ReconciliationContext,
 };
 use stackable_operator::role_utils;
 use stackable_operator::role_utils::RoleGroup;
-use stackable_spark_crd::commands::{Restart, Start, Stop};
+use stackable_spark_crd::commands::{Cancel, Pause, Restart, Resume, Scale, Start, Stop};
+use stackable_spark_crd::properties;
 use stackable_spark_crd::{
-    ClusterExecutionStatus, Config, CurrentCommand, NodeGroup, SparkCluster, SparkClusterStatus,
-    SparkNodeType, SparkVersion,
+    AutoscalingStatus, ClusterExecutionStatus, ClusterInfo, Config, CurrentCommand, NodeGroup,
+    Placement, PvcRetentionPolicy, SparkCluster, SparkClusterStatus, SparkNodeType, SparkVersion,
 };
 use std::collections::{BTreeMap, HashMap};
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use strum::IntoEnumIterator;
 use tracing::{debug, info, trace, warn};
@@ -46,42 +64,73 @@ struct SparkState {
     context: ReconciliationContext<SparkCluster>,
     existing_pods: Vec<Pod>,
     eligible_nodes: HashMap<SparkNodeType, HashMap<String, Vec<Node>>>,
+    /// Number of nodes matching the selector of every worker role group (before Node placement
+    /// limits the eligible nodes to the instances)
+    worker_node_counts: HashMap<String, usize>,
+    http_client: reqwest::Client,
+    /// Web ui url and reported state (None if unreachable) of all ready masters
+    master_states: Vec<(String, Option<MasterState>)>,
+    /// Cluster info derived from the polled masters (the status may contain an older usage)
+    cluster_info: Option<ClusterInfo>,
+    /// Cleanup schedule of the finished commands of all clusters (shared between reconciles)
+    command_cleanups: CommandCleanups,
 }
 
 impl SparkState {
-    async fn set_installing_condition(
-        &self,
-        conditions: &[Condition],
-        message: &str,
-        reason: &str,
-        status: ConditionStatus,
-    ) -> OperatorResult<SparkCluster> {
-        let resource = self
-            .context
-            .build_and_set_condition(
-                Some(conditions),
-                message.to_string(),
-                reason.to_string(),
-                status,
-                "Installing".to_string(),
-            )
-            .await?;
+    /// Publish an event for the SparkCluster this reconcile is working on.
+    async fn publish_event(&self, event_type: EventType, reason: EventReason, message: &str) {
+        events::publish_event(
+            &self.context.client,
+            &self.context.resource,
+            event_type,
+            reason,
+            message,
+        )
+        .await
+    }
 
-        Ok(resource)
+    /// Merge the given conditions into the current status conditions and write them if
+    /// anything changed. The conditions are merged again into the latest status if it was
+    /// changed in the meantime.
+    async fn set_conditions(&mut self, new_conditions: &[ClusterCondition]) -> OperatorResult<()> {
+        self.context.resource = status::update_status(
+            &self.context.client,
+            &self.context.resource,
+            |cluster: &SparkCluster| {
+                let current_conditions = cluster
+                    .status
+                    .as_ref()
+                    .map(|status| status.conditions.clone())
+                    .unwrap_or_default();
+
+                let merged_conditions = conditions::merge_conditions(
+                    &current_conditions,
+                    new_conditions,
+                    cluster.metadata.generation,
+                );
+
+                if merged_conditions != current_conditions {
+                    Some(json!({ "conditions": merged_conditions }))
+                } else {
+                    None
+                }
+            },
+        )
+        .await?;
+
+        Ok(())
     }
 
     async fn set_current_version(
         &self,
         version: Option<&SparkVersion>,
     ) -> OperatorResult<SparkCluster> {
-        let resource = self
-            .context
-            .client
-            .merge_patch_status(
-                &self.context.resource,
-                &json!({ "currentVersion": version }),
-            )
-            .await?;
+        let resource = status::patch_status(
+            &self.context.client,
+            &self.context.resource,
+            json!({ "currentVersion": version }),
+        )
+        .await?;
 
         Ok(resource)
     }
@@ -90,26 +139,94 @@ impl SparkState {
         &self,
         version: Option<&SparkVersion>,
     ) -> OperatorResult<SparkCluster> {
-        let resource = self
-            .context
-            .client
-            .merge_patch_status(&self.context.resource, &json!({ "targetVersion": version }))
-            .await?;
+        let resource = status::patch_status(
+            &self.context.client,
+            &self.context.resource,
+            json!({ "targetVersion": version }),
+        )
+        .await?;
 
         Ok(resource)
     }
 
+    /// Add the cleanup finalizer to new clusters. If the cluster was deleted, tear it down in
+    /// order: workers, history servers and masters are deleted one role after another, then
+    /// the PersistentVolumeClaims (depending on the retention policy) and the ConfigMaps.
+    /// The finalizer is removed at the end, which lets Kubernetes delete the cluster.
+    pub async fn handle_deletion(&mut self) -> SparkReconcileResult {
+        if !cleanup::is_being_deleted(&self.context.resource) {
+            if !cleanup::has_finalizer(&self.context.resource) {
+                self.context.resource =
+                    cleanup::add_finalizer(&self.context.client, &self.context.resource).await?;
+            }
+            return Ok(ReconcileFunctionAction::Continue);
+        }
+
+        if !cleanup::has_finalizer(&self.context.resource) {
+            return Ok(ReconcileFunctionAction::Done);
+        }
+
+        for node_type in &cleanup::TEARDOWN_ORDER {
+            let pods = filter_pods_for_type(&self.existing_pods, node_type);
+            if pods.is_empty() {
+                continue;
+            }
+
+            for pod in pods
+                .iter()
+                .filter(|pod| pod.metadata.deletion_timestamp.is_none())
+            {
+                info!(
+                    "Deleting [{}] pod [{}] of the deleted cluster",
+                    node_type,
+                    pod.name()
+                );
+                self.context.client.delete(pod).await?;
+            }
+            // wait until all pods of this role are gone before the next role is removed
+            return Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(5)));
+        }
+
+        if self.context.resource.spec.get_pvc_retention_policy() == PvcRetentionPolicy::Delete {
+            for pvc in
+                cleanup::list_persistent_volume_claims(&self.context.client, &self.context.resource)
+                    .await?
+            {
+                info!("Deleting PersistentVolumeClaim [{}]", pvc.name());
+                self.context.client.delete(&pvc).await?;
+            }
+        }
+
+        for config_map in
+            cleanup::list_owned_config_maps(&self.context.client, &self.context.resource).await?
+        {
+            info!("Deleting ConfigMap [{}]", config_map.name());
+            self.context.client.delete(&config_map).await?;
+        }
+
+        info!(
+            "Teardown of cluster [{}] finished, removing finalizer [{}]",
+            self.context.resource.name(),
+            cleanup::FINALIZER_NAME
+        );
+        cleanup::remove_finalizer(&self.context.client, &self.context.resource).await?;
+        self.command_cleanups
+            .lock()
+            .unwrap()
+            .remove(&get_cluster_key(&self.context.resource));
+
+        Ok(ReconcileFunctionAction::Done)
+    }
+
     /// Will initialize the status object if it's never been set.
     pub async fn init_status(&mut self) -> SparkReconcileResult {
         // We'll begin by setting an empty status here because later in this method we might
         // update its conditions. To avoid any issues we'll just create it once here.
         if self.context.resource.status.is_none() {
             let status = SparkClusterStatus::default();
-            self.context
-                .client
-                .merge_patch_status(&self.context.resource, &status)
-                .await?;
-            self.context.resource.status = Some(status);
+            self.context.resource =
+                status::patch_status(&self.context.client, &self.context.resource, json!(status))
+                    .await?;
         }
 
         // This should always return either the existing one or the one we just created above.
@@ -119,22 +236,19 @@ impl SparkState {
         match (&status.current_version, &status.target_version) {
             (None, None) => {
                 // No current_version and no target_version: Must be initial installation.
-                // We'll set the Upgrading condition and the target_version to the version from spec.
+                // We'll set the target_version to the version from spec. The Progressing
+                // condition is derived from the versions in `update_status_conditions`.
                 info!(
                     "Initial installation, now moving towards version [{}]",
                     spec_version
                 );
-                self.context.resource.status = self
-                    .set_installing_condition(
-                        &status.conditions,
-                        &format!("Initial installation to version [{:?}]", spec_version),
-                        "InitialInstallation",
-                        ConditionStatus::True,
-                    )
-                    .await?
-                    .status;
-                self.context.resource.status =
-                    self.set_target_version(Some(&spec_version)).await?.status;
+                self.context.resource = self.set_target_version(Some(&spec_version)).await?;
+                self.publish_event(
+                    EventType::Normal,
+                    EventReason::InstallationStarted,
+                    &format!("Initial installation to version [{}]", spec_version),
+                )
+                .await;
             }
             (None, Some(target_version)) => {
                 // No current_version but a target_version means we're still doing the initial
@@ -147,63 +261,41 @@ impl SparkState {
                 if &spec_version != target_version {
                     info!("A new target version ([{}]) was requested while we still do the initial installation to [{}], finishing running upgrade first", spec_version, target_version)
                 }
-                // We do this here to update the observedGeneration if needed
-                self.context.resource.status = self
-                    .set_installing_condition(
-                        &status.conditions,
-                        &format!("Initial installation to version [{:?}]", spec_version),
-                        "InitialInstallation",
-                        ConditionStatus::True,
-                    )
-                    .await?
-                    .status;
+                self.context.resource.status = Some(status.clone());
             }
             (Some(current_version), None) => {
                 // We are at a stable version but have no target_version set. This will be the normal state.
                 // We'll check if there is a different version in spec and if it is will set it in target_version.
                 // TODO: check valid up/downgrades
-                let message;
-                let reason;
                 if current_version.is_upgrade(&spec_version)? {
-                    message = format!(
-                        "Upgrading from [{:?}] to [{:?}]",
+                    let message = format!(
+                        "Upgrading from [{}] to [{}]",
                         current_version, &spec_version
                     );
-                    reason = "Upgrading";
-                    self.context.resource.status =
-                        self.set_target_version(Some(&spec_version)).await?.status;
+                    info!("{}", message);
+                    self.context.resource = self.set_target_version(Some(&spec_version)).await?;
+                    self.publish_event(EventType::Normal, EventReason::UpgradeStarted, &message)
+                        .await;
                 } else if current_version.is_downgrade(&spec_version)? {
-                    message = format!(
-                        "Downgrading from [{:?}] to [{:?}]",
+                    let message = format!(
+                        "Downgrading from [{}] to [{}]",
                         current_version, &spec_version
                     );
-                    reason = "Downgrading";
-                    self.context.resource.status =
-                        self.set_target_version(Some(&spec_version)).await?.status;
+                    info!("{}", message);
+                    self.context.resource = self.set_target_version(Some(&spec_version)).await?;
+                    self.publish_event(EventType::Normal, EventReason::DowngradeStarted, &message)
+                        .await;
                 } else {
-                    message = format!(
-                        "No upgrade/downgrade required [{:?}] is still the current_version",
+                    trace!(
+                        "No upgrade/downgrade required [{}] is still the current_version",
                         current_version
                     );
-                    reason = "";
+                    self.context.resource.status = Some(status.clone());
                 }
-
-                trace!("{}", message);
-
-                self.context.resource.status = self
-                    .set_installing_condition(
-                        &status.conditions,
-                        &message,
-                        &reason,
-                        ConditionStatus::False,
-                    )
-                    .await?
-                    .status;
             }
             (Some(current_version), Some(target_version)) => {
                 // current_version and target_version are set means we're still in the process
-                // of upgrading. We'll only do some logging and checks and will update
-                // the condition so observedGeneration can be updated.
+                // of upgrading. We'll only do some logging and checks here.
                 debug!(
                     "Still changing version from [{}] to [{}]",
                     current_version, target_version
@@ -211,57 +303,285 @@ impl SparkState {
                 if &self.context.resource.spec.version != target_version {
                     info!("A new target version was requested while we still upgrade from [{}] to [{}], finishing running upgrade/downgrade first", current_version, target_version)
                 }
-                let message = format!(
-                    "Changing version from [{:?}] to [{:?}]",
-                    current_version, target_version
-                );
+                self.context.resource.status = Some(status.clone());
+            }
+        }
 
-                self.context.resource.status = self
-                    .set_installing_condition(
-                        &status.conditions,
-                        &message,
-                        "",
-                        ConditionStatus::True,
-                    )
-                    .await?
-                    .status;
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Compute the ready / desired pods per role and the cluster conditions from the existing
+    /// pods and write them to the status.
+    pub async fn update_status_conditions(&mut self) -> SparkReconcileResult {
+        let status = self.context.resource.status.clone().unwrap_or_default();
+
+        let desired_pods = SparkNodeType::iter()
+            .filter_map(|node_type| {
+                placement::get_desired_pods(
+                    &self.context.resource.spec,
+                    &node_type,
+                    self.eligible_nodes.get(&node_type),
+                )
+                .map(|desired| (node_type, desired))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let masters = conditions::build_role_status(
+            &SparkNodeType::Master,
+            &self.context.name(),
+            &self.existing_pods,
+            desired_pods.get(&SparkNodeType::Master),
+        );
+        let workers = conditions::build_role_status(
+            &SparkNodeType::Worker,
+            &self.context.name(),
+            &self.existing_pods,
+            desired_pods.get(&SparkNodeType::Worker),
+        );
+        let history_servers = conditions::build_role_status(
+            &SparkNodeType::HistoryServer,
+            &self.context.name(),
+            &self.existing_pods,
+            desired_pods.get(&SparkNodeType::HistoryServer),
+        );
+
+        if status.masters != masters
+            || status.workers != workers
+            || status.history_servers != history_servers
+        {
+            self.context.resource = status::patch_status(
+                &self.context.client,
+                &self.context.resource,
+                json!({
+                    "masters": masters,
+                    "workers": workers,
+                    "historyServers": history_servers
+                }),
+            )
+            .await?;
+        }
+
+        // missing pods are expected while installing or changing the version
+        let check_missing_pods =
+            status.current_version.is_some() && status.target_version.is_none();
+        let mut degraded_role_groups = vec![];
+        for node_type in SparkNodeType::iter() {
+            degraded_role_groups.extend(conditions::find_degraded_role_groups(
+                &node_type,
+                &self.existing_pods,
+                self.eligible_nodes.get(&node_type),
+                desired_pods.get(&node_type),
+                check_missing_pods,
+            ));
+        }
+
+        let mut cluster_conditions = conditions::build_cluster_conditions(
+            &status,
+            &masters.unwrap_or_default(),
+            &workers.unwrap_or_default(),
+            &degraded_role_groups,
+            status.cluster_info.as_ref(),
+        );
+        cluster_conditions.push(conditions::build_configuration_condition(
+            &properties::validate_cluster_config(&self.context.resource.spec),
+        ));
+        self.set_conditions(&cluster_conditions).await?;
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Query the JSON API of all running and ready masters and write the aggregated cluster
+    /// info (master states, registered workers, resources and applications) to the status.
+    /// Unreachable masters are recorded in the status but do not fail the reconcile. Usage
+    /// changes are written rate limited, see `master_api::is_cluster_info_update_required`.
+    pub async fn poll_masters(&mut self) -> SparkReconcileResult {
+        let ready_master_pods = filter_pods_for_type(&self.existing_pods, &SparkNodeType::Master)
+            .into_iter()
+            .filter(|pod| pod_utils::is_pod_running_and_ready(pod))
+            .collect::<Vec<_>>();
+
+        let web_ui_urls = stackable_spark_crd::get_master_web_ui_urls(
+            &ready_master_pods,
+            &self.context.resource.spec,
+        );
+
+        let mut master_states = vec![];
+        for web_ui_url in web_ui_urls {
+            match master_api::get_master_state(&self.http_client, &web_ui_url).await {
+                Ok(master_state) => master_states.push((web_ui_url, Some(master_state))),
+                Err(err) => {
+                    warn!("{}", err);
+                    master_states.push((web_ui_url, None));
+                }
+            }
+        }
+
+        let cluster_info = if master_states.is_empty() {
+            None
+        } else {
+            let mut cluster_info = master_api::build_cluster_info(&master_states);
+
+            if let Some((_, Some(alive_master))) = master_api::find_alive_master(&master_states) {
+                let worker_pods = filter_pods_for_type(&self.existing_pods, &SparkNodeType::Worker);
+                let timeout = self
+                    .context
+                    .resource
+                    .spec
+                    .worker_registration_timeout_seconds
+                    .map(Duration::from_secs)
+                    .unwrap_or(master_api::DEFAULT_WORKER_REGISTRATION_TIMEOUT);
+
+                cluster_info.unregistered_workers =
+                    master_api::find_unregistered_workers(&worker_pods, alive_master, timeout)
+                        .iter()
+                        .map(|pod| pod.name())
+                        .collect();
             }
+
+            Some(cluster_info)
+        };
+
+        self.master_states = master_states;
+        self.cluster_info = cluster_info.clone();
+
+        let current_cluster_info = self
+            .context
+            .resource
+            .status
+            .as_ref()
+            .and_then(|status| status.cluster_info.as_ref());
+        let now = chrono::Utc::now();
+
+        if master_api::is_cluster_info_update_required(
+            current_cluster_info,
+            cluster_info.as_ref(),
+            now,
+        ) {
+            let cluster_info = cluster_info.map(|cluster_info| ClusterInfo {
+                last_update_time: Some(now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+                ..cluster_info
+            });
+
+            self.context.resource = status::patch_status(
+                &self.context.client,
+                &self.context.resource,
+                json!({ "clusterInfo": cluster_info }),
+            )
+            .await?;
         }
 
         Ok(ReconcileFunctionAction::Continue)
     }
 
+    /// Workers that are running and ready but did not register at the alive master within the
+    /// registration timeout are deleted (and recreated) one after another.
+    pub async fn replace_unregistered_workers(&self) -> SparkReconcileResult {
+        let unregistered_workers = self
+            .cluster_info
+            .as_ref()
+            .map(|cluster_info| cluster_info.unregistered_workers.clone())
+            .unwrap_or_default();
+
+        let worker_pods = filter_pods_for_type(&self.existing_pods, &SparkNodeType::Worker);
+
+        if let Some(pod) = worker_pods
+            .iter()
+            .find(|pod| unregistered_workers.contains(&pod.name()))
+        {
+            let alive_master = master_api::find_alive_master(&self.master_states)
+                .map(|(web_ui_url, _)| web_ui_url.as_str())
+                .unwrap_or("<no alive master found>");
+
+            warn!(
+                "Worker pod [{}] is not registered at the alive master [{}], deleting it",
+                pod.name(),
+                alive_master
+            );
+            self.context.client.delete(pod).await?;
+            self.publish_event(
+                EventType::Warning,
+                EventReason::WorkerUnregistered,
+                &format!(
+                    "Replacing worker pod [{}] because it did not register at the alive master [{}]",
+                    pod.name(),
+                    alive_master
+                ),
+            )
+            .await;
+            return Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(10)));
+        }
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Set the ReconcileError condition depending on the result of the reconcile. Failing to
+    /// set the condition is only logged to not hide the original reconcile result. The status
+    /// of a cluster that is being deleted is not written: the finalizer may already be removed
+    /// and the cluster gone.
+    async fn set_reconcile_error_condition(&mut self, result: &SparkReconcileResult) {
+        if cleanup::is_being_deleted(&self.context.resource) {
+            return;
+        }
+
+        let condition = match result {
+            Ok(_) => ClusterCondition::new(
+                ClusterConditionType::ReconcileError,
+                false,
+                "ReconcileSucceeded",
+                "Last reconcile finished without errors",
+            ),
+            Err(err) => ClusterCondition::new(
+                ClusterConditionType::ReconcileError,
+                true,
+                "ReconcileFailed",
+                &err.to_string(),
+            ),
+        };
+
+        if let Err(err) = self.set_conditions(&[condition]).await {
+            warn!("Could not set the [ReconcileError] condition: {}", err);
+        }
+    }
+
     /// Process available / running commands. If current_command in the status is set, we have
     /// a running command. If it is not set, but commands are available, start the oldest command.
     /// If no command is running, no command is waiting and the cluster_status field is "Stopped",
-    /// abort the reconcile action.
+    /// abort the reconcile action. While the cluster is paused only Resume commands are started.
     pub async fn process_commands(&mut self) -> SparkReconcileResult {
         if let Some(status) = self.context.resource.status.clone() {
+            let paused = status.cluster_execution_status == Some(ClusterExecutionStatus::Paused);
+
             // if a current_command is available we are currently processing that command
             if let Some(current_command) = &status.current_command {
-                let running_command = command_utils::get_command_from_ref(
+                return self.process_current_command(current_command).await;
+            }
+
+            // a Cancel command without a running command has nothing to abort
+            if let Some(cancel) =
+                command_utils::get_next_cancel_command(&self.context.client, &self.context.resource)
+                    .await?
+            {
+                command_utils::finalize_cancel_command(
                     &self.context.client,
-                    &current_command.command_type,
-                    &current_command.command_ref,
-                    self.context.resource.namespace().as_deref(),
+                    &cancel,
+                    "No command was running, nothing to cancel",
                 )
                 .await?;
+            }
 
-                return Ok(running_command
-                    .process_command_running(
-                        &self.context.client,
-                        &mut self.context.resource,
-                        current_command,
-                    )
-                    .await?);
             // if no current commands are running, check if any commands are available
-            } else if let Some(next_command) =
-                command_utils::get_next_command(&self.context.client).await?
+            if let Some(next_command) = command_utils::get_next_command(
+                &self.context.client,
+                &self.context.resource,
+                paused,
+            )
+            .await?
             {
                 let current_command = CurrentCommand {
                     command_ref: next_command.get_name(),
                     command_type: next_command.get_type(),
                     started_at: command_utils::get_current_timestamp(),
+                    retries: 0,
                 };
 
                 return Ok(next_command
@@ -283,6 +603,357 @@ impl SparkState {
         Ok(ReconcileFunctionAction::Continue)
     }
 
+    /// Process the command referenced by current_command in the status. The command is aborted
+    /// if it was deleted, a Cancel command is available or its timeout expired without retries
+    /// left. A timed out command with retries left is executed again.
+    async fn process_current_command(
+        &mut self,
+        current_command: &CurrentCommand,
+    ) -> SparkReconcileResult {
+        let client = &self.context.client;
+
+        let running_command = match command_utils::get_command_from_ref(
+            client,
+            &current_command.command_type,
+            &current_command.command_ref,
+            self.context.resource.namespace().as_deref(),
+        )
+        .await
+        {
+            Ok(running_command) => running_command,
+            Err(err) if command_utils::is_not_found(&err) => {
+                warn!(
+                    "[{}] command '{}' was deleted, aborting it",
+                    current_command.command_type, current_command.command_ref
+                );
+                command_utils::clear_current_command(client, &mut self.context.resource).await?;
+                self.publish_event(
+                    EventType::Warning,
+                    EventReason::CommandCanceled,
+                    &format!(
+                        "Aborted [{}] command [{}] because it was deleted",
+                        current_command.command_type, current_command.command_ref
+                    ),
+                )
+                .await;
+                return Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(10)));
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        if let Some(cancel) =
+            command_utils::get_next_cancel_command(client, &self.context.resource).await?
+        {
+            let reason = format!(
+                "Canceled by [{}] command [{}]",
+                Cancel::kind(&()),
+                cancel.name()
+            );
+            let action = running_command
+                .process_command_failed(client, &mut self.context.resource, &reason)
+                .await?;
+            command_utils::finalize_cancel_command(
+                client,
+                &cancel,
+                &format!(
+                    "Canceled [{}] command [{}]",
+                    current_command.command_type, current_command.command_ref
+                ),
+            )
+            .await?;
+            return Ok(action);
+        }
+
+        match command_utils::check_timeout(
+            current_command,
+            running_command.get_timeout(),
+            running_command.get_max_retries(),
+            chrono::Utc::now(),
+        ) {
+            TimeoutAction::Wait => Ok(running_command
+                .process_command_running(client, &mut self.context.resource, current_command)
+                .await?),
+            TimeoutAction::Retry => {
+                let retried_command = CurrentCommand {
+                    started_at: command_utils::get_current_timestamp(),
+                    retries: current_command.retries + 1,
+                    ..current_command.clone()
+                };
+                warn!(
+                    "[{}] command '{}' timed out, retrying ({}/{})",
+                    current_command.command_type,
+                    current_command.command_ref,
+                    retried_command.retries,
+                    running_command.get_max_retries()
+                );
+                Ok(running_command
+                    .process_command_execute(
+                        client,
+                        &self.context.resource,
+                        &retried_command,
+                        &self.existing_pods,
+                    )
+                    .await?)
+            }
+            TimeoutAction::Fail => {
+                let reason = format!(
+                    "Timed out after {} seconds and {} retries",
+                    running_command.get_timeout().unwrap_or_default().as_secs(),
+                    current_command.retries
+                );
+                Ok(running_command
+                    .process_command_failed(client, &mut self.context.resource, &reason)
+                    .await?)
+            }
+        }
+    }
+
+    /// Delete finished commands of this cluster whose TTL expired or that exceed the command
+    /// history limit. This only runs when due according to the `CommandCleanupSchedule` of the
+    /// cluster (e.g. after a command finished), not on every reconcile.
+    pub async fn delete_expired_commands(&self) -> SparkReconcileResult {
+        let key = get_cluster_key(&self.context.resource);
+        let now = chrono::Utc::now();
+
+        let due = self
+            .command_cleanups
+            .lock()
+            .unwrap()
+            .get(&key)
+            .map(|schedule| schedule.is_due(&self.context.resource, now))
+            .unwrap_or(true);
+        if !due {
+            return Ok(ReconcileFunctionAction::Continue);
+        }
+
+        let (deleted, next_expiry) = command_utils::delete_expired_commands(
+            &self.context.client,
+            &self.context.resource,
+            now,
+        )
+        .await?;
+        if deleted > 0 {
+            debug!("Deleted [{}] finished commands", deleted);
+        }
+
+        self.command_cleanups.lock().unwrap().insert(
+            key,
+            CommandCleanupSchedule::new(&self.context.resource, next_expiry, now),
+        );
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// While the cluster is paused, pods are neither created nor deleted and no configuration
+    /// is rolled out. Only the running Pause command and Resume commands are processed, the
+    /// rest of the reconcile is skipped.
+    pub async fn process_paused(&mut self) -> SparkReconcileResult {
+        let paused = self
+            .context
+            .resource
+            .status
+            .as_ref()
+            .and_then(|status| status.cluster_execution_status.as_ref())
+            == Some(&ClusterExecutionStatus::Paused);
+
+        if !paused {
+            return Ok(ReconcileFunctionAction::Continue);
+        }
+
+        match self.process_commands().await? {
+            ReconcileFunctionAction::Continue => {
+                info!("Cluster is paused. Waiting for Resume command!");
+                Ok(ReconcileFunctionAction::Done)
+            }
+            action => Ok(action),
+        }
+    }
+
+    /// Scale worker role groups with autoscaling settings depending on the core utilization
+    /// and the waiting applications reported by the alive master. The new instances are
+    /// patched into the role group (like a manual scale) and the decision is stored in the
+    /// status. Autoscaling is skipped while `spec.workers.replicas` is set.
+    pub async fn autoscale_workers(&mut self) -> SparkReconcileResult {
+        let spec = self.context.resource.spec.clone();
+        if spec.workers.replicas.is_some() {
+            return Ok(ReconcileFunctionAction::Continue);
+        }
+
+        let alive_master = match master_api::find_alive_master(&self.master_states) {
+            Some((_, Some(alive_master))) => alive_master.clone(),
+            _ => return Ok(ReconcileFunctionAction::Continue),
+        };
+
+        let status = self.context.resource.status.clone().unwrap_or_default();
+        let cluster_info = match &self.cluster_info {
+            Some(cluster_info) => cluster_info.clone(),
+            None => return Ok(ReconcileFunctionAction::Continue),
+        };
+
+        let now = chrono::Utc::now();
+        let core_utilization = autoscaling::get_core_utilization(&cluster_info);
+        let mut autoscaling_status = BTreeMap::new();
+        let mut scaled = false;
+
+        for (role_group, selector) in &spec.workers.selectors {
+            let autoscaling = match &selector.autoscaling {
+                Some(autoscaling) => autoscaling,
+                None => continue,
+            };
+
+            let current_instances = selector.instances;
+            let previous_status = status.autoscaling.get(role_group);
+            let last_scale_time =
+                previous_status.and_then(|previous| previous.last_scale_time.clone());
+
+            let decision = autoscaling::compute_desired_instances(
+                autoscaling,
+                current_instances,
+                &cluster_info,
+            );
+            let mut desired_instances = decision.desired_instances;
+            let mut message = decision.message;
+
+            if desired_instances != current_instances
+                && autoscaling::is_in_cooldown(
+                    autoscaling,
+                    desired_instances > current_instances,
+                    last_scale_time.as_deref(),
+                    now,
+                )
+            {
+                desired_instances = current_instances;
+                message = format!("{} (cooling down)", message);
+            }
+
+            if desired_instances > current_instances && spec.get_placement() == Placement::Node {
+                let eligible_node_count = self
+                    .worker_node_counts
+                    .get(role_group)
+                    .cloned()
+                    .unwrap_or_default();
+                let limited_instances = autoscaling::limit_scale_up(
+                    current_instances,
+                    desired_instances,
+                    eligible_node_count,
+                );
+                if limited_instances != desired_instances {
+                    desired_instances = limited_instances;
+                    message = format!(
+                        "{} (limited to {} eligible nodes)",
+                        message, eligible_node_count
+                    );
+                }
+            }
+
+            if desired_instances < current_instances && !spec.supports_graceful_decommission() {
+                desired_instances = current_instances;
+                message = format!(
+                    "{} (scaling down requires the graceful decommission of Spark 3.1 and the kubernetes runtime)",
+                    message
+                );
+            }
+
+            if desired_instances < current_instances {
+                let group_pods = pod_utils::filter_pods_for_role_group(
+                    &self.existing_pods,
+                    &SparkNodeType::Worker,
+                    role_group,
+                );
+                let eligible_nodes = self
+                    .eligible_nodes
+                    .get(&SparkNodeType::Worker)
+                    .and_then(|groups| groups.get(role_group))
+                    .cloned()
+                    .unwrap_or_default();
+                let pods_by_position = placement::get_pods_by_position(
+                    &spec.get_placement(),
+                    &group_pods,
+                    &eligible_nodes,
+                    usize::from(current_instances),
+                );
+
+                let limited_instances = autoscaling::limit_scale_down(
+                    current_instances,
+                    desired_instances,
+                    &pods_by_position,
+                    &alive_master,
+                );
+                if limited_instances != desired_instances {
+                    desired_instances = limited_instances;
+                    message = format!("{} (waiting for busy workers)", message);
+                }
+            }
+
+            let mut last_scale_time = last_scale_time;
+
+            if desired_instances != current_instances {
+                info!(
+                    "Scaling worker role group [{}] from [{}] to [{}] instances: {}",
+                    role_group, current_instances, desired_instances, message
+                );
+                self.context.resource = self
+                    .context
+                    .client
+                    .merge_patch(
+                        &self.context.resource,
+                        json!({ "spec": { "workers": { "selectors": { role_group: { "instances": desired_instances } } } } }),
+                    )
+                    .await?;
+
+                let reason = if desired_instances > current_instances {
+                    EventReason::WorkersScaledUp
+                } else {
+                    EventReason::WorkersScaledDown
+                };
+                self.publish_event(
+                    EventType::Normal,
+                    reason,
+                    &format!("Worker role group [{}]: {}", role_group, message),
+                )
+                .await;
+
+                last_scale_time = Some(now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true));
+                scaled = true;
+            }
+
+            autoscaling_status.insert(
+                role_group.clone(),
+                AutoscalingStatus {
+                    current_instances,
+                    desired_instances,
+                    core_utilization,
+                    message,
+                    last_scale_time,
+                },
+            );
+        }
+
+        if autoscaling_status != status.autoscaling {
+            // role groups without autoscaling are removed from the status
+            let mut patch = serde_json::Map::new();
+            for role_group in status.autoscaling.keys() {
+                patch.insert(role_group.clone(), serde_json::Value::Null);
+            }
+            for (role_group, group_status) in &autoscaling_status {
+                patch.insert(role_group.clone(), serde_json::to_value(group_status)?);
+            }
+
+            self.context.resource = status::patch_status(
+                &self.context.client,
+                &self.context.resource,
+                json!({ "autoscaling": patch }),
+            )
+            .await?;
+        }
+
+        if scaled {
+            return Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(10)));
+        }
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
     pub fn get_full_pod_node_map(&self) -> Vec<(Vec<Node>, LabelOptionalValueMap)> {
         let mut eligible_nodes_map = vec![];
 
@@ -325,43 +996,278 @@ impl SparkState {
         mandatory_labels
     }
 
+    /// Delete all pods that miss one of the mandatory deletion labels (or have an invalid
+    /// value) and publish an event for each of them.
+    async fn delete_illegal_pods(&self) -> SparkReconcileResult {
+        let deletion_labels = self.get_deletion_labels();
+        let mut changes_applied = false;
+
+        for pod in k8s_utils::find_invalid_pods(&self.existing_pods, &deletion_labels) {
+            self.delete_pod(
+                pod,
+                EventReason::IllegalPodDeleted,
+                "it is missing required labels or has invalid label values",
+            )
+            .await?;
+            changes_applied = true;
+        }
+
+        if changes_applied {
+            return Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(10)));
+        }
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Delete all pods that are not required anymore (e.g. node does not match the selector
+    /// anymore) and publish an event for each of them.
+    async fn delete_excess_pods(&self) -> SparkReconcileResult {
+        if self.context.resource.spec.get_placement() == Placement::Scheduler {
+            return self.delete_excess_scheduled_pods().await;
+        }
+
+        let nodes_and_labels = self.get_full_pod_node_map();
+        let mut changes_applied = false;
+
+        for pod in k8s_utils::find_excess_pods(nodes_and_labels.as_slice(), &self.existing_pods) {
+            self.delete_pod(
+                pod,
+                EventReason::ExcessPodDeleted,
+                "it is not required by any role group anymore",
+            )
+            .await?;
+            changes_applied = true;
+        }
+
+        if changes_applied {
+            return Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(10)));
+        }
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Delete the given pod and publish an event once the deletion was accepted. If the
+    /// deletion fails, a warning event is published and the error is returned.
+    ///
+    /// # Arguments
+    /// * `pod` - The pod to delete
+    /// * `reason` - The reason of the event published after the deletion
+    /// * `cause` - Why the pod is deleted (part of the event message)
+    ///
+    async fn delete_pod(&self, pod: &Pod, reason: EventReason, cause: &str) -> OperatorResult<()> {
+        match self.context.client.delete(pod).await {
+            Ok(_) => {
+                self.publish_event(
+                    EventType::Normal,
+                    reason,
+                    &format!("Deleted pod [{}] because {}", pod.name(), cause),
+                )
+                .await;
+                Ok(())
+            }
+            Err(err) => {
+                self.publish_event(
+                    EventType::Warning,
+                    EventReason::PodDeletionFailed,
+                    &format!(
+                        "Failed to delete pod [{}] because {}: {}",
+                        pod.name(),
+                        cause,
+                        err
+                    ),
+                )
+                .await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Create the ServiceAccount the pods of the cluster run under if it does not exist.
+    async fn create_service_account(&self) -> SparkReconcileResult {
+        let service_account = rbac::build_service_account(&self.context.resource)?;
+        let name = service_account.name();
+
+        match self
+            .context
+            .client
+            .get::<ServiceAccount>(&name, Some(&self.context.namespace()))
+            .await
+        {
+            Ok(_) => {}
+            Err(err) if command_utils::is_not_found(&err) => {
+                info!("ServiceAccount [{}] does not exist, creating it!", name);
+                if let Err(err) = self.context.client.create(&service_account).await {
+                    // already exists: created by someone else since we read it
+                    if !command_utils::is_conflict(&err) {
+                        return Err(err.into());
+                    }
+                }
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Delete the role group config maps of the cluster that do not belong to a current role
+    /// group anymore (e.g. the role group was removed or renamed).
+    async fn delete_orphaned_config_maps(&self) -> SparkReconcileResult {
+        let config_maps =
+            cleanup::list_owned_config_maps(&self.context.client, &self.context.resource).await?;
+
+        for config_map in config::find_orphaned_config_maps(&self.context.resource, &config_maps) {
+            info!(
+                "Deleting ConfigMap [{}] because its role group does not exist anymore",
+                config_map.name()
+            );
+            self.context.client.delete(config_map).await?;
+        }
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Delete all scheduled pods that exceed the instances of their role group (or whose role
+    /// group was removed) and publish an event for each of them. Pods placed via `nodeName`
+    /// before the switch to Scheduler placement are replaced one role group at a time.
+    async fn delete_excess_scheduled_pods(&self) -> SparkReconcileResult {
+        let spec = &self.context.resource.spec;
+        let mut changes_applied = false;
+
+        for node_type in SparkNodeType::iter() {
+            // roles without eligible nodes (e.g. a history server without a shared event log)
+            // must not run any pods
+            let excess_pods = if self.eligible_nodes.contains_key(&node_type) {
+                placement::find_excess_scheduled_pods(&self.existing_pods, spec, &node_type)
+            } else {
+                filter_pods_for_type(&self.existing_pods, &node_type)
+            };
+
+            for pod in excess_pods {
+                self.delete_pod(
+                    &pod,
+                    EventReason::ExcessPodDeleted,
+                    "it exceeds the instances of its role group",
+                )
+                .await?;
+                changes_applied = true;
+            }
+        }
+
+        if changes_applied {
+            return Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(10)));
+        }
+
+        for pod in placement::find_node_placed_pods_to_replace(
+            &self.existing_pods,
+            spec,
+            &self.get_role_groups(),
+        ) {
+            self.delete_pod(
+                &pod,
+                EventReason::PodReplaced,
+                "the cluster switched to Scheduler placement",
+            )
+            .await?;
+            changes_applied = true;
+        }
+
+        if changes_applied {
+            return Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(10)));
+        }
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Return the role groups with eligible nodes of all roles in start order (masters,
+    /// workers, history servers) and ordered by name within a role.
+    fn get_role_groups(&self) -> Vec<(SparkNodeType, String)> {
+        let mut role_groups = vec![];
+
+        for node_type in SparkNodeType::iter() {
+            if let Some(nodes_for_role) = self.eligible_nodes.get(&node_type) {
+                let mut names = nodes_for_role.keys().cloned().collect::<Vec<_>>();
+                names.sort();
+                role_groups.extend(names.into_iter().map(|name| (node_type.clone(), name)));
+            }
+        }
+
+        role_groups
+    }
+
     /// Create or update a config map.
     /// - Create if no config map of that name exists
     /// - Update if config map exists but the content differs
     /// - Do nothing if the config map exists and the content is identical
+    /// - Fail with a conflict if the config map was changed or created in between, so it is
+    ///   written in the next reconcile based on the latest version
     async fn create_config_map<T>(&self, cm_name: &str, config: Option<T>) -> Result<(), Error>
     where
         T: Config,
     {
         let config_map = create_config_map_with_data(&self.context.resource, config, cm_name)?;
 
-        match self
+        let existing_config_map = match self
             .context
             .client
             .get::<ConfigMap>(cm_name, Some(&self.context.namespace()))
             .await
         {
-            Ok(ConfigMap {
-                data: existing_config_map_data,
-                ..
-            }) if existing_config_map_data == config_map.data => {
+            Ok(existing_config_map) => Some(existing_config_map),
+            Err(err) if command_utils::is_not_found(&err) => None,
+            Err(source) => {
+                return Err(Error::ConfigMapGetError {
+                    name: cm_name.to_string(),
+                    source,
+                })
+            }
+        };
+
+        match existing_config_map {
+            Some(existing_config_map) if existing_config_map.data == config_map.data => {
                 debug!(
                     "ConfigMap [{}] already exists with identical data, skipping creation!",
                     cm_name
                 );
             }
-            Ok(_) => {
+            Some(existing_config_map) => {
                 debug!(
                     "ConfigMap [{}] already exists, but differs, updating it!",
                     cm_name
                 );
-                self.context.client.update(&config_map).await?;
+                // The resourceVersion of the read ConfigMap makes the update fail with a
+                // conflict instead of overwriting changes made in between.
+                let mut config_map = config_map;
+                config_map.metadata.resource_version =
+                    existing_config_map.metadata.resource_version;
+
+                if let Err(err) = self.context.client.update(&config_map).await {
+                    return Err(if command_utils::is_conflict(&err) {
+                        Error::ConfigMapConflict {
+                            name: cm_name.to_string(),
+                        }
+                    } else {
+                        Error::ConfigMapUpdateError {
+                            name: cm_name.to_string(),
+                            source: err,
+                        }
+                    });
+                }
             }
-            Err(e) => {
-                // TODO: This is shit, but works for now. If there is an actual error in comes with
-                //   K8S, it will most probably also occur further down and be properly handled
-                debug!("Error getting ConfigMap [{}]: [{:?}]", cm_name, e);
-                self.context.client.create(&config_map).await?;
+            None => {
+                debug!("ConfigMap [{}] does not exist, creating it!", cm_name);
+                if let Err(err) = self.context.client.create(&config_map).await {
+                    // already exists: created by someone else since we read it
+                    return Err(if command_utils::is_conflict(&err) {
+                        Error::ConfigMapConflict {
+                            name: cm_name.to_string(),
+                        }
+                    } else {
+                        Error::ConfigMapCreateError {
+                            name: cm_name.to_string(),
+                            source: err,
+                        }
+                    });
+                }
             }
         }
 
@@ -393,6 +1299,13 @@ impl SparkState {
                 let config = self.context.resource.spec.get_config(node_type, role_group);
                 self.create_config_map(&cm_name, config).await?;
 
+                if self.context.resource.spec.get_placement() == Placement::Scheduler {
+                    changes_applied |= self
+                        .create_missing_scheduled_pods(node_type, role_group)
+                        .await?;
+                    continue;
+                }
+
                 debug!(
                     "Identify missing pods for [{}] role and group [{}]",
                     node_type, role_group
@@ -460,6 +1373,18 @@ impl SparkState {
                     )?;
 
                     self.context.client.create(&pod).await?;
+                    self.publish_event(
+                        EventType::Normal,
+                        EventReason::PodCreated,
+                        &format!(
+                            "Created pod [{}] on node [{}] for [{}] role and group [{}]",
+                            pod.name(),
+                            node_name,
+                            node_type,
+                            role_group
+                        ),
+                    )
+                    .await;
                     changes_applied = true;
                 }
             }
@@ -474,6 +1399,78 @@ impl SparkState {
         Ok(ReconcileFunctionAction::Continue)
     }
 
+    /// Create the missing pods of a role group for Scheduler placement. Pods are identified
+    /// by their index within the role group, so exactly `instances` pods are created and the
+    /// Kubernetes scheduler decides on which nodes they run.
+    /// Returns true if any pod was created.
+    ///
+    /// # Arguments
+    /// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+    /// * `role_group` - The role group of the selector
+    ///
+    async fn create_missing_scheduled_pods(
+        &self,
+        node_type: &SparkNodeType,
+        role_group: &str,
+    ) -> Result<bool, Error> {
+        let instances = self
+            .context
+            .resource
+            .spec
+            .get_instances(node_type, role_group)
+            .map(usize::from)
+            .unwrap_or_default();
+
+        let group_pods =
+            pod_utils::filter_pods_for_role_group(&self.existing_pods, node_type, role_group);
+
+        // pods placed via nodeName are replaced first (see delete_excess_scheduled_pods)
+        if group_pods
+            .iter()
+            .any(|pod| placement::get_pod_index(pod).is_none())
+        {
+            return Ok(false);
+        }
+
+        let missing_indices = placement::find_missing_pod_indices(&group_pods, instances);
+
+        if missing_indices.is_empty() {
+            return Ok(false);
+        }
+
+        let master_pods = filter_pods_for_type(&self.existing_pods, &SparkNodeType::Master);
+        let master_urls = stackable_spark_crd::get_master_urls(
+            master_pods.as_slice(),
+            &self.context.resource.spec,
+        );
+        debug!("Found master urls: {:?}", master_urls);
+
+        for pod_index in missing_indices {
+            let pod = pod_utils::build_scheduled_pod(
+                &self.context.resource,
+                pod_index,
+                role_group,
+                node_type,
+                &master_urls,
+            )?;
+
+            self.context.client.create(&pod).await?;
+            self.publish_event(
+                EventType::Normal,
+                EventReason::PodCreated,
+                &format!(
+                    "Created pod [{}] for [{}] role and group [{}]",
+                    pod.name(),
+                    node_type,
+                    role_group
+                ),
+            )
+            .await;
+        }
+
+        Ok(true)
+    }
+
     /// In spark stand alone, workers are started via script and require the master urls to connect to.
     /// If masters change (added/deleted), workers need to be updated accordingly to be able
     /// to fall back on other masters, if the primary master fails.
@@ -503,6 +1500,15 @@ impl SparkState {
                             current_hashed_master_urls,
                         );
                         self.context.client.delete(pod).await?;
+                        self.publish_event(
+                            EventType::Normal,
+                            EventReason::WorkerReplaced,
+                            &format!(
+                                "Replacing worker pod [{}] because the known master urls changed",
+                                pod.name()
+                            ),
+                        )
+                        .await;
                         return Ok(ReconcileFunctionAction::Requeue(Duration::from_secs(10)));
                     }
                 }
@@ -547,28 +1553,81 @@ impl SparkState {
                     &target_version
                 );
 
-                self.context.resource.status = self.set_target_version(None).await?.status;
-                self.context.resource.status = self
-                    .set_current_version(Some(&target_version))
-                    .await?
-                    .status;
-                self.context.resource.status = self
-                    .set_installing_condition(
-                        &status.conditions,
-                        &format!(
-                            "No change required [{:?}] is still the current_version",
-                            target_version
-                        ),
-                        "",
-                        ConditionStatus::False,
-                    )
-                    .await?
-                    .status;
+                let reason = match &status.current_version {
+                    None => EventReason::InstallationFinished,
+                    Some(current_version) if current_version.is_downgrade(target_version)? => {
+                        EventReason::DowngradeFinished
+                    }
+                    Some(_) => EventReason::UpgradeFinished,
+                };
+                self.publish_event(
+                    EventType::Normal,
+                    reason,
+                    &format!("Cluster is running version [{}]", target_version),
+                )
+                .await;
+
+                self.context.resource = self.set_target_version(None).await?;
+                self.context.resource = self.set_current_version(Some(&target_version)).await?;
+                // update the Progressing and Upgrading conditions with the new versions
+                self.update_status_conditions().await?;
             }
         }
 
         Ok(ReconcileFunctionAction::Continue)
     }
+
+    /// Run all reconcile steps in order.
+    async fn reconcile_cluster(&mut self) -> SparkReconcileResult {
+        self.handle_deletion()
+            .await?
+            .then(self.init_status())
+            .await?
+            .then(self.poll_masters())
+            .await?
+            .then(self.update_status_conditions())
+            .await?
+            .then(self.delete_expired_commands())
+            .await?
+            .then(self.process_paused())
+            .await?
+            .then(self.delete_illegal_pods())
+            .await?
+            .then(
+                self.context
+                    .wait_for_terminating_pods(self.existing_pods.as_slice()),
+            )
+            .await?
+            .then(
+                self.context
+                    .wait_for_running_and_ready_pods(&self.existing_pods),
+            )
+            .await?
+            .then(self.replace_unregistered_workers())
+            .await?
+            .then(self.process_commands())
+            .await?
+            .then(self.autoscale_workers())
+            .await?
+            .then(self.delete_excess_pods())
+            .await?
+            .then(self.delete_orphaned_config_maps())
+            .await?
+            .then(self.create_service_account())
+            .await?
+            .then(self.create_missing_pods(&SparkNodeType::Master))
+            .await?
+            .then(self.create_missing_pods(&SparkNodeType::Worker))
+            .await?
+            .then(self.create_missing_pods(&SparkNodeType::HistoryServer))
+            .await?
+            .then(self.check_worker_master_urls())
+            .await?
+            .then(self.finalize_commands())
+            .await?
+            .then(self.process_version())
+            .await
+    }
 }
 
 impl ReconciliationState for SparkState {
@@ -582,54 +1641,26 @@ impl ReconciliationState for SparkState {
         debug!("Deletion Labels: [{:?}]", &self.get_deletion_labels());
 
         Box::pin(async move {
-            self.init_status()
-                .await?
-                .then(self.context.delete_illegal_pods(
-                    self.existing_pods.as_slice(),
-                    &self.get_deletion_labels(),
-                    ContinuationStrategy::OneRequeue,
-                ))
-                .await?
-                .then(
-                    self.context
-                        .wait_for_terminating_pods(self.existing_pods.as_slice()),
-                )
-                .await?
-                .then(
-                    self.context
-                        .wait_for_running_and_ready_pods(&self.existing_pods),
-                )
-                .await?
-                .then(self.process_commands())
-                .await?
-                .then(self.context.delete_excess_pods(
-                    self.get_full_pod_node_map().as_slice(),
-                    &self.existing_pods,
-                    ContinuationStrategy::OneRequeue,
-                ))
-                .await?
-                .then(self.create_missing_pods(&SparkNodeType::Master))
-                .await?
-                .then(self.create_missing_pods(&SparkNodeType::Worker))
-                .await?
-                .then(self.create_missing_pods(&SparkNodeType::HistoryServer))
-                .await?
-                .then(self.check_worker_master_urls())
-                .await?
-                .then(self.finalize_commands())
-                .await?
-                .then(self.process_version())
-                .await
+            let result = self.reconcile_cluster().await;
+            self.set_reconcile_error_condition(&result).await;
+            result
         })
     }
 }
 
+/// Cleanup schedule of the finished commands per cluster (see `get_cluster_key`)
+type CommandCleanups = Arc<Mutex<HashMap<String, CommandCleanupSchedule>>>;
+
 #[derive(Debug)]
-struct SparkStrategy {}
+struct SparkStrategy {
+    command_cleanups: CommandCleanups,
+}
 
 impl SparkStrategy {
     pub fn new() -> SparkStrategy {
-        SparkStrategy {}
+        SparkStrategy {
+            command_cleanups: Arc::new(Mutex::new(HashMap::new())),
+        }
     }
 }
 
@@ -660,21 +1691,71 @@ impl ControllerStrategy for SparkStrategy {
             get_nodes_that_fit_selectors(&context.client, &cluster_spec.workers).await?,
         );
 
+        // without a shared event log the history server could not show any application, the
+        // ConfigurationValid condition reports why it is not started
         if let Some(history_servers) = &cluster_spec.history_servers {
-            eligible_nodes.insert(
-                SparkNodeType::HistoryServer,
-                get_nodes_that_fit_selectors(&context.client, &history_servers).await?,
-            );
+            if !cluster_spec.has_shared_event_log() {
+                warn!(
+                    "Not starting history servers of [{}]: the event log is not shared",
+                    context.resource.name()
+                );
+            } else {
+                eligible_nodes.insert(
+                    SparkNodeType::HistoryServer,
+                    get_nodes_that_fit_selectors(&context.client, &history_servers).await?,
+                );
+            }
+        }
+
+        // the autoscaling of Node placed workers is limited to the nodes matching the selector
+        let worker_node_counts = eligible_nodes
+            .get(&SparkNodeType::Worker)
+            .iter()
+            .flat_map(|groups| groups.iter())
+            .map(|(role_group, nodes)| (role_group.clone(), nodes.len()))
+            .collect();
+
+        // with Node placement one pod is created per eligible node, so the eligible nodes are
+        // limited to the instances of each role group if requested
+        if cluster_spec.get_placement() == Placement::Node
+            && cluster_spec.is_node_placement_limited()
+        {
+            eligible_nodes = eligible_nodes
+                .into_iter()
+                .map(|(node_type, nodes)| {
+                    let nodes = placement::limit_eligible_nodes(
+                        nodes,
+                        cluster_spec,
+                        &node_type,
+                        &existing_pods,
+                    );
+                    (node_type, nodes)
+                })
+                .collect();
         }
 
         Ok(SparkState {
             context,
             existing_pods,
             eligible_nodes,
+            worker_node_counts,
+            http_client: master_api::build_http_client()?,
+            master_states: vec![],
+            cluster_info: None,
+            command_cleanups: self.command_cleanups.clone(),
         })
     }
 }
 
+/// Return the key of the cluster in the state shared between reconciles: <namespace>/<name>
+fn get_cluster_key(cluster: &SparkCluster) -> String {
+    format!(
+        "{}/{}",
+        cluster.namespace().unwrap_or_default(),
+        cluster.name()
+    )
+}
+
 async fn get_nodes_that_fit_selectors<T>(
     client: &Client,
     group: &NodeGroup<T>,
@@ -711,16 +1792,26 @@ pub async fn create_controller(client: Client) {
     let spark_api: Api<SparkCluster> = client.get_all_api();
     let pods_api: Api<Pod> = client.get_all_api();
     let config_maps_api: Api<ConfigMap> = client.get_all_api();
+    let service_accounts_api: Api<ServiceAccount> = client.get_all_api();
     let cmd_restart_api: Api<Restart> = client.get_all_api();
     let cmd_start_api: Api<Start> = client.get_all_api();
     let cmd_stop_api: Api<Stop> = client.get_all_api();
+    let cmd_scale_api: Api<Scale> = client.get_all_api();
+    let cmd_pause_api: Api<Pause> = client.get_all_api();
+    let cmd_resume_api: Api<Resume> = client.get_all_api();
+    let cmd_cancel_api: Api<Cancel> = client.get_all_api();
 
     let controller = Controller::new(spark_api)
         .owns(pods_api, ListParams::default())
         .owns(config_maps_api, ListParams::default())
+        .owns(service_accounts_api, ListParams::default())
         .owns(cmd_restart_api, ListParams::default())
         .owns(cmd_start_api, ListParams::default())
-        .owns(cmd_stop_api, ListParams::default());
+        .owns(cmd_stop_api, ListParams::default())
+        .owns(cmd_scale_api, ListParams::default())
+        .owns(cmd_pause_api, ListParams::default())
+        .owns(cmd_resume_api, ListParams::default())
+        .owns(cmd_cancel_api, ListParams::default());
 
     let strategy = SparkStrategy::new();
 
@@ -0,0 +1,548 @@
+//! This module contains all methods to query the JSON API of the Spark masters. The API is
+//! offered on the master web ui port under "/json" and reports the master state (ALIVE,
+//! STANDBY), registered workers, available resources and running applications.
+use crate::error::Error;
+use crate::pod_utils;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::Pod;
+use serde::Deserialize;
+use stackable_spark_crd::{ClusterInfo, MasterInfo};
+use std::time::Duration;
+
+/// State of a master that is currently leading the cluster
+pub const MASTER_STATE_ALIVE: &str = "ALIVE";
+/// State of a master that could not be reached
+pub const MASTER_STATE_UNREACHABLE: &str = "UNREACHABLE";
+/// State of a worker that is registered and sends heartbeats
+const WORKER_STATE_ALIVE: &str = "ALIVE";
+/// State of an application that waits for resources
+const APPLICATION_STATE_WAITING: &str = "WAITING";
+/// Default time a ready worker may take to register at the alive master
+pub const DEFAULT_WORKER_REGISTRATION_TIMEOUT: Duration = Duration::from_secs(300);
+/// Timeout for requests against the master JSON API
+const MASTER_API_TIMEOUT: Duration = Duration::from_secs(5);
+/// Minimum interval between two status updates that only change the usage of the cluster
+const CLUSTER_INFO_USAGE_UPDATE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The response of the master JSON API (only the fields the operator is interested in).
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MasterState {
+    pub status: String,
+    pub workers: Vec<WorkerState>,
+    pub aliveworkers: usize,
+    pub cores: usize,
+    pub coresused: usize,
+    pub memory: usize,
+    pub memoryused: usize,
+    pub activeapps: Vec<ApplicationState>,
+    pub completedapps: Vec<ApplicationState>,
+}
+
+/// A worker as reported by the master JSON API.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WorkerState {
+    pub host: String,
+    pub state: String,
+    pub coresused: usize,
+}
+
+/// An application as reported by the master JSON API.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ApplicationState {
+    pub id: String,
+    pub name: String,
+    pub state: String,
+}
+
+/// Build the http client used to query the master JSON API.
+pub fn build_http_client() -> Result<reqwest::Client, Error> {
+    Ok(reqwest::Client::builder()
+        .timeout(MASTER_API_TIMEOUT)
+        .build()?)
+}
+
+/// Request the state of a master via its JSON API.
+///
+/// # Arguments
+/// * `http_client` - Client to send the request with
+/// * `web_ui_url` - The master web ui url in format <node_name>:<port>
+///
+pub async fn get_master_state(
+    http_client: &reqwest::Client,
+    web_ui_url: &str,
+) -> Result<MasterState, Error> {
+    let url = format!("http://{}/json", web_ui_url);
+
+    let response = http_client
+        .get(&url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+
+    match response {
+        Ok(response) => {
+            response
+                .json::<MasterState>()
+                .await
+                .map_err(|source| Error::MasterApiError {
+                    url: url.clone(),
+                    source,
+                })
+        }
+        Err(source) => Err(Error::MasterApiError { url, source }),
+    }
+}
+
+/// Aggregate the states of all masters into the cluster info for the status. Resources,
+/// workers and applications are taken from the master in state ALIVE.
+///
+/// # Arguments
+/// * `master_states` - Web ui url and reported state (None if unreachable) of all masters
+///
+pub fn build_cluster_info(master_states: &[(String, Option<MasterState>)]) -> ClusterInfo {
+    let mut cluster_info = ClusterInfo::default();
+
+    for (web_ui_url, master_state) in master_states {
+        let state = match master_state {
+            Some(master_state) => master_state.status.clone(),
+            None => MASTER_STATE_UNREACHABLE.to_string(),
+        };
+
+        cluster_info.masters.push(MasterInfo {
+            address: web_ui_url.clone(),
+            state,
+        });
+    }
+
+    if let Some((web_ui_url, Some(alive_master))) = find_alive_master(master_states) {
+        cluster_info.alive_master = Some(web_ui_url.clone());
+        cluster_info.alive_workers = alive_master.aliveworkers;
+        cluster_info.cores = alive_master.cores;
+        cluster_info.cores_used = alive_master.coresused;
+        cluster_info.memory = alive_master.memory;
+        cluster_info.memory_used = alive_master.memoryused;
+        cluster_info.running_applications = alive_master
+            .activeapps
+            .iter()
+            .map(|app| app.name.clone())
+            .collect();
+        cluster_info.waiting_applications = alive_master
+            .activeapps
+            .iter()
+            .filter(|app| app.state == APPLICATION_STATE_WAITING)
+            .count();
+    }
+
+    cluster_info
+}
+
+/// Check if the cluster info in the status must be updated. Changes of the masters, workers
+/// and resources are written immediately. Changes that only affect the usage (used cores and
+/// memory, running and waiting applications) are written at most every
+/// `CLUSTER_INFO_USAGE_UPDATE_INTERVAL`: every status update triggers another reconcile, so a
+/// busy cluster would otherwise be reconciled continuously. Decisions based on the usage
+/// (e.g. autoscaling) use the polled cluster info instead of the status.
+///
+/// # Arguments
+/// * `current` - The cluster info in the status
+/// * `polled` - The cluster info derived from the polled masters
+/// * `now` - The current time
+///
+pub fn is_cluster_info_update_required(
+    current: Option<&ClusterInfo>,
+    polled: Option<&ClusterInfo>,
+    now: DateTime<Utc>,
+) -> bool {
+    let (current, polled) = match (current, polled) {
+        (None, None) => return false,
+        (Some(current), Some(polled)) => (current, polled),
+        _ => return true,
+    };
+
+    if without_usage(current) != without_usage(polled) {
+        return true;
+    }
+
+    let unchanged = ClusterInfo {
+        last_update_time: None,
+        ..current.clone()
+    } == ClusterInfo {
+        last_update_time: None,
+        ..polled.clone()
+    };
+    if unchanged {
+        return false;
+    }
+
+    current
+        .last_update_time
+        .as_deref()
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .and_then(|last_update| (now - last_update.with_timezone(&Utc)).to_std().ok())
+        .map(|elapsed| elapsed >= CLUSTER_INFO_USAGE_UPDATE_INTERVAL)
+        .unwrap_or(true)
+}
+
+/// Return the cluster info without the usage and the update time.
+fn without_usage(cluster_info: &ClusterInfo) -> ClusterInfo {
+    ClusterInfo {
+        cores_used: 0,
+        memory_used: 0,
+        running_applications: vec![],
+        waiting_applications: 0,
+        last_update_time: None,
+        ..cluster_info.clone()
+    }
+}
+
+/// Return the web ui url and state of the first master in state ALIVE.
+///
+/// # Arguments
+/// * `master_states` - Web ui url and reported state (None if unreachable) of all masters
+///
+pub fn find_alive_master(
+    master_states: &[(String, Option<MasterState>)],
+) -> Option<&(String, Option<MasterState>)> {
+    master_states.iter().find(|(_, master_state)| {
+        master_state
+            .as_ref()
+            .map(|state| state.status == MASTER_STATE_ALIVE)
+            .unwrap_or(false)
+    })
+}
+
+/// Find an application of the master. If the application id is known the application is
+/// matched by id, otherwise by name. Running applications are preferred over completed ones,
+/// for completed applications the most recent one is returned.
+///
+/// # Arguments
+/// * `master_state` - The state of the alive master
+/// * `app_name` - The name the application was submitted with
+/// * `app_id` - The application id if already known
+///
+pub fn find_application<'a>(
+    master_state: &'a MasterState,
+    app_name: &str,
+    app_id: Option<&str>,
+) -> Option<&'a ApplicationState> {
+    let matches = |app: &&ApplicationState| match app_id {
+        Some(app_id) => app.id == app_id,
+        None => app.name == app_name,
+    };
+
+    master_state
+        .activeapps
+        .iter()
+        .find(matches)
+        .or_else(|| master_state.completedapps.iter().rev().find(matches))
+}
+
+/// Find all worker pods that are running and ready for longer than the registration timeout,
+/// but are not registered as alive worker at the alive master. Workers are matched via the
+/// node name or the pod ip.
+///
+/// # Arguments
+/// * `worker_pods` - All existing worker pods
+/// * `alive_master` - The state of the alive master
+/// * `timeout` - Time a ready worker may take to register
+///
+pub fn find_unregistered_workers<'a>(
+    worker_pods: &'a [Pod],
+    alive_master: &MasterState,
+    timeout: Duration,
+) -> Vec<&'a Pod> {
+    let now = chrono::Utc::now();
+
+    worker_pods
+        .iter()
+        .filter(|pod| pod_utils::is_pod_running_and_ready(pod))
+        .filter(|pod| {
+            let ready_since = pod_utils::get_ready_since(pod);
+            match ready_since {
+                Some(ready_since) => (now - ready_since)
+                    .to_std()
+                    .map(|ready_for| ready_for >= timeout)
+                    .unwrap_or(false),
+                None => false,
+            }
+        })
+        .filter(|pod| !is_worker_registered(pod, alive_master))
+        .collect()
+}
+
+/// Check if the master has an alive worker on the node (or ip) of the given pod.
+///
+/// # Arguments
+/// * `pod` - The worker pod
+/// * `master_state` - The state of the master
+///
+fn is_worker_registered(pod: &Pod, master_state: &MasterState) -> bool {
+    find_alive_worker(pod, master_state).is_some()
+}
+
+/// Check if the worker of the given pod runs no executors (uses no cores). Workers that are
+/// not registered at the master are idle as well.
+///
+/// # Arguments
+/// * `pod` - The worker pod
+/// * `master_state` - The state of the alive master
+///
+pub fn is_worker_idle(pod: &Pod, master_state: &MasterState) -> bool {
+    find_alive_worker(pod, master_state)
+        .map(|worker| worker.coresused == 0)
+        .unwrap_or(true)
+}
+
+/// Find the alive worker of the master that runs on the node (or ip) of the given pod.
+///
+/// # Arguments
+/// * `pod` - The worker pod
+/// * `master_state` - The state of the master
+///
+fn find_alive_worker<'a>(pod: &Pod, master_state: &'a MasterState) -> Option<&'a WorkerState> {
+    let node_name = pod.spec.as_ref().and_then(|spec| spec.node_name.as_deref());
+    let pod_ip = pod
+        .status
+        .as_ref()
+        .and_then(|status| status.pod_ip.as_deref());
+
+    master_state.workers.iter().find(|worker| {
+        worker.state == WORKER_STATE_ALIVE
+            && (Some(worker.host.as_str()) == node_name || Some(worker.host.as_str()) == pod_ip)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_spark_test_utils::cluster::{Data, TestSparkCluster};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const MASTER_JSON: &str = r#"{
+        "url": "spark://master_node_1:7077",
+        "workers": [
+            {
+                "id": "worker-1",
+                "host": "worker_node_1",
+                "port": 3031,
+                "cores": 2,
+                "coresused": 1,
+                "memory": 1024,
+                "memoryused": 512,
+                "state": "ALIVE"
+            }
+        ],
+        "aliveworkers": 1,
+        "cores": 2,
+        "coresused": 1,
+        "memory": 1024,
+        "memoryused": 512,
+        "activeapps": [
+            { "id": "app-1", "name": "pi", "cores": 1, "state": "RUNNING" }
+        ],
+        "completedapps": [
+            { "id": "app-0", "name": "pi", "cores": 1, "state": "FAILED" },
+            { "id": "app-2", "name": "etl", "cores": 1, "state": "FINISHED" }
+        ],
+        "status": "ALIVE"
+    }"#;
+
+    /// Start a local http server that answers exactly one request with the given body.
+    async fn start_master_stub(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buffer = [0; 1024];
+            let read = socket.read(&mut buffer).await.unwrap();
+            assert!(read > 0);
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        address
+    }
+
+    #[tokio::test]
+    async fn test_get_master_state() {
+        let address = start_master_stub(MASTER_JSON).await;
+        let http_client = build_http_client().unwrap();
+
+        let master_state = get_master_state(&http_client, &address).await.unwrap();
+
+        assert_eq!(master_state.status, MASTER_STATE_ALIVE);
+        assert_eq!(master_state.aliveworkers, 1);
+        assert_eq!(master_state.workers.len(), 1);
+        assert_eq!(master_state.activeapps[0].name, "pi");
+    }
+
+    #[tokio::test]
+    async fn test_get_master_state_unreachable() {
+        // bind and drop a listener to get a free port nobody listens on
+        let address = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let http_client = build_http_client().unwrap();
+
+        assert!(get_master_state(&http_client, &address).await.is_err());
+    }
+
+    #[test]
+    fn test_build_cluster_info() {
+        let alive: MasterState = serde_json::from_str(MASTER_JSON).unwrap();
+        let standby = MasterState {
+            status: "STANDBY".to_string(),
+            ..MasterState::default()
+        };
+
+        let cluster_info = build_cluster_info(&[
+            ("master_node_2:8082".to_string(), Some(standby)),
+            ("master_node_3:8080".to_string(), None),
+            ("master_node_1:8081".to_string(), Some(alive)),
+        ]);
+
+        assert_eq!(
+            cluster_info.alive_master,
+            Some("master_node_1:8081".to_string())
+        );
+        assert_eq!(cluster_info.masters.len(), 3);
+        assert_eq!(cluster_info.masters[0].state, "STANDBY");
+        assert_eq!(cluster_info.masters[1].state, MASTER_STATE_UNREACHABLE);
+        assert_eq!(cluster_info.alive_workers, 1);
+        assert_eq!(cluster_info.cores_used, 1);
+        assert_eq!(cluster_info.running_applications, vec!["pi".to_string()]);
+        assert_eq!(cluster_info.waiting_applications, 0);
+    }
+
+    #[test]
+    fn test_is_cluster_info_update_required() {
+        let now = DateTime::parse_from_rfc3339("2021-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let current = ClusterInfo {
+            alive_workers: 2,
+            cores: 8,
+            cores_used: 4,
+            last_update_time: Some("2021-06-01T11:59:30Z".to_string()),
+            ..ClusterInfo::default()
+        };
+        let polled = ClusterInfo {
+            last_update_time: None,
+            ..current.clone()
+        };
+
+        assert!(!is_cluster_info_update_required(None, None, now));
+        assert!(is_cluster_info_update_required(None, Some(&polled), now));
+        assert!(is_cluster_info_update_required(Some(&current), None, now));
+        // only the update time differs
+        assert!(!is_cluster_info_update_required(
+            Some(&current),
+            Some(&polled),
+            now
+        ));
+
+        // worker changes are written immediately
+        let workers_changed = ClusterInfo {
+            alive_workers: 3,
+            ..polled.clone()
+        };
+        assert!(is_cluster_info_update_required(
+            Some(&current),
+            Some(&workers_changed),
+            now
+        ));
+
+        // usage changes are written after the interval
+        let usage_changed = ClusterInfo {
+            cores_used: 8,
+            waiting_applications: 1,
+            ..polled
+        };
+        assert!(!is_cluster_info_update_required(
+            Some(&current),
+            Some(&usage_changed),
+            now
+        ));
+        assert!(is_cluster_info_update_required(
+            Some(&current),
+            Some(&usage_changed),
+            now + chrono::Duration::seconds(30)
+        ));
+    }
+
+    #[test]
+    fn test_find_application() {
+        let alive: MasterState = serde_json::from_str(MASTER_JSON).unwrap();
+
+        // running applications are preferred
+        assert_eq!(find_application(&alive, "pi", None).unwrap().id, "app-1");
+        assert_eq!(
+            find_application(&alive, "pi", Some("app-0")).unwrap().state,
+            "FAILED"
+        );
+        assert_eq!(
+            find_application(&alive, "etl", None).unwrap().state,
+            "FINISHED"
+        );
+        assert!(find_application(&alive, "unknown", None).is_none());
+    }
+
+    #[test]
+    fn test_find_unregistered_workers() {
+        let alive: MasterState = serde_json::from_str(MASTER_JSON).unwrap();
+        let mut spark_cluster: stackable_spark_crd::SparkCluster =
+            stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+
+        let master_urls = stackable_spark_test_utils::create_master_urls();
+        let mut worker_pods = vec![
+            pod_utils::build_pod(
+                &spark_cluster,
+                TestSparkCluster::WORKER_1_NODE_NAME,
+                TestSparkCluster::WORKER_1_ROLE_GROUP,
+                &stackable_spark_crd::SparkNodeType::Worker,
+                &master_urls,
+            )
+            .unwrap(),
+            pod_utils::build_pod(
+                &spark_cluster,
+                TestSparkCluster::WORKER_2_NODE_NAME,
+                TestSparkCluster::WORKER_2_ROLE_GROUP,
+                &stackable_spark_crd::SparkNodeType::Worker,
+                &master_urls,
+            )
+            .unwrap(),
+        ];
+        for pod in worker_pods.iter_mut() {
+            stackable_spark_test_utils::set_pod_running_and_ready(pod);
+        }
+
+        // ready since "now" -> still within the timeout
+        assert!(
+            find_unregistered_workers(&worker_pods, &alive, Duration::from_secs(60)).is_empty()
+        );
+
+        let unregistered = find_unregistered_workers(&worker_pods, &alive, Duration::from_secs(0));
+        assert_eq!(unregistered.len(), 1);
+        assert_eq!(
+            unregistered[0].spec.as_ref().unwrap().node_name,
+            Some(TestSparkCluster::WORKER_2_NODE_NAME.to_string())
+        );
+
+        // worker 1 runs an executor, worker 2 is not registered at all
+        assert!(!is_worker_idle(&worker_pods[0], &alive));
+        assert!(is_worker_idle(&worker_pods[1], &alive));
+    }
+}
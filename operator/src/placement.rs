@@ -0,0 +1,565 @@
+//! This module contains all methods required to place pods via the Kubernetes scheduler
+//! (Scheduler placement) instead of pinning them to nodes via `nodeName` (Node placement).
+use crate::pod_utils;
+use crate::pod_utils::{APP_NAME, POD_INDEX_LABEL};
+use k8s_openapi::api::core::v1::{
+    Affinity, Node, NodeAffinity, NodeSelector, NodeSelectorRequirement, NodeSelectorTerm, Pod,
+    PodAffinityTerm, PodAntiAffinity, WeightedPodAffinityTerm,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use stackable_operator::labels;
+use stackable_spark_crd::{Placement, SparkClusterSpec, SparkNodeType};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Topology key to spread masters over nodes
+const HOSTNAME_TOPOLOGY_KEY: &str = "kubernetes.io/hostname";
+/// Weight of the preferred master anti affinity
+const MASTER_ANTI_AFFINITY_WEIGHT: i32 = 100;
+
+/// Build the affinity for a scheduled pod. The node affinity is derived from the selector
+/// of the role group. Masters additionally prefer nodes without other masters of the cluster.
+///
+/// # Arguments
+/// * `selector` - The selector of the role group
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+/// * `cluster_name` - The name of the cluster as specified in the custom resource
+///
+pub fn build_affinity(
+    selector: Option<&LabelSelector>,
+    node_type: &SparkNodeType,
+    cluster_name: &str,
+) -> Option<Affinity> {
+    let node_affinity = selector.and_then(build_node_affinity);
+    let pod_anti_affinity = if node_type == &SparkNodeType::Master {
+        Some(build_master_anti_affinity(cluster_name))
+    } else {
+        None
+    };
+
+    if node_affinity.is_none() && pod_anti_affinity.is_none() {
+        return None;
+    }
+
+    Some(Affinity {
+        node_affinity,
+        pod_anti_affinity,
+        ..Affinity::default()
+    })
+}
+
+/// Convert a label selector into a required node affinity. Match labels are converted into
+/// "In" requirements, match expressions are taken over as is (the operators In, NotIn, Exists
+/// and DoesNotExist are valid for both). Returns None for an empty selector.
+///
+/// # Arguments
+/// * `selector` - The selector of the role group
+///
+fn build_node_affinity(selector: &LabelSelector) -> Option<NodeAffinity> {
+    let mut match_expressions = vec![];
+
+    if let Some(match_labels) = &selector.match_labels {
+        for (key, value) in match_labels {
+            match_expressions.push(NodeSelectorRequirement {
+                key: key.clone(),
+                operator: "In".to_string(),
+                values: Some(vec![value.clone()]),
+            });
+        }
+    }
+
+    if let Some(expressions) = &selector.match_expressions {
+        for expression in expressions {
+            match_expressions.push(NodeSelectorRequirement {
+                key: expression.key.clone(),
+                operator: expression.operator.clone(),
+                values: expression.values.clone(),
+            });
+        }
+    }
+
+    if match_expressions.is_empty() {
+        return None;
+    }
+
+    Some(NodeAffinity {
+        required_during_scheduling_ignored_during_execution: Some(NodeSelector {
+            node_selector_terms: vec![NodeSelectorTerm {
+                match_expressions: Some(match_expressions),
+                match_fields: None,
+            }],
+        }),
+        ..NodeAffinity::default()
+    })
+}
+
+/// Prefer nodes that do not run a master of the same cluster yet. This is only preferred
+/// (not required) to allow multiple masters on small clusters.
+///
+/// # Arguments
+/// * `cluster_name` - The name of the cluster as specified in the custom resource
+///
+fn build_master_anti_affinity(cluster_name: &str) -> PodAntiAffinity {
+    let mut match_labels = BTreeMap::new();
+    match_labels.insert(labels::APP_NAME_LABEL.to_string(), APP_NAME.to_string());
+    match_labels.insert(
+        labels::APP_INSTANCE_LABEL.to_string(),
+        cluster_name.to_string(),
+    );
+    match_labels.insert(
+        labels::APP_COMPONENT_LABEL.to_string(),
+        SparkNodeType::Master.to_string(),
+    );
+
+    PodAntiAffinity {
+        preferred_during_scheduling_ignored_during_execution: Some(vec![WeightedPodAffinityTerm {
+            pod_affinity_term: PodAffinityTerm {
+                label_selector: Some(LabelSelector {
+                    match_labels: Some(match_labels),
+                    ..LabelSelector::default()
+                }),
+                namespaces: None,
+                topology_key: HOSTNAME_TOPOLOGY_KEY.to_string(),
+            },
+            weight: MASTER_ANTI_AFFINITY_WEIGHT,
+        }]),
+        ..PodAntiAffinity::default()
+    }
+}
+
+/// Return the desired number of pods per role group: one pod per eligible node for Node
+/// placement and the number of instances for Scheduler placement.
+/// Returns None if the role is not specified in the cluster.
+///
+/// # Arguments
+/// * `spec` - The spark cluster spec
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+/// * `eligible_nodes` - Eligible nodes of the role mapped by role group
+///
+pub fn get_desired_pods(
+    spec: &SparkClusterSpec,
+    node_type: &SparkNodeType,
+    eligible_nodes: Option<&HashMap<String, Vec<Node>>>,
+) -> Option<HashMap<String, usize>> {
+    let eligible_nodes = eligible_nodes?;
+    let placement = spec.get_placement();
+
+    Some(
+        eligible_nodes
+            .iter()
+            .map(|(role_group, nodes)| {
+                let desired = match placement {
+                    Placement::Node => nodes.len(),
+                    Placement::Scheduler => spec
+                        .get_instances(node_type, role_group)
+                        .map(usize::from)
+                        .unwrap_or_default(),
+                };
+                (role_group.clone(), desired)
+            })
+            .collect(),
+    )
+}
+
+/// Limit the eligible nodes of every role group to the instances of the role group for Node
+/// placement (only if `limitNodePlacement` is set). Nodes that already run a pod of the role
+/// group are kept first to avoid moving pods, the remaining nodes are ordered by name.
+///
+/// # Arguments
+/// * `eligible_nodes` - Eligible nodes of the role mapped by role group
+/// * `spec` - The spark cluster spec
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+/// * `pods` - Slice of all existing pods
+///
+pub fn limit_eligible_nodes(
+    eligible_nodes: HashMap<String, Vec<Node>>,
+    spec: &SparkClusterSpec,
+    node_type: &SparkNodeType,
+    pods: &[Pod],
+) -> HashMap<String, Vec<Node>> {
+    eligible_nodes
+        .into_iter()
+        .map(|(role_group, mut nodes)| {
+            let used_nodes = pod_utils::filter_pods_for_role_group(pods, node_type, &role_group)
+                .into_iter()
+                .filter_map(|pod| pod.spec.and_then(|spec| spec.node_name))
+                .collect::<BTreeSet<_>>();
+
+            nodes.sort_by_key(|node| {
+                let name = node.metadata.name.clone().unwrap_or_default();
+                (!used_nodes.contains(&name), name)
+            });
+            if let Some(instances) = spec.get_instances(node_type, &role_group) {
+                nodes.truncate(usize::from(instances));
+            }
+
+            (role_group, nodes)
+        })
+        .collect()
+}
+
+/// Return the pod index (POD_INDEX_LABEL) of a scheduled pod.
+///
+/// # Arguments
+/// * `pod` - The scheduled pod
+///
+pub fn get_pod_index(pod: &Pod) -> Option<usize> {
+    pod.metadata
+        .labels
+        .as_ref()
+        .and_then(|labels| labels.get(POD_INDEX_LABEL))
+        .and_then(|index| index.parse().ok())
+}
+
+/// Return the pod at every position (0..instances) of a role group or None if it is missing.
+/// The position is the pod index for Scheduler placement and the position of the pod's node
+/// in the (limited) eligible nodes for Node placement. If the instances are decreased, the
+/// pods at the highest positions are removed.
+///
+/// # Arguments
+/// * `placement` - The placement of the cluster
+/// * `group_pods` - All existing pods of the role group
+/// * `eligible_nodes` - The eligible nodes of the role group
+/// * `instances` - The current number of instances of the role group
+///
+pub fn get_pods_by_position(
+    placement: &Placement,
+    group_pods: &[Pod],
+    eligible_nodes: &[Node],
+    instances: usize,
+) -> Vec<Option<Pod>> {
+    (0..instances)
+        .map(|position| {
+            group_pods
+                .iter()
+                .find(|pod| match placement {
+                    Placement::Scheduler => get_pod_index(pod) == Some(position),
+                    Placement::Node => {
+                        let node_name = pod.spec.as_ref().and_then(|spec| spec.node_name.as_ref());
+                        eligible_nodes
+                            .get(position)
+                            .map(|node| node.metadata.name.as_ref() == node_name)
+                            .unwrap_or(false)
+                    }
+                })
+                .cloned()
+        })
+        .collect()
+}
+
+/// Return all pod indices within 0..instances that are not used by an existing pod of
+/// the role group.
+///
+/// # Arguments
+/// * `group_pods` - All existing pods of the role group
+/// * `instances` - The desired number of pods of the role group
+///
+pub fn find_missing_pod_indices(group_pods: &[Pod], instances: usize) -> Vec<usize> {
+    let existing_indices = group_pods
+        .iter()
+        .filter_map(get_pod_index)
+        .collect::<BTreeSet<_>>();
+
+    (0..instances)
+        .filter(|index| !existing_indices.contains(index))
+        .collect()
+}
+
+/// Return all scheduled pods of a role that are not required anymore: their role group was
+/// removed or their pod index exceeds the instances of the role group. Pods without a pod index
+/// of existing role groups were placed via `nodeName` and are replaced by
+/// `find_node_placed_pods_to_replace` instead.
+///
+/// # Arguments
+/// * `pods` - Slice of all existing pods
+/// * `spec` - The spark cluster spec
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+///
+pub fn find_excess_scheduled_pods(
+    pods: &[Pod],
+    spec: &SparkClusterSpec,
+    node_type: &SparkNodeType,
+) -> Vec<Pod> {
+    pod_utils::filter_pods_for_type(pods, node_type)
+        .into_iter()
+        .filter(|pod| {
+            let instances = pod
+                .metadata
+                .labels
+                .as_ref()
+                .and_then(|labels| labels.get(labels::APP_ROLE_GROUP_LABEL))
+                .and_then(|role_group| spec.get_instances(node_type, role_group));
+
+            match (instances, get_pod_index(pod)) {
+                (Some(instances), Some(index)) => index >= usize::from(instances),
+                (Some(_), None) => false,
+                (None, _) => true,
+            }
+        })
+        .collect()
+}
+
+/// Return the pods placed via `nodeName` (Node placement) of the first role group that still
+/// has such pods after switching to Scheduler placement. Like a rolling restart the role groups
+/// are replaced one after the other: the next role group is only returned once all scheduled
+/// pods of the previous role groups exist. Whether they are ready is checked before by
+/// `wait_for_running_and_ready_pods`.
+///
+/// # Arguments
+/// * `pods` - Slice of all existing pods
+/// * `spec` - The spark cluster spec
+/// * `role_groups` - The role groups of all roles in replacement order
+///
+pub fn find_node_placed_pods_to_replace(
+    pods: &[Pod],
+    spec: &SparkClusterSpec,
+    role_groups: &[(SparkNodeType, String)],
+) -> Vec<Pod> {
+    for (node_type, role_group) in role_groups {
+        let group_pods = pod_utils::filter_pods_for_role_group(pods, node_type, role_group);
+
+        let node_placed_pods = group_pods
+            .iter()
+            .filter(|pod| get_pod_index(pod).is_none())
+            .cloned()
+            .collect::<Vec<_>>();
+        if !node_placed_pods.is_empty() {
+            return node_placed_pods;
+        }
+
+        let instances = spec
+            .get_instances(node_type, role_group)
+            .map(usize::from)
+            .unwrap_or_default();
+        if !find_missing_pod_indices(&group_pods, instances).is_empty() {
+            return vec![];
+        }
+    }
+
+    vec![]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelectorRequirement, ObjectMeta};
+    use stackable_spark_crd::SparkCluster;
+    use stackable_spark_test_utils::cluster::{Data, TestSparkCluster};
+
+    #[test]
+    fn test_build_affinity() {
+        let mut match_labels = BTreeMap::new();
+        match_labels.insert("disktype".to_string(), "ssd".to_string());
+        let selector = LabelSelector {
+            match_labels: Some(match_labels),
+            match_expressions: Some(vec![LabelSelectorRequirement {
+                key: "zone".to_string(),
+                operator: "NotIn".to_string(),
+                values: Some(vec!["a".to_string()]),
+            }]),
+        };
+
+        let affinity =
+            build_affinity(Some(&selector), &SparkNodeType::Master, "spark-cluster").unwrap();
+
+        let requirements = affinity
+            .node_affinity
+            .unwrap()
+            .required_during_scheduling_ignored_during_execution
+            .unwrap()
+            .node_selector_terms[0]
+            .match_expressions
+            .clone()
+            .unwrap();
+        assert_eq!(requirements.len(), 2);
+        assert_eq!(requirements[0].key, "disktype");
+        assert_eq!(requirements[0].operator, "In");
+        assert_eq!(requirements[0].values, Some(vec!["ssd".to_string()]));
+        assert_eq!(requirements[1].operator, "NotIn");
+
+        let anti_affinity_terms = affinity
+            .pod_anti_affinity
+            .unwrap()
+            .preferred_during_scheduling_ignored_during_execution
+            .unwrap();
+        assert_eq!(
+            anti_affinity_terms[0].pod_affinity_term.topology_key,
+            HOSTNAME_TOPOLOGY_KEY
+        );
+
+        // workers without selector are not restricted at all
+        assert_eq!(
+            build_affinity(None, &SparkNodeType::Worker, "spark-cluster"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_limit_eligible_nodes() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+
+        let build_node = |name: &str| Node {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                ..ObjectMeta::default()
+            },
+            ..Node::default()
+        };
+        let mut eligible_nodes = HashMap::new();
+        eligible_nodes.insert(
+            TestSparkCluster::WORKER_1_ROLE_GROUP.to_string(),
+            vec![
+                build_node("node_a"),
+                build_node("node_b"),
+                build_node("node_c"),
+            ],
+        );
+
+        let master_urls = stackable_spark_test_utils::create_master_urls();
+        let pod = pod_utils::build_pod(
+            &spark_cluster,
+            "node_b",
+            TestSparkCluster::WORKER_1_ROLE_GROUP,
+            &SparkNodeType::Worker,
+            &master_urls,
+        )
+        .unwrap();
+
+        // the role group has one instance -> the node that already runs a pod is kept
+        let limited = limit_eligible_nodes(
+            eligible_nodes.clone(),
+            &spark_cluster.spec,
+            &SparkNodeType::Worker,
+            &[pod],
+        );
+        let nodes = &limited[TestSparkCluster::WORKER_1_ROLE_GROUP];
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].metadata.name, Some("node_b".to_string()));
+
+        // the replicas of the workers are distributed over both role groups
+        spark_cluster.spec.workers.replicas = Some(4);
+        let limited = limit_eligible_nodes(
+            eligible_nodes,
+            &spark_cluster.spec,
+            &SparkNodeType::Worker,
+            &[],
+        );
+        let nodes = &limited[TestSparkCluster::WORKER_1_ROLE_GROUP];
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].metadata.name, Some("node_a".to_string()));
+    }
+
+    #[test]
+    fn test_find_missing_and_excess_pods() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+        spark_cluster.spec.placement = Some(Placement::Scheduler);
+
+        let master_urls = stackable_spark_test_utils::create_master_urls();
+        let pods = (0..3)
+            .map(|index| {
+                pod_utils::build_scheduled_pod(
+                    &spark_cluster,
+                    index,
+                    TestSparkCluster::WORKER_1_ROLE_GROUP,
+                    &SparkNodeType::Worker,
+                    &master_urls,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(find_missing_pod_indices(&pods[1..], 2), vec![0]);
+
+        let pods_by_position = get_pods_by_position(&Placement::Scheduler, &pods[1..], &[], 4);
+        assert!(pods_by_position[0].is_none());
+        assert_eq!(
+            get_pod_index(pods_by_position[2].as_ref().unwrap()),
+            Some(2)
+        );
+        assert!(pods_by_position[3].is_none());
+        assert!(find_missing_pod_indices(&pods, 3).is_empty());
+
+        // the role group has one instance -> pod 1 and 2 are excess
+        let excess = find_excess_scheduled_pods(&pods, &spark_cluster.spec, &SparkNodeType::Worker);
+        assert_eq!(excess.len(), 2);
+        assert_eq!(get_pod_index(&excess[0]), Some(1));
+        assert_eq!(get_pod_index(&excess[1]), Some(2));
+
+        // pods of removed role groups are excess
+        spark_cluster
+            .spec
+            .workers
+            .selectors
+            .remove(TestSparkCluster::WORKER_1_ROLE_GROUP);
+        let excess = find_excess_scheduled_pods(&pods, &spark_cluster.spec, &SparkNodeType::Worker);
+        assert_eq!(excess.len(), 3);
+    }
+
+    #[test]
+    fn test_find_node_placed_pods_to_replace() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+
+        let master_urls = stackable_spark_test_utils::create_master_urls();
+        let role_groups = vec![
+            (
+                SparkNodeType::Master,
+                TestSparkCluster::MASTER_1_ROLE_GROUP.to_string(),
+            ),
+            (
+                SparkNodeType::Worker,
+                TestSparkCluster::WORKER_1_ROLE_GROUP.to_string(),
+            ),
+        ];
+        let node_placed_master = pod_utils::build_pod(
+            &spark_cluster,
+            TestSparkCluster::MASTER_1_NODE_NAME,
+            TestSparkCluster::MASTER_1_ROLE_GROUP,
+            &SparkNodeType::Master,
+            &master_urls,
+        )
+        .unwrap();
+        let node_placed_worker = pod_utils::build_pod(
+            &spark_cluster,
+            TestSparkCluster::WORKER_1_NODE_NAME,
+            TestSparkCluster::WORKER_1_ROLE_GROUP,
+            &SparkNodeType::Worker,
+            &master_urls,
+        )
+        .unwrap();
+
+        spark_cluster.spec.placement = Some(Placement::Scheduler);
+        let scheduled_master = pod_utils::build_scheduled_pod(
+            &spark_cluster,
+            0,
+            TestSparkCluster::MASTER_1_ROLE_GROUP,
+            &SparkNodeType::Master,
+            &master_urls,
+        )
+        .unwrap();
+
+        // node placed pods of existing role groups are not excess
+        let pods = vec![node_placed_master.clone(), node_placed_worker.clone()];
+        assert!(
+            find_excess_scheduled_pods(&pods, &spark_cluster.spec, &SparkNodeType::Master)
+                .is_empty()
+        );
+
+        // the masters are replaced first
+        let replaced = find_node_placed_pods_to_replace(&pods, &spark_cluster.spec, &role_groups);
+        assert_eq!(replaced.len(), 1);
+        assert_eq!(replaced[0].metadata.name, node_placed_master.metadata.name);
+
+        // the workers wait until the scheduled master was created
+        let pods = vec![node_placed_worker.clone()];
+        assert!(
+            find_node_placed_pods_to_replace(&pods, &spark_cluster.spec, &role_groups).is_empty()
+        );
+
+        let pods = vec![scheduled_master, node_placed_worker.clone()];
+        let replaced = find_node_placed_pods_to_replace(&pods, &spark_cluster.spec, &role_groups);
+        assert_eq!(replaced.len(), 1);
+        assert_eq!(replaced[0].metadata.name, node_placed_worker.metadata.name);
+    }
+}
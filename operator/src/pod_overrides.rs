@@ -0,0 +1,374 @@
+//! This module applies the user defined pod overrides (cluster, role and role group level) to
+//! the pods built by the operator. The overrides are merged with strategic merge semantics:
+//! objects are merged recursively, `null` removes a field, lists of named items (containers,
+//! volumes, env, ...) are merged by their merge key and items with `$patch: delete` are
+//! removed. All other lists are replaced.
+//!
+//! Fields the operator relies on are restored after merging: the pod name, namespace, owner
+//! references and operator labels, the node name, the Spark container with its command,
+//! required startup env and volume mounts, and the volumes created by the operator.
+use crate::error::Error;
+use k8s_openapi::api::core::v1::{Container, Pod};
+use serde_json::{Map, Value};
+
+/// Directive key of strategic merge patches
+const PATCH_DIRECTIVE: &str = "$patch";
+
+/// Returns the merge key of the list items of a pod field, or None if the list is replaced.
+///
+/// # Arguments
+/// * `field` - The name of the list field
+///
+fn get_merge_key(field: &str) -> Option<&'static str> {
+    match field {
+        "containers"
+        | "initContainers"
+        | "ephemeralContainers"
+        | "volumes"
+        | "env"
+        | "imagePullSecrets"
+        | "readinessGates" => Some("name"),
+        "volumeMounts" | "volumeDevices" => Some("mountPath"),
+        "ports" => Some("containerPort"),
+        "hostAliases" => Some("ip"),
+        _ => None,
+    }
+}
+
+/// Merge the pod overrides in the given order onto the pod and restore the fields managed by
+/// the operator afterwards.
+///
+/// # Arguments
+/// * `pod` - The pod built by the operator
+/// * `pod_overrides` - The pod overrides of the cluster, role and role group
+/// * `container_name` - The name of the container managed by the operator
+///
+pub fn apply_pod_overrides(
+    pod: Pod,
+    pod_overrides: &[Value],
+    container_name: &str,
+) -> Result<Pod, Error> {
+    if pod_overrides.is_empty() {
+        return Ok(pod);
+    }
+
+    let mut merged = serde_json::to_value(&pod)?;
+    for pod_override in pod_overrides {
+        strategic_merge(&mut merged, pod_override, None);
+    }
+
+    let merged: Pod = serde_json::from_value(merged)?;
+    Ok(restore_managed_fields(&pod, merged, container_name))
+}
+
+/// Merge a strategic merge patch into the target value.
+///
+/// # Arguments
+/// * `target` - The value to merge into
+/// * `patch` - The patch to apply
+/// * `field` - The name of the field the values belong to (used for list merge keys)
+///
+pub fn strategic_merge(target: &mut Value, patch: &Value, field: Option<&str>) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            if patch.get(PATCH_DIRECTIVE) == Some(&Value::String("replace".to_string())) {
+                *target = strip_directives(patch);
+                return;
+            }
+
+            for (key, value) in patch {
+                if key == PATCH_DIRECTIVE {
+                    continue;
+                }
+
+                if value.is_null() {
+                    target.remove(key);
+                } else if let Some(existing) = target.get_mut(key) {
+                    strategic_merge(existing, value, Some(key));
+                } else {
+                    target.insert(key.clone(), strip_directives_value(value));
+                }
+            }
+        }
+        (Value::Array(target), Value::Array(patch)) => match field.and_then(get_merge_key) {
+            Some(merge_key) => merge_list(target, patch, merge_key),
+            None => *target = patch.iter().map(strip_directives_value).collect(),
+        },
+        (target, patch) => *target = strip_directives_value(patch),
+    }
+}
+
+/// Merge list items by their merge key. Items of the patch with `$patch: delete` remove the
+/// item with the same key, new items are appended.
+fn merge_list(target: &mut Vec<Value>, patch: &[Value], merge_key: &str) {
+    for item in patch {
+        let key = item.get(merge_key);
+        let position = key.and_then(|key| {
+            target
+                .iter()
+                .position(|existing| existing.get(merge_key) == Some(key))
+        });
+
+        let delete = item.get(PATCH_DIRECTIVE) == Some(&Value::String("delete".to_string()));
+
+        match (position, delete) {
+            (Some(position), true) => {
+                target.remove(position);
+            }
+            (None, true) => {}
+            (Some(position), false) => strategic_merge(&mut target[position], item, None),
+            (None, false) => target.push(strip_directives_value(item)),
+        }
+    }
+}
+
+/// Remove all patch directives from an object (recursively).
+fn strip_directives(object: &Map<String, Value>) -> Map<String, Value> {
+    object
+        .iter()
+        .filter(|(key, _)| *key != PATCH_DIRECTIVE)
+        .map(|(key, value)| (key.clone(), strip_directives_value(value)))
+        .collect()
+}
+
+/// Remove all patch directives from a value (recursively).
+fn strip_directives_value(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(strip_directives(object)),
+        Value::Array(items) => Value::Array(items.iter().map(strip_directives_value).collect()),
+        value => value.clone(),
+    }
+}
+
+/// Restore the fields of the merged pod that are managed by the operator.
+///
+/// # Arguments
+/// * `pod` - The pod built by the operator
+/// * `merged` - The pod with all overrides applied
+/// * `container_name` - The name of the container managed by the operator
+///
+fn restore_managed_fields(pod: &Pod, mut merged: Pod, container_name: &str) -> Pod {
+    merged.metadata.name = pod.metadata.name.clone();
+    merged.metadata.namespace = pod.metadata.namespace.clone();
+    merged.metadata.owner_references = pod.metadata.owner_references.clone();
+    if let Some(labels) = &pod.metadata.labels {
+        merged
+            .metadata
+            .labels
+            .get_or_insert_with(Default::default)
+            .extend(labels.clone());
+    }
+
+    let spec = match &pod.spec {
+        Some(spec) => spec,
+        None => return merged,
+    };
+    let merged_spec = merged.spec.get_or_insert_with(Default::default);
+
+    merged_spec.node_name = spec.node_name.clone();
+
+    if let Some(container) = spec
+        .containers
+        .iter()
+        .find(|container| container.name == container_name)
+    {
+        match merged_spec
+            .containers
+            .iter_mut()
+            .find(|merged_container| merged_container.name == container_name)
+        {
+            Some(merged_container) => restore_container(container, merged_container),
+            None => merged_spec.containers.insert(0, container.clone()),
+        }
+    }
+
+    for volume in spec.volumes.iter().flatten() {
+        let merged_volumes = merged_spec.volumes.get_or_insert_with(Vec::new);
+        merged_volumes.retain(|merged_volume| merged_volume.name != volume.name);
+        merged_volumes.push(volume.clone());
+    }
+
+    merged
+}
+
+/// Restore the command, the required env vars and the volume mounts of the container.
+fn restore_container(container: &Container, merged_container: &mut Container) {
+    merged_container.command = container.command.clone();
+
+    for env_var in container.env.iter().flatten() {
+        let merged_env = merged_container.env.get_or_insert_with(Vec::new);
+        merged_env.retain(|merged_env_var| merged_env_var.name != env_var.name);
+        merged_env.push(env_var.clone());
+    }
+
+    for volume_mount in container.volume_mounts.iter().flatten() {
+        let merged_mounts = merged_container.volume_mounts.get_or_insert_with(Vec::new);
+        merged_mounts.retain(|merged_mount| {
+            merged_mount.name != volume_mount.name
+                && merged_mount.mount_path != volume_mount.mount_path
+        });
+        merged_mounts.push(volume_mount.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::core::v1::{EnvVar, PodSpec, Volume, VolumeMount};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn build_pod() -> Pod {
+        let mut labels = BTreeMap::new();
+        labels.insert("app.kubernetes.io/name".to_string(), "spark".to_string());
+
+        Pod {
+            metadata: ObjectMeta {
+                name: Some("spark-pod".to_string()),
+                labels: Some(labels),
+                ..ObjectMeta::default()
+            },
+            spec: Some(PodSpec {
+                node_name: Some("node_1".to_string()),
+                containers: vec![Container {
+                    name: "spark".to_string(),
+                    command: Some(vec!["start-master.sh".to_string()]),
+                    env: Some(vec![EnvVar {
+                        name: "SPARK_CONF_DIR".to_string(),
+                        value: Some("/stackable/config".to_string()),
+                        ..EnvVar::default()
+                    }]),
+                    volume_mounts: Some(vec![VolumeMount {
+                        name: "config-volume".to_string(),
+                        mount_path: "/stackable/config".to_string(),
+                        ..VolumeMount::default()
+                    }]),
+                    ..Container::default()
+                }],
+                volumes: Some(vec![Volume {
+                    name: "config-volume".to_string(),
+                    ..Volume::default()
+                }]),
+                ..PodSpec::default()
+            }),
+            ..Pod::default()
+        }
+    }
+
+    #[test]
+    fn test_strategic_merge() {
+        let mut target = json!({
+            "metadata": { "labels": { "a": "1", "b": "2" } },
+            "spec": {
+                "containers": [
+                    { "name": "spark", "env": [{ "name": "A", "value": "1" }] },
+                    { "name": "sidecar", "image": "sidecar:1" }
+                ],
+                "tolerations": [{ "key": "a" }]
+            }
+        });
+
+        strategic_merge(
+            &mut target,
+            &json!({
+                "metadata": { "labels": { "b": null, "c": "3" } },
+                "spec": {
+                    "containers": [
+                        { "name": "spark", "env": [{ "name": "B", "value": "2" }] },
+                        { "name": "sidecar", "$patch": "delete" },
+                        { "name": "logger", "image": "logger:1" }
+                    ],
+                    "tolerations": [{ "key": "b" }]
+                }
+            }),
+            None,
+        );
+
+        assert_eq!(
+            target,
+            json!({
+                "metadata": { "labels": { "a": "1", "c": "3" } },
+                "spec": {
+                    "containers": [
+                        {
+                            "name": "spark",
+                            "env": [{ "name": "A", "value": "1" }, { "name": "B", "value": "2" }]
+                        },
+                        { "name": "logger", "image": "logger:1" }
+                    ],
+                    "tolerations": [{ "key": "b" }]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_apply_pod_overrides() {
+        let pod = build_pod();
+
+        let cluster_override = json!({
+            "metadata": { "annotations": { "team": "data" } },
+            "spec": { "priorityClassName": "high" }
+        });
+        let role_group_override = json!({
+            "metadata": { "name": "renamed", "labels": { "app.kubernetes.io/name": "other" } },
+            "spec": {
+                "nodeName": "node_2",
+                "containers": [{
+                    "name": "spark",
+                    "command": ["sleep"],
+                    "env": [
+                        { "name": "SPARK_CONF_DIR", "value": "/tmp" },
+                        { "name": "EXTRA", "value": "1" }
+                    ],
+                    "volumeMounts": [{ "name": "config-volume", "$patch": "delete" }]
+                }],
+                "volumes": [{ "name": "config-volume", "$patch": "delete" }]
+            }
+        });
+
+        let merged = apply_pod_overrides(
+            pod.clone(),
+            &[cluster_override, role_group_override],
+            "spark",
+        )
+        .unwrap();
+
+        // overrides are applied
+        assert_eq!(
+            merged
+                .metadata
+                .annotations
+                .as_ref()
+                .and_then(|annotations| annotations.get("team")),
+            Some(&"data".to_string())
+        );
+        let spec = merged.spec.unwrap();
+        assert_eq!(spec.priority_class_name, Some("high".to_string()));
+        let container = &spec.containers[0];
+        assert!(container
+            .env
+            .as_ref()
+            .unwrap()
+            .iter()
+            .any(|env_var| env_var.name == "EXTRA"));
+
+        // managed fields are restored
+        let original_spec = pod.spec.unwrap();
+        assert_eq!(merged.metadata.name, pod.metadata.name);
+        assert_eq!(merged.metadata.labels, pod.metadata.labels);
+        assert_eq!(spec.node_name, original_spec.node_name);
+        assert_eq!(container.command, original_spec.containers[0].command);
+        assert!(container.env.as_ref().unwrap().contains(&EnvVar {
+            name: "SPARK_CONF_DIR".to_string(),
+            value: Some("/stackable/config".to_string()),
+            ..EnvVar::default()
+        }));
+        assert_eq!(
+            container.volume_mounts,
+            original_spec.containers[0].volume_mounts
+        );
+        assert_eq!(spec.volumes, original_spec.volumes);
+    }
+}
@@ -2,28 +2,58 @@
 use crate::config;
 use crate::config::create_config_map_name;
 use crate::error::Error;
+use crate::placement;
+use crate::pod_overrides;
+use crate::rbac;
+use chrono::{DateTime, Utc};
 use k8s_openapi::api::core::v1::{
-    ConfigMapVolumeSource, Container, Pod, PodSpec, Volume, VolumeMount,
+    Capabilities, ConfigMapVolumeSource, Container, EmptyDirVolumeSource, EnvVar, ExecAction,
+    HTTPGetAction, Handler, HostPathVolumeSource, Lifecycle, PersistentVolumeClaimVolumeSource,
+    Pod, PodSecurityContext, PodSpec, Probe, SeccompProfile, SecretVolumeSource, SecurityContext,
+    Toleration, Volume, VolumeMount,
 };
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
 use kube::Resource;
 use stackable_operator::krustlet::create_tolerations;
 use stackable_operator::labels;
 use stackable_operator::metadata;
-use stackable_spark_crd::{SparkCluster, SparkClusterSpec, SparkNodeType};
+use stackable_spark_common::constants::{
+    SPARK_IMAGE_CONFIG_DIR, SPARK_IMAGE_LOG_DIR, SPARK_IMAGE_TMP_DIR, SPARK_IMAGE_WORK_DIR,
+};
+use stackable_spark_crd::probes::ProbeTimings;
+use stackable_spark_crd::volumes::{EventLogVolume, ExtraVolume};
+use stackable_spark_crd::{Config, Runtime, SparkCluster, SparkClusterSpec, SparkNodeType};
 use std::collections::hash_map::DefaultHasher;
 use std::collections::BTreeMap;
 use std::hash::{Hash, Hasher};
+use tracing::warn;
 
 /// Value for the APP_NAME_LABEL label key
 pub const APP_NAME: &str = "spark";
 /// Pod label which indicates the known master urls for a worker pod
 pub const MASTER_URLS_HASH_LABEL: &str = "spark.stackable.tech/masterUrls";
+/// Pod label which contains the index of a pod within its role group (Scheduler placement)
+pub const POD_INDEX_LABEL: &str = "spark.stackable.tech/podIndex";
+/// Name of the container running the Spark daemon
+const SPARK_CONTAINER: &str = "spark";
 /// Name of the config volume to store configmap data
 const CONFIG_VOLUME: &str = "config-volume";
 /// Name of the logging / event volume for SparkNode logs required by the history server
 const EVENT_VOLUME: &str = "event-volume";
+/// HTTP path the probes of masters and workers check
+const WEB_UI_PROBE_PATH: &str = "/";
+/// HTTP path the probes of the history server check
+const HISTORY_SERVER_PROBE_PATH: &str = "/api/v1/applications";
+/// Name of the writable scratch volume if the root filesystem is read only
+const SCRATCH_VOLUME: &str = "scratch-volume";
+/// preStop command of workers: SIGPWR starts the decommission of the worker (Spark 3.1), then
+/// the hook waits until all executors of the worker exited
+const DECOMMISSION_COMMAND: &str = "pkill -PWR -f org.apache.spark.deploy.worker.Worker; \
+    while pgrep -f org.apache.spark.executor.CoarseGrainedExecutorBackend > /dev/null; \
+    do sleep 1; done";
 
 /// Build a pod which represents a SparkNode (Master, Worker, HistoryServer) in the cluster.
+/// The pod is pinned to the given node via `nodeName` (Node placement).
 ///
 /// # Arguments
 /// * `resource` - SparkCluster
@@ -38,18 +68,115 @@ pub fn build_pod(
     role_group: &str,
     node_type: &SparkNodeType,
     master_urls: &[String],
+) -> Result<Pod, Error> {
+    // we use the node_name in the pod name; otherwise pod names are not unique
+    let pod_name = create_pod_name(
+        &resource.name(),
+        role_group,
+        &node_type.to_string(),
+        Some(node_name),
+    );
+
+    build_pod_with_spec(
+        resource,
+        pod_name,
+        BTreeMap::new(),
+        role_group,
+        node_type,
+        master_urls,
+        PodSpec {
+            node_name: Some(node_name.to_string()),
+            tolerations: get_tolerations(&resource.spec),
+            ..PodSpec::default()
+        },
+    )
+}
+
+/// Build a pod which represents a SparkNode (Master, Worker, HistoryServer) in the cluster.
+/// The pod is placed by the Kubernetes scheduler (Scheduler placement) on a node that matches
+/// the selector of the role group. Masters are spread over nodes via pod anti affinity.
+///
+/// # Arguments
+/// * `resource` - SparkCluster
+/// * `pod_index` - Index of the pod within its role group (0..instances)
+/// * `role_group` - The role group of the selector
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+/// * `master_urls` - Slice of all known master urls
+///
+pub fn build_scheduled_pod(
+    resource: &SparkCluster,
+    pod_index: usize,
+    role_group: &str,
+    node_type: &SparkNodeType,
+    master_urls: &[String],
 ) -> Result<Pod, Error> {
     let cluster_name = &resource.name();
 
-    // we use the node_name in the pod name; otherwise pod names are not unique
+    // we use the pod index in the pod name; otherwise pod names are not unique
     let pod_name = create_pod_name(
         cluster_name,
         role_group,
         &node_type.to_string(),
-        Some(node_name),
+        Some(&pod_index.to_string()),
     );
 
-    // we do not attach the node_name to the config map name
+    let mut index_label = BTreeMap::new();
+    index_label.insert(POD_INDEX_LABEL.to_string(), pod_index.to_string());
+
+    let selector = resource.spec.get_selector(node_type, role_group);
+
+    build_pod_with_spec(
+        resource,
+        pod_name,
+        index_label,
+        role_group,
+        node_type,
+        master_urls,
+        PodSpec {
+            affinity: placement::build_affinity(selector.as_ref(), node_type, cluster_name),
+            tolerations: get_tolerations(&resource.spec),
+            ..PodSpec::default()
+        },
+    )
+}
+
+/// Return the configured tolerations. If not configured, the Krustlet tolerations are
+/// returned for the stackableAgent runtime.
+///
+/// # Arguments
+/// * `spec` - The spark cluster spec
+///
+pub fn get_tolerations(spec: &SparkClusterSpec) -> Option<Vec<Toleration>> {
+    match (&spec.tolerations, spec.get_runtime()) {
+        (Some(tolerations), _) => Some(tolerations.clone()),
+        (None, Runtime::StackableAgent) => Some(create_tolerations()),
+        (None, Runtime::Kubernetes) => None,
+    }
+}
+
+/// Add metadata, containers and volumes to the given (placement specific) pod spec.
+///
+/// # Arguments
+/// * `resource` - SparkCluster
+/// * `pod_name` - The name of the pod
+/// * `additional_labels` - Labels to add to the default pod labels
+/// * `role_group` - The role group of the selector
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+/// * `master_urls` - Slice of all known master urls
+/// * `pod_spec` - Pod spec containing the placement (node name, affinity, tolerations)
+///
+fn build_pod_with_spec(
+    resource: &SparkCluster,
+    pod_name: String,
+    additional_labels: BTreeMap<String, String>,
+    role_group: &str,
+    node_type: &SparkNodeType,
+    master_urls: &[String],
+    pod_spec: PodSpec,
+) -> Result<Pod, Error> {
+    let cluster_name = &resource.name();
+
+    // we do not attach the node_name or pod index to the config map name
     let cm_name = create_config_map_name(&create_pod_name(
         cluster_name,
         role_group,
@@ -57,30 +184,62 @@ pub fn build_pod(
         None,
     ));
 
-    let (containers, volumes) = build_containers(&resource.spec, node_type, &cm_name, master_urls);
-
-    Ok(Pod {
-        metadata: metadata::build_metadata(
-            pod_name,
-            Some(build_labels(
-                node_type,
-                role_group,
-                cluster_name,
-                &resource.spec.version.to_string(),
-                master_urls,
-            )),
-            resource,
-            true,
-        )?,
+    let config = resource.spec.get_config(node_type, role_group);
+    let extra_volumes = config
+        .as_ref()
+        .and_then(|config| config.get_extra_volumes().cloned())
+        .unwrap_or_default();
+    let env = config
+        .as_ref()
+        .and_then(|config| config.get_env().cloned())
+        .unwrap_or_default();
+
+    let probes = build_probes(&resource.spec, node_type, config.as_deref());
+
+    let (containers, volumes) = build_containers(
+        &resource.spec,
+        node_type,
+        &cm_name,
+        master_urls,
+        &extra_volumes,
+        &env,
+        probes,
+    )?;
+
+    let mut labels = build_labels(
+        node_type,
+        role_group,
+        cluster_name,
+        &resource.spec.version.to_string(),
+        master_urls,
+    );
+    labels.extend(additional_labels);
+
+    // the pod must not be killed before the worker finished its decommission
+    let termination_grace_period_seconds = if is_decommissioned(&resource.spec, node_type) {
+        Some(resource.spec.get_decommission_timeout(role_group).as_secs() as i64)
+    } else {
+        None
+    };
+
+    let pod = Pod {
+        metadata: metadata::build_metadata(pod_name, Some(labels), resource, true)?,
         spec: Some(PodSpec {
-            node_name: Some(node_name.to_string()),
-            tolerations: Some(create_tolerations()),
             containers,
             volumes: Some(volumes),
-            ..PodSpec::default()
+            security_context: build_pod_security_context(&resource.spec),
+            service_account_name: Some(rbac::create_service_account_name(cluster_name)),
+            termination_grace_period_seconds,
+            ..pod_spec
         }),
         ..Pod::default()
-    })
+    };
+
+    pod_overrides::apply_pod_overrides(
+        pod,
+        &resource.spec.get_pod_overrides(node_type, role_group),
+        SPARK_CONTAINER,
+    )
 }
 
 /// Build required pod containers
@@ -90,33 +249,292 @@ pub fn build_pod(
 /// * `node_type` - The cluster node type (e.g. master, worker, history-server)
 /// * `cm_name` - The name of the config map
 /// * `master_urls` - Slice of all known master urls
+/// * `extra_volumes` - User defined ConfigMaps and Secrets to mount
+/// * `env` - User defined env vars of the Spark container
+/// * `probes` - The liveness and readiness probes of the Spark container
 ///
 fn build_containers(
     spec: &SparkClusterSpec,
     node_type: &SparkNodeType,
     cm_name: &str,
     master_urls: &[String],
-) -> (Vec<Container>, Vec<Volume>) {
-    let image_name = format!("spark:{}", &spec.version.to_string());
+    extra_volumes: &[ExtraVolume],
+    env: &[EnvVar],
+    probes: (Option<Probe>, Option<Probe>),
+) -> Result<(Vec<Container>, Vec<Volume>), Error> {
+    let runtime = spec.get_runtime();
+    let image_name = runtime.get_image_name(&spec.version);
 
-    let mut command = vec![node_type.get_command(&spec.version.to_string())];
+    let mut command = vec![node_type.get_command(&spec.version.to_string(), &runtime)];
     // adapt worker command with master url(s)
     if let Some(master_urls) = config::adapt_worker_command(node_type, master_urls) {
         command.push(master_urls);
     }
 
+    let mut volume_mounts = create_volume_mounts(&spec.log_dir, &runtime);
+    let mut volumes = create_volumes(
+        &cm_name,
+        spec.log_dir.clone(),
+        &runtime,
+        &spec.event_log_volume,
+    )?;
+
+    for extra_volume in extra_volumes {
+        volumes.push(create_extra_volume(extra_volume)?);
+        volume_mounts.push(VolumeMount {
+            mount_path: extra_volume.mount_path.clone(),
+            name: extra_volume.volume_name(),
+            read_only: Some(true),
+            ..VolumeMount::default()
+        });
+    }
+
+    let security_context = build_security_context(spec);
+    // Spark requires writable work, log and tmp directories
+    if security_context
+        .as_ref()
+        .and_then(|security_context| security_context.read_only_root_filesystem)
+        == Some(true)
+    {
+        add_scratch_volume(&mut volumes, &mut volume_mounts);
+    }
+
     let containers = vec![Container {
         image: Some(image_name),
-        name: "spark".to_string(),
+        name: SPARK_CONTAINER.to_string(),
         command: Some(command),
-        volume_mounts: Some(create_volume_mounts(&spec.log_dir)),
-        env: Some(config::create_required_startup_env()),
+        volume_mounts: Some(volume_mounts),
+        env: Some(config::create_container_env(&runtime, node_type, env)),
+        security_context,
+        liveness_probe: probes.0,
+        readiness_probe: probes.1,
+        lifecycle: build_lifecycle(spec, node_type),
         ..Container::default()
     }];
 
-    let volumes = create_volumes(&cm_name, spec.log_dir.clone());
+    Ok((containers, volumes))
+}
+
+/// Check if pods of the given node type are decommissioned gracefully before they are stopped.
+/// Only workers are decommissioned, see `SparkClusterSpec::supports_graceful_decommission`.
+///
+/// # Arguments
+/// * `spec` - SparkClusterSpec to get the runtime and version
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+///
+fn is_decommissioned(spec: &SparkClusterSpec, node_type: &SparkNodeType) -> bool {
+    node_type == &SparkNodeType::Worker && spec.supports_graceful_decommission()
+}
+
+/// Build the lifecycle of the Spark container. Workers that support the graceful decommission
+/// get a preStop hook which decommissions the worker and waits for its executors to exit
+/// before the container is stopped. The wait is limited by the termination grace period.
+///
+/// # Arguments
+/// * `spec` - SparkClusterSpec to get the runtime and version
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+///
+fn build_lifecycle(spec: &SparkClusterSpec, node_type: &SparkNodeType) -> Option<Lifecycle> {
+    if !is_decommissioned(spec, node_type) {
+        return None;
+    }
+
+    Some(Lifecycle {
+        pre_stop: Some(Handler {
+            exec: Some(ExecAction {
+                command: Some(vec![
+                    "/bin/sh".to_string(),
+                    "-c".to_string(),
+                    DECOMMISSION_COMMAND.to_string(),
+                ]),
+            }),
+            ..Handler::default()
+        }),
+        ..Lifecycle::default()
+    })
+}
+
+/// Build the liveness and readiness probes of the Spark container. Both check the web ui of
+/// the daemon via HTTP (history server: the applications REST endpoint). Only applied for the
+/// kubernetes runtime if not disabled. A worker that lost its master still serves its web ui,
+/// such workers are replaced via `master_api::find_unregistered_workers` instead.
+///
+/// # Arguments
+/// * `spec` - SparkClusterSpec to get the runtime
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+/// * `config` - The config of the role group for the probe timings and the web ui port
+///
+fn build_probes(
+    spec: &SparkClusterSpec,
+    node_type: &SparkNodeType,
+    config: Option<&dyn Config>,
+) -> (Option<Probe>, Option<Probe>) {
+    let config = match config {
+        Some(config) => config,
+        None => return (None, None),
+    };
+
+    let probes = config.get_probes().cloned().unwrap_or_default();
+    if spec.get_runtime() != Runtime::Kubernetes || !probes.is_enabled() {
+        return (None, None);
+    }
+
+    let web_ui_port = config.get_web_ui_port(spec);
+    let port = match web_ui_port.parse::<i32>() {
+        Ok(port) => port,
+        Err(_) => {
+            warn!(
+                "Web ui port [{}] of [{}] is not a number, no probes are added",
+                web_ui_port, node_type
+            );
+            return (None, None);
+        }
+    };
+
+    let path = match node_type {
+        SparkNodeType::HistoryServer => HISTORY_SERVER_PROBE_PATH,
+        _ => WEB_UI_PROBE_PATH,
+    };
+
+    let build_probe = |timings: ProbeTimings| Probe {
+        http_get: Some(HTTPGetAction {
+            path: Some(path.to_string()),
+            port: IntOrString::Int(port),
+            ..HTTPGetAction::default()
+        }),
+        initial_delay_seconds: timings.initial_delay_seconds,
+        period_seconds: timings.period_seconds,
+        timeout_seconds: timings.timeout_seconds,
+        failure_threshold: timings.failure_threshold,
+        ..Probe::default()
+    };
+
+    (
+        Some(build_probe(probes.get_liveness())),
+        Some(build_probe(probes.get_readiness())),
+    )
+}
+
+/// Create the volume of a user defined ConfigMap or Secret.
+///
+/// # Arguments
+/// * `extra_volume` - The extra volume of the role group config
+///
+fn create_extra_volume(extra_volume: &ExtraVolume) -> Result<Volume, Error> {
+    let (config_map, secret) = match (&extra_volume.config_map, &extra_volume.secret) {
+        (Some(config_map), None) => (
+            Some(ConfigMapVolumeSource {
+                name: Some(config_map.clone()),
+                ..ConfigMapVolumeSource::default()
+            }),
+            None,
+        ),
+        (None, Some(secret)) => (
+            None,
+            Some(SecretVolumeSource {
+                secret_name: Some(secret.clone()),
+                ..SecretVolumeSource::default()
+            }),
+        ),
+        _ => {
+            return Err(Error::InvalidExtraVolume {
+                name: extra_volume.name.clone(),
+            })
+        }
+    };
+
+    Ok(Volume {
+        name: extra_volume.volume_name(),
+        config_map,
+        secret,
+        ..Volume::default()
+    })
+}
+
+/// Build the pod security context (non root user, fsGroup and seccomp profile). Only applied
+/// for the kubernetes runtime if not disabled.
+///
+/// # Arguments
+/// * `spec` - SparkClusterSpec to get the runtime and security settings
+///
+fn build_pod_security_context(spec: &SparkClusterSpec) -> Option<PodSecurityContext> {
+    let security = spec.get_security();
+    if spec.get_runtime() != Runtime::Kubernetes || !security.is_enabled() {
+        return None;
+    }
+
+    Some(PodSecurityContext {
+        run_as_non_root: Some(true),
+        run_as_user: Some(security.get_run_as_user()),
+        run_as_group: Some(security.get_run_as_group()),
+        fs_group: Some(security.get_fs_group()),
+        seccomp_profile: Some(SeccompProfile {
+            type_: security.get_seccomp_profile().to_string(),
+            ..SeccompProfile::default()
+        }),
+        ..PodSecurityContext::default()
+    })
+}
+
+/// Build the security context of the Spark container (no privilege escalation, all
+/// capabilities dropped and optionally a read only root filesystem). Only applied for the
+/// kubernetes runtime if not disabled.
+///
+/// # Arguments
+/// * `spec` - SparkClusterSpec to get the runtime and security settings
+///
+fn build_security_context(spec: &SparkClusterSpec) -> Option<SecurityContext> {
+    let security = spec.get_security();
+    if spec.get_runtime() != Runtime::Kubernetes || !security.is_enabled() {
+        return None;
+    }
+
+    Some(SecurityContext {
+        allow_privilege_escalation: Some(false),
+        capabilities: Some(Capabilities {
+            drop: Some(vec!["ALL".to_string()]),
+            ..Capabilities::default()
+        }),
+        privileged: Some(false),
+        read_only_root_filesystem: Some(security.is_read_only_root_filesystem()),
+        run_as_non_root: Some(true),
+        ..SecurityContext::default()
+    })
+}
+
+/// Add an emptyDir scratch volume and mount it to the work, log and tmp directories of Spark,
+/// unless these directories are already mounted (e.g. the log dir or extra volumes).
+///
+/// # Arguments
+/// * `volumes` - The volumes of the pod
+/// * `volume_mounts` - The volume mounts of the Spark container
+///
+fn add_scratch_volume(volumes: &mut Vec<Volume>, volume_mounts: &mut Vec<VolumeMount>) {
+    volumes.push(Volume {
+        name: SCRATCH_VOLUME.to_string(),
+        empty_dir: Some(EmptyDirVolumeSource::default()),
+        ..Volume::default()
+    });
+
+    for (sub_path, mount_path) in &[
+        ("work", SPARK_IMAGE_WORK_DIR),
+        ("logs", SPARK_IMAGE_LOG_DIR),
+        ("tmp", SPARK_IMAGE_TMP_DIR),
+    ] {
+        if volume_mounts
+            .iter()
+            .any(|volume_mount| volume_mount.mount_path == *mount_path)
+        {
+            continue;
+        }
 
-    (containers, volumes)
+        volume_mounts.push(VolumeMount {
+            mount_path: mount_path.to_string(),
+            name: SCRATCH_VOLUME.to_string(),
+            sub_path: Some(sub_path.to_string()),
+            ..VolumeMount::default()
+        });
+    }
 }
 
 /// Create a volume to store the spark config files and optional an event volume for spark logs.
@@ -124,8 +542,15 @@ fn build_containers(
 /// # Arguments
 /// * `cm_name` - ConfigMap name where the required spark configuration files (spark-defaults.conf and spark-env.sh) are located
 /// * `log_dir` - Event/Log dir for SparkNodes. History Server reads these logs to offer metrics
+/// * `runtime` - The kind of node agent that runs the pods
+/// * `event_log_volume` - The shared volume of the event logs (kubernetes runtime)
 ///
-fn create_volumes(cm_name: &str, log_dir: Option<String>) -> Vec<Volume> {
+fn create_volumes(
+    cm_name: &str,
+    log_dir: Option<String>,
+    runtime: &Runtime,
+    event_log_volume: &Option<EventLogVolume>,
+) -> Result<Vec<Volume>, Error> {
     let mut volumes = vec![Volume {
         name: CONFIG_VOLUME.to_string(),
         config_map: Some(ConfigMapVolumeSource {
@@ -136,23 +561,61 @@ fn create_volumes(cm_name: &str, log_dir: Option<String>) -> Vec<Volume> {
     }];
 
     if log_dir.is_some() {
-        volumes.push(Volume {
+        let mut volume = Volume {
             name: EVENT_VOLUME.to_string(),
             ..Volume::default()
-        })
+        };
+
+        // a regular kubelet requires a volume source, without a shared volume the event logs
+        // only live as long as the pod
+        if *runtime == Runtime::Kubernetes {
+            match event_log_volume {
+                Some(event_log_volume) if !event_log_volume.is_valid() => {
+                    return Err(Error::InvalidEventLogVolume)
+                }
+                Some(EventLogVolume {
+                    persistent_volume_claim: Some(claim_name),
+                    ..
+                }) => {
+                    volume.persistent_volume_claim = Some(PersistentVolumeClaimVolumeSource {
+                        claim_name: claim_name.clone(),
+                        ..PersistentVolumeClaimVolumeSource::default()
+                    })
+                }
+                Some(EventLogVolume {
+                    host_path: Some(path),
+                    ..
+                }) => {
+                    volume.host_path = Some(HostPathVolumeSource {
+                        path: path.clone(),
+                        type_: Some("DirectoryOrCreate".to_string()),
+                    })
+                }
+                _ => volume.empty_dir = Some(EmptyDirVolumeSource::default()),
+            }
+        }
+
+        volumes.push(volume)
     }
 
-    volumes
+    Ok(volumes)
 }
 
 /// Create volume mounts for the spark config files and optional an event dir for spark logs.
 ///
 /// # Arguments
 /// * `log_dir` - Event/Log dir for SparkNodes. History Server reads these logs to offer metrics
+/// * `runtime` - The kind of node agent that runs the pods
 ///
-fn create_volume_mounts(log_dir: &Option<String>) -> Vec<VolumeMount> {
+fn create_volume_mounts(log_dir: &Option<String>, runtime: &Runtime) -> Vec<VolumeMount> {
+    // the Stackable agent mounts relative to the config root of the pod
+    let config_mount_path = match runtime {
+        Runtime::StackableAgent => "conf",
+        Runtime::Kubernetes => SPARK_IMAGE_CONFIG_DIR,
+    };
+
     let mut volume_mounts = vec![VolumeMount {
-        mount_path: "conf".to_string(),
+        mount_path: config_mount_path.to_string(),
         name: CONFIG_VOLUME.to_string(),
         ..VolumeMount::default()
     }];
@@ -215,13 +678,33 @@ fn build_labels(
     labels
 }
 
+/// Build the label selector (e.g. for the scale subresource) that matches all pods of a
+/// role in a cluster.
+///
+/// # Arguments
+/// * `cluster_name` - The name of the cluster as specified in the custom resource
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+///
+pub fn build_role_selector(cluster_name: &str, node_type: &SparkNodeType) -> String {
+    format!(
+        "{}={},{}={},{}={}",
+        labels::APP_NAME_LABEL,
+        APP_NAME,
+        labels::APP_INSTANCE_LABEL,
+        cluster_name,
+        labels::APP_COMPONENT_LABEL,
+        node_type
+    )
+}
+
 /// All pod names follow a simple pattern: spark-<cluster_name>-<role_group>-<node_type>-<node_name>
+/// (or spark-<cluster_name>-<role_group>-<node_type>-<pod_index> for Scheduler placement)
 ///
 /// # Arguments
 /// * `cluster_name` - The name of the cluster as specified in the custom resource
 /// * `role_group` - The role group of the selector
 /// * `node_type` - The cluster node type (e.g. master, worker, history-server)
-/// * `node_name` - The node or host name
+/// * `node_name` - The node or host name (or the pod index)
 ///
 pub fn create_pod_name(
     cluster_name: &str,
@@ -275,10 +758,103 @@ pub fn filter_pods_for_type(pods: &[Pod], node_type: &SparkNodeType) -> Vec<Pod>
     filtered_pods
 }
 
+/// Filter all existing pods for the specified spark node type and role group.
+///
+/// # Arguments
+/// * `pods` - Slice of all existing pods
+/// * `node_type` - The cluster node type (e.g. master, worker, history-server)
+/// * `role_group` - The role group of the selector
+///
+pub fn filter_pods_for_role_group(
+    pods: &[Pod],
+    node_type: &SparkNodeType,
+    role_group: &str,
+) -> Vec<Pod> {
+    filter_pods_for_type(pods, node_type)
+        .into_iter()
+        .filter(|pod| {
+            pod.metadata
+                .labels
+                .as_ref()
+                .and_then(|labels| labels.get(labels::APP_ROLE_GROUP_LABEL))
+                .map(|group| group == role_group)
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+/// Check if a pod is in phase "Running" and has the "Ready" condition set to "True".
+///
+/// # Arguments
+/// * `pod` - The pod to check
+///
+pub fn is_pod_running_and_ready(pod: &Pod) -> bool {
+    if let Some(status) = &pod.status {
+        let running = status.phase.as_deref() == Some("Running");
+        let ready = status
+            .conditions
+            .as_ref()
+            .map(|conditions| {
+                conditions
+                    .iter()
+                    .any(|condition| condition.type_ == "Ready" && condition.status == "True")
+            })
+            .unwrap_or(false);
+
+        return running && ready;
+    }
+
+    false
+}
+
+/// Return the time since when the pod is ready (the last transition time of the
+/// "Ready" condition if it is set to "True").
+///
+/// # Arguments
+/// * `pod` - The pod to check
+///
+pub fn get_ready_since(pod: &Pod) -> Option<DateTime<Utc>> {
+    pod.status
+        .as_ref()
+        .and_then(|status| status.conditions.as_ref())
+        .and_then(|conditions| {
+            conditions
+                .iter()
+                .find(|condition| condition.type_ == "Ready" && condition.status == "True")
+        })
+        .and_then(|condition| condition.last_transition_time.as_ref())
+        .map(|time| time.0)
+}
+
+/// Check if any container of a pod is waiting to be restarted after crashing repeatedly.
+///
+/// # Arguments
+/// * `pod` - The pod to check
+///
+pub fn is_pod_crash_looping(pod: &Pod) -> bool {
+    pod.status
+        .as_ref()
+        .and_then(|status| status.container_statuses.as_ref())
+        .map(|container_statuses| {
+            container_statuses.iter().any(|container_status| {
+                container_status
+                    .state
+                    .as_ref()
+                    .and_then(|state| state.waiting.as_ref())
+                    .and_then(|waiting| waiting.reason.as_deref())
+                    == Some("CrashLoopBackOff")
+            })
+        })
+        .unwrap_or(false)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::adapt_worker_command;
+    use stackable_spark_crd::probes::ProbesConfig;
+    use stackable_spark_crd::security::SecurityConfig;
+    use stackable_spark_crd::SparkVersion;
     use stackable_spark_test_utils::cluster::{Data, TestSparkCluster};
 
     #[test]
@@ -322,7 +898,10 @@ mod tests {
         let container = containers.get(0).unwrap();
         assert_eq!(
             container.command.clone().unwrap(),
-            vec![node_type.get_command(&spark_cluster.spec.version.to_string())]
+            vec![node_type.get_command(
+                &spark_cluster.spec.version.to_string(),
+                &Runtime::StackableAgent
+            )]
         );
         // only start command for masters
         assert_eq!(container.command.clone().unwrap().len(), 1);
@@ -386,15 +965,428 @@ mod tests {
         assert_eq!(
             command,
             vec![
-                node_type.get_command(&spark_cluster.spec.version.to_string()),
+                node_type.get_command(
+                    &spark_cluster.spec.version.to_string(),
+                    &Runtime::StackableAgent
+                ),
                 adapt_worker_command(node_type, master_urls.as_slice()).unwrap()
             ]
         );
     }
 
+    #[test]
+    fn test_build_scheduled_master_pod() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+        spark_cluster.spec.placement = Some(stackable_spark_crd::Placement::Scheduler);
+        spark_cluster.spec.runtime = Some(Runtime::Kubernetes);
+
+        let master_urls = stackable_spark_test_utils::create_master_urls();
+
+        let pod = build_scheduled_pod(
+            &spark_cluster,
+            1,
+            TestSparkCluster::MASTER_1_ROLE_GROUP,
+            &SparkNodeType::Master,
+            master_urls.as_slice(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            pod.metadata.name,
+            Some(create_pod_name(
+                &spark_cluster.name(),
+                TestSparkCluster::MASTER_1_ROLE_GROUP,
+                &SparkNodeType::Master.to_string(),
+                Some("1"),
+            ))
+        );
+        assert_eq!(
+            pod.metadata.labels.as_ref().unwrap().get(POD_INDEX_LABEL),
+            Some(&"1".to_string())
+        );
+
+        // the scheduler places the pod, no krustlet tolerations are added
+        let spec = pod.spec.unwrap();
+        assert_eq!(spec.node_name, None);
+        assert_eq!(spec.tolerations, None);
+
+        // kubernetes runtime uses images and absolute config paths
+        let container = &spec.containers[0];
+        assert_eq!(
+            container.image,
+            Some(Runtime::Kubernetes.get_image_name(&spark_cluster.spec.version))
+        );
+        assert_eq!(
+            container.volume_mounts.as_ref().unwrap()[0].mount_path,
+            SPARK_IMAGE_CONFIG_DIR
+        );
+        assert!(spec
+            .volumes
+            .unwrap()
+            .iter()
+            .filter(|volume| volume.name == EVENT_VOLUME)
+            .all(|volume| volume.empty_dir.is_some()));
+
+        let affinity = spec.affinity.unwrap();
+        assert!(affinity.node_affinity.is_some());
+        assert!(affinity.pod_anti_affinity.is_some());
+    }
+
+    #[test]
+    fn test_build_pod_with_extra_volumes() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+        spark_cluster.spec.workers.config = Some(stackable_spark_crd::WorkerConfig {
+            volumes: Some(vec![ExtraVolume {
+                name: "jars".to_string(),
+                mount_path: "/stackable/jars".to_string(),
+                config_map: Some("spark-jars".to_string()),
+                secret: None,
+            }]),
+            ..stackable_spark_crd::WorkerConfig::default()
+        });
+
+        let master_urls = stackable_spark_test_utils::create_master_urls();
+        let build_worker_pod = |spark_cluster: &SparkCluster| {
+            build_pod(
+                spark_cluster,
+                TestSparkCluster::WORKER_1_NODE_NAME,
+                TestSparkCluster::WORKER_1_ROLE_GROUP,
+                &SparkNodeType::Worker,
+                master_urls.as_slice(),
+            )
+        };
+
+        let spec = build_worker_pod(&spark_cluster).unwrap().spec.unwrap();
+        let volume = spec
+            .volumes
+            .unwrap()
+            .into_iter()
+            .find(|volume| volume.name == "extra-jars")
+            .unwrap();
+        assert_eq!(
+            volume.config_map.unwrap().name,
+            Some("spark-jars".to_string())
+        );
+        assert!(spec.containers[0]
+            .volume_mounts
+            .as_ref()
+            .unwrap()
+            .iter()
+            .any(|mount| mount.name == "extra-jars" && mount.mount_path == "/stackable/jars"));
+
+        // a volume must reference exactly one source
+        if let Some(config) = spark_cluster.spec.workers.config.as_mut() {
+            config.volumes.as_mut().unwrap()[0].secret = Some("spark-secret".to_string());
+        }
+        assert!(build_worker_pod(&spark_cluster).is_err());
+    }
+
+    #[test]
+    fn test_build_pod_with_event_log_volume() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+        spark_cluster.spec.runtime = Some(Runtime::Kubernetes);
+        spark_cluster.spec.event_log_volume = Some(EventLogVolume {
+            persistent_volume_claim: Some("spark-events".to_string()),
+            host_path: None,
+        });
+
+        let master_urls = stackable_spark_test_utils::create_master_urls();
+        let build_worker_pod = |spark_cluster: &SparkCluster| {
+            build_pod(
+                spark_cluster,
+                TestSparkCluster::WORKER_1_NODE_NAME,
+                TestSparkCluster::WORKER_1_ROLE_GROUP,
+                &SparkNodeType::Worker,
+                master_urls.as_slice(),
+            )
+        };
+
+        let spec = build_worker_pod(&spark_cluster).unwrap().spec.unwrap();
+        let volume = spec
+            .volumes
+            .unwrap()
+            .into_iter()
+            .find(|volume| volume.name == EVENT_VOLUME)
+            .unwrap();
+        assert_eq!(
+            volume.persistent_volume_claim.unwrap().claim_name,
+            "spark-events"
+        );
+        assert!(volume.empty_dir.is_none());
+        assert!(spec.containers[0]
+            .volume_mounts
+            .as_ref()
+            .unwrap()
+            .iter()
+            .any(|mount| mount.name == EVENT_VOLUME
+                && Some(&mount.mount_path) == spark_cluster.spec.log_dir.as_ref()));
+
+        // the event log volume must reference exactly one source
+        if let Some(event_log_volume) = spark_cluster.spec.event_log_volume.as_mut() {
+            event_log_volume.host_path = Some("/data/spark-events".to_string());
+        }
+        assert!(build_worker_pod(&spark_cluster).is_err());
+    }
+
+    #[test]
+    fn test_build_pod_security_context() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+        spark_cluster.spec.placement = Some(stackable_spark_crd::Placement::Scheduler);
+        spark_cluster.spec.runtime = Some(Runtime::Kubernetes);
+
+        let master_urls = stackable_spark_test_utils::create_master_urls();
+        let build_worker_pod = |spark_cluster: &SparkCluster| {
+            build_scheduled_pod(
+                spark_cluster,
+                0,
+                TestSparkCluster::WORKER_1_ROLE_GROUP,
+                &SparkNodeType::Worker,
+                master_urls.as_slice(),
+            )
+            .unwrap()
+            .spec
+            .unwrap()
+        };
+
+        // secure defaults ("restricted" Pod Security Standard)
+        let spec = build_worker_pod(&spark_cluster);
+        let pod_security_context = spec.security_context.unwrap();
+        assert_eq!(pod_security_context.run_as_non_root, Some(true));
+        assert_eq!(pod_security_context.run_as_user, Some(1000));
+        assert_eq!(pod_security_context.fs_group, Some(1000));
+        assert_eq!(
+            pod_security_context.seccomp_profile.unwrap().type_,
+            "RuntimeDefault"
+        );
+
+        let container = &spec.containers[0];
+        let security_context = container.security_context.as_ref().unwrap();
+        assert_eq!(security_context.allow_privilege_escalation, Some(false));
+        assert_eq!(
+            security_context.capabilities.as_ref().unwrap().drop,
+            Some(vec!["ALL".to_string()])
+        );
+        assert_eq!(security_context.read_only_root_filesystem, Some(true));
+
+        // the work, log and tmp dirs are writable
+        assert!(spec
+            .volumes
+            .unwrap()
+            .iter()
+            .any(|volume| volume.name == SCRATCH_VOLUME && volume.empty_dir.is_some()));
+        for dir in &[
+            SPARK_IMAGE_WORK_DIR,
+            SPARK_IMAGE_LOG_DIR,
+            SPARK_IMAGE_TMP_DIR,
+        ] {
+            assert!(container
+                .volume_mounts
+                .as_ref()
+                .unwrap()
+                .iter()
+                .any(|mount| mount.mount_path == *dir));
+        }
+
+        // no scratch volume with a writable root filesystem
+        spark_cluster.spec.security = Some(SecurityConfig {
+            read_only_root_filesystem: Some(false),
+            ..SecurityConfig::default()
+        });
+        let spec = build_worker_pod(&spark_cluster);
+        assert!(!spec
+            .volumes
+            .unwrap()
+            .iter()
+            .any(|volume| volume.name == SCRATCH_VOLUME));
+
+        // disabled
+        spark_cluster.spec.security = Some(SecurityConfig {
+            enabled: Some(false),
+            ..SecurityConfig::default()
+        });
+        let spec = build_worker_pod(&spark_cluster);
+        assert_eq!(spec.security_context, None);
+        assert_eq!(spec.containers[0].security_context, None);
+
+        // not applied for the stackable agent
+        spark_cluster.spec.security = None;
+        spark_cluster.spec.runtime = Some(Runtime::StackableAgent);
+        let spec = build_worker_pod(&spark_cluster);
+        assert_eq!(spec.security_context, None);
+    }
+
+    #[test]
+    fn test_build_pod_probes() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+        spark_cluster.spec.placement = Some(stackable_spark_crd::Placement::Scheduler);
+        spark_cluster.spec.runtime = Some(Runtime::Kubernetes);
+
+        let master_urls = stackable_spark_test_utils::create_master_urls();
+        let build_container =
+            |spark_cluster: &SparkCluster, role_group: &str, node_type: &SparkNodeType| {
+                build_scheduled_pod(spark_cluster, 0, role_group, node_type, &master_urls)
+                    .unwrap()
+                    .spec
+                    .unwrap()
+                    .containers
+                    .remove(0)
+            };
+
+        // workers check their web ui with the default timings
+        let container = build_container(
+            &spark_cluster,
+            TestSparkCluster::WORKER_1_ROLE_GROUP,
+            &SparkNodeType::Worker,
+        );
+        let readiness_probe = container.readiness_probe.unwrap();
+        let http_get = readiness_probe.http_get.unwrap();
+        assert_eq!(http_get.path, Some(WEB_UI_PROBE_PATH.to_string()));
+        assert_eq!(
+            http_get.port,
+            IntOrString::Int(TestSparkCluster::WORKER_1_WEBUI_PORT as i32)
+        );
+        assert_eq!(readiness_probe.failure_threshold, Some(3));
+        assert_eq!(
+            container.liveness_probe.unwrap().initial_delay_seconds,
+            Some(30)
+        );
+
+        // the history server checks the applications endpoint
+        let container = build_container(
+            &spark_cluster,
+            TestSparkCluster::HISTORY_SERVER_ROLE_GROUP,
+            &SparkNodeType::HistoryServer,
+        );
+        assert_eq!(
+            container.liveness_probe.unwrap().http_get.unwrap().path,
+            Some(HISTORY_SERVER_PROBE_PATH.to_string())
+        );
+
+        // configured timings
+        spark_cluster.spec.workers.config = Some(stackable_spark_crd::WorkerConfig {
+            probes: Some(ProbesConfig {
+                readiness: Some(ProbeTimings {
+                    period_seconds: Some(30),
+                    ..ProbeTimings::default()
+                }),
+                ..ProbesConfig::default()
+            }),
+            ..stackable_spark_crd::WorkerConfig::default()
+        });
+        let container = build_container(
+            &spark_cluster,
+            TestSparkCluster::WORKER_1_ROLE_GROUP,
+            &SparkNodeType::Worker,
+        );
+        let readiness_probe = container.readiness_probe.unwrap();
+        assert_eq!(readiness_probe.period_seconds, Some(30));
+        assert_eq!(readiness_probe.timeout_seconds, Some(5));
+
+        // disabled
+        spark_cluster.spec.workers.config = Some(stackable_spark_crd::WorkerConfig {
+            probes: Some(ProbesConfig {
+                enabled: Some(false),
+                ..ProbesConfig::default()
+            }),
+            ..stackable_spark_crd::WorkerConfig::default()
+        });
+        let container = build_container(
+            &spark_cluster,
+            TestSparkCluster::WORKER_1_ROLE_GROUP,
+            &SparkNodeType::Worker,
+        );
+        assert_eq!(container.liveness_probe, None);
+        assert_eq!(container.readiness_probe, None);
+    }
+
+    #[test]
+    fn test_build_pod_decommission() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+        spark_cluster.spec.placement = Some(stackable_spark_crd::Placement::Scheduler);
+        spark_cluster.spec.runtime = Some(Runtime::Kubernetes);
+
+        let master_urls = stackable_spark_test_utils::create_master_urls();
+        let build_spec = |spark_cluster: &SparkCluster, node_type: &SparkNodeType| {
+            let role_group = match node_type {
+                SparkNodeType::Master => TestSparkCluster::MASTER_1_ROLE_GROUP,
+                _ => TestSparkCluster::WORKER_1_ROLE_GROUP,
+            };
+            build_scheduled_pod(spark_cluster, 0, role_group, node_type, &master_urls)
+                .unwrap()
+                .spec
+                .unwrap()
+        };
+
+        // Spark 3.0 does not support the decommission
+        let spec = build_spec(&spark_cluster, &SparkNodeType::Worker);
+        assert_eq!(spec.containers[0].lifecycle, None);
+        assert_eq!(spec.termination_grace_period_seconds, None);
+
+        // Spark 3.1 workers decommission in a preStop hook
+        spark_cluster.spec.version = SparkVersion::v3_1_1;
+        let spec = build_spec(&spark_cluster, &SparkNodeType::Worker);
+        let command = spec.containers[0]
+            .lifecycle
+            .as_ref()
+            .and_then(|lifecycle| lifecycle.pre_stop.as_ref())
+            .and_then(|pre_stop| pre_stop.exec.as_ref())
+            .and_then(|exec| exec.command.clone())
+            .unwrap();
+        assert_eq!(command.last(), Some(&DECOMMISSION_COMMAND.to_string()));
+        assert_eq!(spec.termination_grace_period_seconds, Some(300));
+
+        // masters are not decommissioned
+        let spec = build_spec(&spark_cluster, &SparkNodeType::Master);
+        assert_eq!(spec.containers[0].lifecycle, None);
+        assert_eq!(spec.termination_grace_period_seconds, None);
+
+        // the stackable agent runs no lifecycle hooks
+        spark_cluster.spec.runtime = Some(Runtime::StackableAgent);
+        let spec = build_spec(&spark_cluster, &SparkNodeType::Worker);
+        assert_eq!(spec.containers[0].lifecycle, None);
+    }
+
     #[test]
     fn test_filter_pods_for_type() {
         let pods = stackable_spark_test_utils::create_master_pods();
         assert_eq!(pods.len(), 3);
     }
+
+    #[test]
+    fn test_filter_pods_for_role_group() {
+        let pods = stackable_spark_test_utils::create_master_pods();
+        let filtered = filter_pods_for_role_group(
+            &pods,
+            &SparkNodeType::Master,
+            TestSparkCluster::MASTER_1_ROLE_GROUP,
+        );
+        assert_eq!(filtered.len(), 1);
+        assert!(filter_pods_for_role_group(&pods, &SparkNodeType::Worker, "master_1").is_empty());
+    }
+
+    #[test]
+    fn test_is_pod_running_and_ready() {
+        let mut pods = stackable_spark_test_utils::create_master_pods();
+        let pod = pods.get_mut(0).unwrap();
+        assert!(!is_pod_running_and_ready(pod));
+
+        stackable_spark_test_utils::set_pod_running_and_ready(pod);
+        assert!(is_pod_running_and_ready(pod));
+    }
+
+    #[test]
+    fn test_is_pod_crash_looping() {
+        let mut pods = stackable_spark_test_utils::create_master_pods();
+        let pod = pods.get_mut(0).unwrap();
+        assert!(!is_pod_crash_looping(pod));
+
+        stackable_spark_test_utils::set_pod_crash_looping(pod);
+        assert!(is_pod_crash_looping(pod));
+    }
 }
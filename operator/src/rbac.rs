@@ -0,0 +1,273 @@
+//! This module contains the RBAC resources of the operator and the Spark pods:
+//! - The ServiceAccount every SparkCluster runs its pods under. The Spark daemons do not access
+//!   the Kubernetes API, so no role is bound and no token is mounted.
+//! - The ClusterRole the operator itself requires (see `generate_rbac`). The rules are derived
+//!   from the resources the controllers watch, read and write.
+use crate::error::Error;
+use crate::pod_utils::APP_NAME;
+use k8s_openapi::api::core::v1::ServiceAccount;
+use k8s_openapi::api::rbac::v1::{ClusterRole, ClusterRoleBinding, PolicyRule, RoleRef, Subject};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::Resource;
+use stackable_operator::labels::{APP_INSTANCE_LABEL, APP_NAME_LABEL};
+use stackable_operator::metadata;
+use stackable_spark_crd::SparkCluster;
+use std::collections::BTreeMap;
+
+/// Name of the ClusterRole, ClusterRoleBinding and ServiceAccount of the operator
+pub const OPERATOR_NAME: &str = "spark-operator";
+
+const CORE_API_GROUP: &str = "";
+const SPARK_API_GROUP: &str = "spark.stackable.tech";
+const COMMAND_API_GROUP: &str = "command.spark.stackable.tech";
+
+/// Plural names of the command custom resources
+const COMMANDS: &[&str] = &[
+    "restarts", "starts", "stops", "scales", "pauses", "resumes", "cancels",
+];
+
+/// Returns the name of the ServiceAccount the pods of a cluster run under
+///
+/// # Arguments
+/// * `cluster_name` - The name of the cluster
+///
+pub fn create_service_account_name(cluster_name: &str) -> String {
+    format!("{}-{}", cluster_name, APP_NAME)
+}
+
+/// Build the ServiceAccount of the cluster pods. It is owned by the cluster and therefore
+/// deleted together with it.
+///
+/// # Arguments
+/// * `cluster` - Spark cluster custom resource
+///
+pub fn build_service_account(cluster: &SparkCluster) -> Result<ServiceAccount, Error> {
+    let mut labels = BTreeMap::new();
+    labels.insert(APP_NAME_LABEL.to_string(), APP_NAME.to_string());
+    labels.insert(APP_INSTANCE_LABEL.to_string(), cluster.name());
+
+    Ok(ServiceAccount {
+        metadata: metadata::build_metadata(
+            create_service_account_name(&cluster.name()),
+            Some(labels),
+            cluster,
+            true,
+        )?,
+        automount_service_account_token: Some(false),
+        ..ServiceAccount::default()
+    })
+}
+
+/// Build the ServiceAccount the operator runs under.
+///
+/// # Arguments
+/// * `namespace` - The namespace the operator is deployed to
+///
+pub fn build_operator_service_account(namespace: &str) -> ServiceAccount {
+    ServiceAccount {
+        metadata: ObjectMeta {
+            name: Some(OPERATOR_NAME.to_string()),
+            namespace: Some(namespace.to_string()),
+            ..ObjectMeta::default()
+        },
+        ..ServiceAccount::default()
+    }
+}
+
+/// Build the ClusterRole with the minimal permissions the operator requires.
+pub fn build_operator_cluster_role() -> ClusterRole {
+    let spark_resources = &[
+        "sparkclusters",
+        "sparkapplications",
+        "sparkscheduledapplications",
+    ];
+
+    ClusterRole {
+        metadata: ObjectMeta {
+            name: Some(OPERATOR_NAME.to_string()),
+            ..ObjectMeta::default()
+        },
+        rules: Some(vec![
+            // cluster and application pods (Controller::owns and submitter pods)
+            policy_rule(
+                CORE_API_GROUP,
+                &["pods"],
+                &["create", "delete", "get", "list", "watch"],
+            ),
+            // role group config (written with get / create / update, orphans are deleted)
+            policy_rule(
+                CORE_API_GROUP,
+                &["configmaps"],
+                &["create", "delete", "get", "list", "update", "watch"],
+            ),
+            // ServiceAccounts of the cluster pods
+            policy_rule(
+                CORE_API_GROUP,
+                &["serviceaccounts"],
+                &["create", "get", "list", "watch"],
+            ),
+            // authentication secret of the submitter pods
+            policy_rule(CORE_API_GROUP, &["secrets"], &["create", "get"]),
+            // PVC retention policy on cluster deletion
+            policy_rule(
+                CORE_API_GROUP,
+                &["persistentvolumeclaims"],
+                &["delete", "list"],
+            ),
+            // Node placement (nodes that match the role group selectors)
+            policy_rule(CORE_API_GROUP, &["nodes"], &["list"]),
+            policy_rule(CORE_API_GROUP, &["events"], &["create"]),
+            // finalizers, applications created by scheduled applications
+            policy_rule(
+                SPARK_API_GROUP,
+                spark_resources,
+                &["create", "delete", "get", "list", "patch", "watch"],
+            ),
+            policy_rule(
+                SPARK_API_GROUP,
+                &subresources(spark_resources, "status"),
+                &["patch"],
+            ),
+            // owner references with blockOwnerDeletion on the pods, ConfigMaps and Secrets of
+            // clusters and applications and on the applications of scheduled applications
+            // (OwnerReferencesPermissionEnforcement admission plugin)
+            policy_rule(
+                SPARK_API_GROUP,
+                &subresources(spark_resources, "finalizers"),
+                &["update"],
+            ),
+            policy_rule(
+                COMMAND_API_GROUP,
+                COMMANDS,
+                &["delete", "get", "list", "patch", "watch"],
+            ),
+            policy_rule(
+                COMMAND_API_GROUP,
+                &subresources(COMMANDS, "status"),
+                &["patch"],
+            ),
+        ]),
+        ..ClusterRole::default()
+    }
+}
+
+/// Build the ClusterRoleBinding of the operator ClusterRole to the operator ServiceAccount.
+///
+/// # Arguments
+/// * `namespace` - The namespace the operator is deployed to
+///
+pub fn build_operator_cluster_role_binding(namespace: &str) -> ClusterRoleBinding {
+    ClusterRoleBinding {
+        metadata: ObjectMeta {
+            name: Some(OPERATOR_NAME.to_string()),
+            ..ObjectMeta::default()
+        },
+        role_ref: RoleRef {
+            api_group: "rbac.authorization.k8s.io".to_string(),
+            kind: "ClusterRole".to_string(),
+            name: OPERATOR_NAME.to_string(),
+        },
+        subjects: Some(vec![Subject {
+            kind: "ServiceAccount".to_string(),
+            name: OPERATOR_NAME.to_string(),
+            namespace: Some(namespace.to_string()),
+            ..Subject::default()
+        }]),
+    }
+}
+
+fn policy_rule<S: AsRef<str>>(api_group: &str, resources: &[S], verbs: &[&str]) -> PolicyRule {
+    PolicyRule {
+        api_groups: Some(vec![api_group.to_string()]),
+        resources: Some(
+            resources
+                .iter()
+                .map(|resource| resource.as_ref().to_string())
+                .collect(),
+        ),
+        verbs: verbs.iter().map(|verb| verb.to_string()).collect(),
+        ..PolicyRule::default()
+    }
+}
+
+fn subresources(resources: &[&str], subresource: &str) -> Vec<String> {
+    resources
+        .iter()
+        .map(|resource| format!("{}/{}", resource, subresource))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_verbs(cluster_role: &ClusterRole, api_group: &str, resource: &str) -> Vec<String> {
+        cluster_role
+            .rules
+            .iter()
+            .flatten()
+            .filter(|rule| rule.api_groups == Some(vec![api_group.to_string()]))
+            .filter(|rule| {
+                rule.resources
+                    .iter()
+                    .flatten()
+                    .any(|rule_resource| rule_resource == resource)
+            })
+            .flat_map(|rule| rule.verbs.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_build_service_account() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.uid = Some("12345".to_string());
+
+        let service_account = build_service_account(&spark_cluster).unwrap();
+        assert_eq!(
+            service_account.metadata.name,
+            Some(format!("{}-spark", spark_cluster.name()))
+        );
+        assert_eq!(service_account.automount_service_account_token, Some(false));
+        assert!(service_account.metadata.owner_references.is_some());
+    }
+
+    #[test]
+    fn test_build_operator_cluster_role() {
+        let cluster_role = build_operator_cluster_role();
+
+        assert!(find_verbs(&cluster_role, CORE_API_GROUP, "events").contains(&"create".into()));
+        assert!(
+            find_verbs(&cluster_role, CORE_API_GROUP, "serviceaccounts").contains(&"create".into())
+        );
+        assert_eq!(
+            find_verbs(&cluster_role, CORE_API_GROUP, "persistentvolumeclaims"),
+            vec!["delete".to_string(), "list".to_string()]
+        );
+        assert!(find_verbs(&cluster_role, CORE_API_GROUP, "configmaps").contains(&"update".into()));
+        assert!(
+            find_verbs(&cluster_role, SPARK_API_GROUP, "sparkclusters/status")
+                .contains(&"patch".into())
+        );
+        for resource in &[
+            "sparkclusters/finalizers",
+            "sparkapplications/finalizers",
+            "sparkscheduledapplications/finalizers",
+        ] {
+            assert_eq!(
+                find_verbs(&cluster_role, SPARK_API_GROUP, resource),
+                vec!["update".to_string()]
+            );
+        }
+        for command in COMMANDS {
+            let verbs = find_verbs(&cluster_role, COMMAND_API_GROUP, command);
+            assert!(verbs.contains(&"watch".into()));
+            assert!(verbs.contains(&"delete".into()));
+        }
+        // no wildcards
+        assert!(cluster_role
+            .rules
+            .unwrap()
+            .iter()
+            .all(|rule| !rule.verbs.contains(&"*".to_string())));
+    }
+}
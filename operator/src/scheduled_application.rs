@@ -0,0 +1,643 @@
+//! This module contains the controller for SparkScheduledApplications. A SparkApplication is
+//! created from the template of the resource whenever the cron schedule is due. Active runs
+//! are handled according to the concurrency policy and finished runs are deleted according
+//! to the history limits. All existing runs are recorded in the status.
+use crate::error::Error;
+use crate::events;
+use crate::events::{EventReason, EventType};
+use crate::status;
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use cron::Schedule;
+use kube::api::ListParams;
+use kube::{Api, Resource};
+use serde_json::json;
+use stackable_operator::client::Client;
+use stackable_operator::controller::{Controller, ControllerStrategy, ReconciliationState};
+use stackable_operator::metadata;
+use stackable_operator::reconcile::{
+    ReconcileFunctionAction, ReconcileResult, ReconciliationContext,
+};
+use stackable_spark_crd::{
+    ApplicationState, ConcurrencyPolicy, ScheduledRun, SparkApplication, SparkScheduledApplication,
+};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{info, warn};
+
+type SparkScheduledApplicationReconcileResult = ReconcileResult<Error>;
+
+/// Label that links a SparkApplication to the SparkScheduledApplication that created it
+const SCHEDULED_APPLICATION_LABEL: &str = "spark.stackable.tech/scheduledApplication";
+/// Annotation that holds the schedule time of a run
+const SCHEDULED_AT_ANNOTATION: &str = "spark.stackable.tech/scheduledAt";
+/// Minimum requeue interval while waiting for the next schedule
+const MIN_REQUEUE_INTERVAL: Duration = Duration::from_secs(1);
+
+struct SparkScheduledApplicationState {
+    context: ReconciliationContext<SparkScheduledApplication>,
+    schedule: Option<Schedule>,
+    /// All runs of this resource ordered by their schedule time
+    applications: Vec<SparkApplication>,
+}
+
+impl SparkScheduledApplicationState {
+    /// Publish an event for the SparkScheduledApplication
+    async fn publish_event(&self, event_type: EventType, reason: EventReason, message: &str) {
+        events::publish_event(
+            &self.context.client,
+            &self.context.resource,
+            event_type,
+            reason,
+            message,
+        )
+        .await;
+    }
+
+    /// Merge the given fields into the status.
+    async fn patch_status(&mut self, patch: serde_json::Value) -> Result<(), Error> {
+        self.context.resource =
+            status::patch_status(&self.context.client, &self.context.resource, patch).await?;
+        Ok(())
+    }
+
+    /// Parse the cron schedule. An invalid schedule is reported in the status and the
+    /// resource is not processed until the schedule is changed.
+    async fn parse_schedule(&mut self) -> SparkScheduledApplicationReconcileResult {
+        let message = match parse_schedule(&self.context.resource.spec.schedule) {
+            Ok(schedule) => {
+                self.schedule = Some(schedule);
+                None
+            }
+            Err(err) => {
+                warn!("{}", err);
+                Some(err.to_string())
+            }
+        };
+
+        let current = self
+            .context
+            .resource
+            .status
+            .as_ref()
+            .and_then(|status| status.message.clone());
+        if current != message {
+            self.patch_status(json!({ "message": message })).await?;
+        }
+
+        if self.schedule.is_none() {
+            return Ok(ReconcileFunctionAction::Done);
+        }
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Retrieve all runs created by this resource.
+    async fn get_applications(&mut self) -> SparkScheduledApplicationReconcileResult {
+        let applications_api: Api<SparkApplication> = self
+            .context
+            .client
+            .get_api(self.context.resource.namespace().as_deref());
+        let selector = format!(
+            "{}={}",
+            SCHEDULED_APPLICATION_LABEL,
+            self.context.resource.name()
+        );
+
+        let mut applications = applications_api
+            .list(&ListParams::default().labels(&selector))
+            .await?
+            .items;
+        applications.sort_by_key(get_scheduled_at);
+        self.applications = applications;
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Delete finished and failed runs that exceed the history limits.
+    async fn delete_expired_runs(&mut self) -> SparkScheduledApplicationReconcileResult {
+        let expired = find_expired_runs(
+            &self.applications,
+            self.context.resource.get_successful_runs_history_limit(),
+            self.context.resource.get_failed_runs_history_limit(),
+        );
+
+        for application in &expired {
+            info!(
+                "Deleting run [{}] which exceeds the history limit",
+                application.name()
+            );
+            self.context.client.delete(application).await?;
+        }
+
+        let expired_names = expired
+            .iter()
+            .map(|application| application.name())
+            .collect::<Vec<_>>();
+        self.applications
+            .retain(|application| !expired_names.contains(&application.name()));
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Create a run if the schedule was due since the last run. Active runs are kept, the
+    /// new run is skipped or the active runs are deleted depending on the concurrency policy.
+    async fn schedule_run(&mut self) -> SparkScheduledApplicationReconcileResult {
+        if self.context.resource.is_suspended() {
+            return Ok(ReconcileFunctionAction::Continue);
+        }
+
+        let schedule = match &self.schedule {
+            Some(schedule) => schedule,
+            None => return Ok(ReconcileFunctionAction::Done),
+        };
+
+        let resource = &self.context.resource;
+        let now = Utc::now();
+        let mut earliest = resource
+            .status
+            .as_ref()
+            .and_then(|status| status.last_schedule_time.as_deref())
+            .and_then(parse_time)
+            .or_else(|| {
+                resource
+                    .meta()
+                    .creation_timestamp
+                    .as_ref()
+                    .map(|time| time.0)
+            })
+            .unwrap_or(now);
+        // runs that are due for longer than the starting deadline are skipped
+        if let Some(deadline) = resource.spec.starting_deadline_seconds {
+            earliest = earliest.max(now - chrono::Duration::seconds(deadline as i64));
+        }
+
+        let scheduled_at = match get_missed_schedule(schedule, &earliest, &now) {
+            Some(scheduled_at) => scheduled_at,
+            None => return Ok(ReconcileFunctionAction::Continue),
+        };
+
+        let active = self
+            .applications
+            .iter()
+            .filter(|application| !application.get_state().is_terminal())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        match self.context.resource.get_concurrency_policy() {
+            ConcurrencyPolicy::Forbid if !active.is_empty() => {
+                let message = format!(
+                    "Skipped run scheduled at [{}] because [{}] is still active",
+                    format_time(&scheduled_at),
+                    active[0].name()
+                );
+                info!("{}", message);
+                self.publish_event(EventType::Normal, EventReason::RunSkipped, &message)
+                    .await;
+                self.patch_status(json!({ "lastScheduleTime": format_time(&scheduled_at) }))
+                    .await?;
+                return Ok(ReconcileFunctionAction::Continue);
+            }
+            ConcurrencyPolicy::Replace => {
+                for application in &active {
+                    self.context.client.delete(application).await?;
+                    self.publish_event(
+                        EventType::Normal,
+                        EventReason::RunReplaced,
+                        &format!("Deleted active run [{}]", application.name()),
+                    )
+                    .await;
+                }
+                self.applications
+                    .retain(|application| application.get_state().is_terminal());
+            }
+            _ => {}
+        }
+
+        let application = build_run(&self.context.resource, &scheduled_at)?;
+        let application = match self.context.client.create(&application).await {
+            Ok(application) => application,
+            // the run was created but the status could not be updated
+            Err(err) => match self
+                .applications
+                .iter()
+                .find(|existing| existing.name() == application.name())
+            {
+                Some(existing) => existing.clone(),
+                None => return Err(err.into()),
+            },
+        };
+
+        info!(
+            "Created run [{}] scheduled at [{}]",
+            application.name(),
+            format_time(&scheduled_at)
+        );
+        self.publish_event(
+            EventType::Normal,
+            EventReason::RunScheduled,
+            &format!("Created run [{}]", application.name()),
+        )
+        .await;
+
+        if !self
+            .applications
+            .iter()
+            .any(|existing| existing.name() == application.name())
+        {
+            self.applications.push(application);
+        }
+        self.patch_status(json!({ "lastScheduleTime": format_time(&scheduled_at) }))
+            .await?;
+
+        Ok(ReconcileFunctionAction::Continue)
+    }
+
+    /// Record all runs in the status and requeue until the next schedule.
+    async fn update_status(&mut self) -> SparkScheduledApplicationReconcileResult {
+        let runs = build_runs(&self.applications);
+        let status = self.context.resource.status.clone().unwrap_or_default();
+
+        let last_successful_time = runs
+            .iter()
+            .filter(|run| run.state == ApplicationState::Finished)
+            .filter_map(|run| run.finished_at.clone())
+            .chain(status.last_successful_time.clone())
+            .max();
+
+        if status.runs != runs || status.last_successful_time != last_successful_time {
+            self.patch_status(json!({
+                "runs": runs,
+                "lastSuccessfulTime": last_successful_time
+            }))
+            .await?;
+        }
+
+        if self.context.resource.is_suspended() {
+            return Ok(ReconcileFunctionAction::Done);
+        }
+
+        let next = self
+            .schedule
+            .as_ref()
+            .and_then(|schedule| schedule.upcoming(Utc).next());
+
+        match next.and_then(|next| (next - Utc::now()).to_std().ok()) {
+            Some(duration) => Ok(ReconcileFunctionAction::Requeue(
+                duration.max(MIN_REQUEUE_INTERVAL),
+            )),
+            None => Ok(ReconcileFunctionAction::Done),
+        }
+    }
+}
+
+impl ReconciliationState for SparkScheduledApplicationState {
+    type Error = Error;
+
+    fn reconcile(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<ReconcileFunctionAction, Self::Error>> + Send + '_>>
+    {
+        Box::pin(async move {
+            self.parse_schedule()
+                .await?
+                .then(self.get_applications())
+                .await?
+                .then(self.delete_expired_runs())
+                .await?
+                .then(self.schedule_run())
+                .await?
+                .then(self.update_status())
+                .await
+        })
+    }
+}
+
+/// Parse a cron expression. Standard five field expressions (minute hour day-of-month month
+/// day-of-week) are extended with the leading seconds field the cron crate requires.
+///
+/// # Arguments
+/// * `schedule` - The cron expression of the resource
+///
+fn parse_schedule(schedule: &str) -> Result<Schedule, Error> {
+    let expression = if schedule.split_whitespace().count() == 5 {
+        format!("0 {}", schedule)
+    } else {
+        schedule.to_string()
+    };
+
+    Schedule::from_str(&expression).map_err(|source| Error::InvalidSchedule {
+        schedule: schedule.to_string(),
+        source,
+    })
+}
+
+/// Return the latest schedule time after `earliest` that is not later than `now`. Earlier
+/// schedule times that were missed are not caught up.
+///
+/// # Arguments
+/// * `schedule` - The parsed cron schedule
+/// * `earliest` - The last schedule time (exclusive)
+/// * `now` - The current time (inclusive)
+///
+fn get_missed_schedule(
+    schedule: &Schedule,
+    earliest: &DateTime<Utc>,
+    now: &DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    schedule
+        .after(earliest)
+        .take_while(|time| time <= now)
+        .last()
+}
+
+/// Build the name of a run. The name is derived from the schedule time (in minutes) to
+/// prevent duplicate runs for the same schedule.
+///
+/// # Arguments
+/// * `name` - The name of the SparkScheduledApplication
+/// * `scheduled_at` - The schedule time of the run
+///
+fn build_run_name(name: &str, scheduled_at: &DateTime<Utc>) -> String {
+    format!("{}-{}", name, scheduled_at.timestamp() / 60)
+}
+
+/// Build the SparkApplication for a run from the template of the resource.
+///
+/// # Arguments
+/// * `scheduled` - The SparkScheduledApplication
+/// * `scheduled_at` - The schedule time of the run
+///
+fn build_run(
+    scheduled: &SparkScheduledApplication,
+    scheduled_at: &DateTime<Utc>,
+) -> Result<SparkApplication, Error> {
+    let name = build_run_name(&scheduled.name(), scheduled_at);
+
+    let mut labels = BTreeMap::new();
+    labels.insert(SCHEDULED_APPLICATION_LABEL.to_string(), scheduled.name());
+
+    let mut annotations = BTreeMap::new();
+    annotations.insert(
+        SCHEDULED_AT_ANNOTATION.to_string(),
+        format_time(scheduled_at),
+    );
+
+    let mut application = SparkApplication::new(&name, scheduled.spec.template.clone());
+    application.metadata = metadata::build_metadata(name, Some(labels), scheduled, true)?;
+    application.metadata.annotations = Some(annotations);
+
+    Ok(application)
+}
+
+/// Return the schedule time of a run from its annotation or its creation timestamp.
+///
+/// # Arguments
+/// * `application` - The SparkApplication of the run
+///
+fn get_scheduled_at(application: &SparkApplication) -> Option<DateTime<Utc>> {
+    application
+        .meta()
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(SCHEDULED_AT_ANNOTATION))
+        .and_then(|scheduled_at| parse_time(scheduled_at))
+        .or_else(|| {
+            application
+                .meta()
+                .creation_timestamp
+                .as_ref()
+                .map(|time| time.0)
+        })
+}
+
+/// Return all finished and failed runs exceeding the respective history limit. The oldest
+/// runs are returned first.
+///
+/// # Arguments
+/// * `applications` - All runs ordered by their schedule time
+/// * `successful_limit` - Number of finished runs to keep
+/// * `failed_limit` - Number of failed runs to keep
+///
+fn find_expired_runs(
+    applications: &[SparkApplication],
+    successful_limit: usize,
+    failed_limit: usize,
+) -> Vec<SparkApplication> {
+    let mut expired = vec![];
+
+    for (state, limit) in &[
+        (ApplicationState::Finished, successful_limit),
+        (ApplicationState::Failed, failed_limit),
+    ] {
+        let runs = applications
+            .iter()
+            .filter(|application| &application.get_state() == state)
+            .collect::<Vec<_>>();
+        let excess = runs.len().saturating_sub(*limit);
+        expired.extend(runs.into_iter().take(excess).cloned());
+    }
+
+    expired.sort_by_key(get_scheduled_at);
+    expired
+}
+
+/// Build the status entries for all runs.
+///
+/// # Arguments
+/// * `applications` - All runs ordered by their schedule time
+///
+fn build_runs(applications: &[SparkApplication]) -> Vec<ScheduledRun> {
+    applications
+        .iter()
+        .map(|application| ScheduledRun {
+            name: application.name(),
+            state: application.get_state(),
+            scheduled_at: get_scheduled_at(application).map(|time| format_time(&time)),
+            finished_at: application
+                .status
+                .as_ref()
+                .and_then(|status| status.finished_at.clone()),
+        })
+        .collect()
+}
+
+fn format_time(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+#[derive(Debug)]
+struct SparkScheduledApplicationStrategy {}
+
+impl SparkScheduledApplicationStrategy {
+    pub fn new() -> SparkScheduledApplicationStrategy {
+        SparkScheduledApplicationStrategy {}
+    }
+}
+
+#[async_trait]
+impl ControllerStrategy for SparkScheduledApplicationStrategy {
+    type Item = SparkScheduledApplication;
+    type State = SparkScheduledApplicationState;
+    type Error = Error;
+
+    async fn init_reconcile_state(
+        &self,
+        context: ReconciliationContext<Self::Item>,
+    ) -> Result<Self::State, Self::Error> {
+        Ok(SparkScheduledApplicationState {
+            context,
+            schedule: None,
+            applications: vec![],
+        })
+    }
+}
+
+/// This creates an instance of a [`Controller`] which waits for incoming events and reconciles
+/// SparkScheduledApplications.
+///
+/// This is an async method and the returned future needs to be consumed to make progress.
+pub async fn create_scheduled_application_controller(client: Client) {
+    let scheduled_api: Api<SparkScheduledApplication> = client.get_all_api();
+    let applications_api: Api<SparkApplication> = client.get_all_api();
+
+    let controller = Controller::new(scheduled_api).owns(applications_api, ListParams::default());
+
+    let strategy = SparkScheduledApplicationStrategy::new();
+
+    controller
+        .run(client, strategy, Duration::from_secs(10))
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_spark_crd::SparkApplicationStatus;
+
+    fn build_scheduled_application(schedule: &str) -> SparkScheduledApplication {
+        let mut scheduled: SparkScheduledApplication = serde_json::from_value(json!({
+            "apiVersion": "spark.stackable.tech/v1",
+            "kind": "SparkScheduledApplication",
+            "metadata": { "name": "nightly-etl", "namespace": "default" },
+            "spec": {
+                "schedule": schedule,
+                "template": {
+                    "sparkCluster": "spark-cluster",
+                    "mainApplicationFile": "etl.jar"
+                }
+            }
+        }))
+        .unwrap();
+        scheduled.metadata.uid = Some("12345".to_string());
+        scheduled
+    }
+
+    fn build_finished_run(
+        scheduled: &SparkScheduledApplication,
+        scheduled_at: &str,
+        state: ApplicationState,
+    ) -> SparkApplication {
+        let mut application = build_run(scheduled, &parse_time(scheduled_at).unwrap()).unwrap();
+        application.status = Some(SparkApplicationStatus {
+            state: Some(state),
+            finished_at: Some(scheduled_at.to_string()),
+            ..SparkApplicationStatus::default()
+        });
+        application
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        assert!(parse_schedule("0 2 * * *").is_ok());
+        assert!(parse_schedule("30 0 2 * * *").is_ok());
+        assert!(parse_schedule("every night").is_err());
+    }
+
+    #[test]
+    fn test_get_missed_schedule() {
+        let schedule = parse_schedule("0 2 * * *").unwrap();
+        let earliest = parse_time("2021-06-01T00:00:00Z").unwrap();
+
+        // not due yet
+        let now = parse_time("2021-06-01T01:59:59Z").unwrap();
+        assert_eq!(get_missed_schedule(&schedule, &earliest, &now), None);
+
+        // only the latest missed schedule is returned
+        let now = parse_time("2021-06-03T03:00:00Z").unwrap();
+        assert_eq!(
+            get_missed_schedule(&schedule, &earliest, &now),
+            parse_time("2021-06-03T02:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_build_run() {
+        let scheduled = build_scheduled_application("0 2 * * *");
+        let scheduled_at = parse_time("2021-06-01T02:00:00Z").unwrap();
+
+        let application = build_run(&scheduled, &scheduled_at).unwrap();
+
+        assert_eq!(application.name(), "nightly-etl-27041880");
+        assert_eq!(application.spec, scheduled.spec.template);
+        assert_eq!(
+            application
+                .meta()
+                .labels
+                .as_ref()
+                .unwrap()
+                .get(SCHEDULED_APPLICATION_LABEL),
+            Some(&"nightly-etl".to_string())
+        );
+        assert_eq!(get_scheduled_at(&application), Some(scheduled_at));
+    }
+
+    #[test]
+    fn test_find_expired_runs() {
+        let scheduled = build_scheduled_application("0 2 * * *");
+        let applications = vec![
+            build_finished_run(&scheduled, "2021-06-01T02:00:00Z", ApplicationState::Failed),
+            build_finished_run(
+                &scheduled,
+                "2021-06-02T02:00:00Z",
+                ApplicationState::Finished,
+            ),
+            build_finished_run(&scheduled, "2021-06-03T02:00:00Z", ApplicationState::Failed),
+            build_finished_run(
+                &scheduled,
+                "2021-06-04T02:00:00Z",
+                ApplicationState::Finished,
+            ),
+            build_run(&scheduled, &parse_time("2021-06-05T02:00:00Z").unwrap()).unwrap(),
+        ];
+
+        let expired = find_expired_runs(&applications, 1, 1)
+            .iter()
+            .map(|application| application.name())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            expired,
+            vec![applications[0].name(), applications[1].name()]
+        );
+
+        // active runs are never expired
+        assert_eq!(find_expired_runs(&applications, 0, 0).len(), 4);
+
+        let runs = build_runs(&applications);
+        assert_eq!(runs.len(), 5);
+        assert_eq!(runs[4].state, ApplicationState::Pending);
+        assert_eq!(
+            runs[4].scheduled_at,
+            Some("2021-06-05T02:00:00Z".to_string())
+        );
+    }
+}
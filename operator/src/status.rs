@@ -0,0 +1,132 @@
+//! This module contains all status writes of the custom resources managed by this operator.
+//! Partial updates are sent as JSON merge patches, so fields that are not part of a patch
+//! (e.g. conditions written in between) are never overwritten and `null` removes a field.
+//! All updates carry the resourceVersion as precondition. On conflicts the latest resource
+//! is fetched and the patch is recomputed from it (or reapplied if it does not depend on
+//! the current status).
+use kube::api::{Patch, PatchParams};
+use kube::{Api, Resource};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use stackable_operator::client::Client;
+use stackable_operator::error::OperatorResult;
+use std::fmt::Debug;
+use tracing::debug;
+
+/// Number of times a conflicting status update is recomputed before giving up
+const MAX_CONFLICT_RETRIES: usize = 5;
+
+/// Merge patch the given fields into the status of the resource. Fields that are set to
+/// `null` in the patch are removed from the status. The patch is written with the
+/// resourceVersion as precondition like all status updates and applied to the latest
+/// resource on conflicts (see `update_status`).
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `resource` - The resource whose status is patched
+/// * `patch` - The status fields to change
+///
+pub async fn patch_status<T>(client: &Client, resource: &T, patch: Value) -> OperatorResult<T>
+where
+    T: Resource + Clone + Debug + DeserializeOwned,
+    <T as Resource>::DynamicType: Default,
+{
+    update_status(client, resource, |_| Some(patch.clone())).await
+}
+
+/// Compute a status patch from the current resource and write it with the resourceVersion
+/// of that resource as precondition. If the resource changed in between, the latest
+/// resource is fetched and the patch is computed again. Nothing is written if `build_patch`
+/// returns None.
+///
+/// # Arguments
+/// * `client` - Kubernetes client
+/// * `resource` - The last known state of the resource
+/// * `build_patch` - Computes the status fields to change from the current resource
+///
+pub async fn update_status<T, F>(client: &Client, resource: &T, build_patch: F) -> OperatorResult<T>
+where
+    T: Resource + Clone + Debug + DeserializeOwned,
+    <T as Resource>::DynamicType: Default,
+    F: Fn(&T) -> Option<Value>,
+{
+    let mut current = resource.clone();
+    let mut retries = 0;
+
+    loop {
+        let status_patch = match build_patch(&current) {
+            Some(status_patch) => status_patch,
+            None => return Ok(current),
+        };
+
+        let api: Api<T> = client.get_api(current.namespace().as_deref());
+        let result = api
+            .patch_status(
+                &current.name(),
+                &PatchParams::default(),
+                &Patch::Merge(build_precondition_patch(&current, status_patch)),
+            )
+            .await;
+
+        match result {
+            Ok(updated) => return Ok(updated),
+            Err(kube::Error::Api(response))
+                if response.code == 409 && retries < MAX_CONFLICT_RETRIES =>
+            {
+                retries += 1;
+                debug!(
+                    "Status of [{}] changed in between, retrying with the latest version ({}/{})",
+                    current.name(),
+                    retries,
+                    MAX_CONFLICT_RETRIES
+                );
+                current = client
+                    .get::<T>(&current.name(), current.namespace().as_deref())
+                    .await?;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Wrap a status patch into a merge patch with the resourceVersion of the resource as
+/// precondition. The API server rejects the patch with a conflict if the resource was
+/// changed in the meantime.
+///
+/// # Arguments
+/// * `resource` - The resource the patch was computed from
+/// * `status_patch` - The status fields to change
+///
+pub fn build_precondition_patch<T>(resource: &T, status_patch: Value) -> Value
+where
+    T: Resource,
+{
+    json!({
+        "metadata": {
+            "resourceVersion": resource.meta().resource_version
+        },
+        "status": status_patch
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stackable_spark_crd::SparkCluster;
+
+    #[test]
+    fn test_build_precondition_patch() {
+        let mut spark_cluster: SparkCluster = stackable_spark_test_utils::setup_test_cluster();
+        spark_cluster.metadata.resource_version = Some("42".to_string());
+
+        let patch = build_precondition_patch(&spark_cluster, json!({ "currentCommand": null }));
+
+        assert_eq!(
+            patch,
+            json!({
+                "metadata": { "resourceVersion": "42" },
+                "status": { "currentCommand": null }
+            })
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use stackable_spark_crd::{Restart, SparkCluster, Start, Stop};
+use stackable_spark_crd::{
+    Cancel, Pause, Restart, Resume, Scale, SparkApplication, SparkCluster,
+    SparkScheduledApplication, Start, Stop,
+};
 use std::error::Error;
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -6,6 +9,14 @@ fn main() -> Result<(), Box<dyn Error>> {
         "SparkCluster CRD:\n{}\n",
         serde_yaml::to_string(&SparkCluster::crd())?
     );
+    println!(
+        "SparkApplication CRD:\n{}\n",
+        serde_yaml::to_string(&SparkApplication::crd())?
+    );
+    println!(
+        "SparkScheduledApplication CRD:\n{}\n",
+        serde_yaml::to_string(&SparkScheduledApplication::crd())?
+    );
     println!(
         "Restart Command CRD:\n{}\n",
         serde_yaml::to_string(&Restart::crd())?
@@ -18,5 +29,21 @@ fn main() -> Result<(), Box<dyn Error>> {
         "Stop Command CRD:\n{}\n",
         serde_yaml::to_string(&Stop::crd())?
     );
+    println!(
+        "Scale Command CRD:\n{}\n",
+        serde_yaml::to_string(&Scale::crd())?
+    );
+    println!(
+        "Pause Command CRD:\n{}\n",
+        serde_yaml::to_string(&Pause::crd())?
+    );
+    println!(
+        "Resume Command CRD:\n{}\n",
+        serde_yaml::to_string(&Resume::crd())?
+    );
+    println!(
+        "Cancel Command CRD:\n{}\n",
+        serde_yaml::to_string(&Cancel::crd())?
+    );
     Ok(())
 }
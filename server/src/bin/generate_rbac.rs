@@ -0,0 +1,25 @@
+use stackable_spark_operator::rbac;
+use std::error::Error;
+
+/// Namespace of the operator ServiceAccount if not given as first argument
+const DEFAULT_NAMESPACE: &str = "default";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let namespace = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_NAMESPACE.to_string());
+
+    println!(
+        "{}",
+        serde_yaml::to_string(&rbac::build_operator_service_account(&namespace))?
+    );
+    println!(
+        "{}",
+        serde_yaml::to_string(&rbac::build_operator_cluster_role())?
+    );
+    println!(
+        "{}",
+        serde_yaml::to_string(&rbac::build_operator_cluster_role_binding(&namespace))?
+    );
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use stackable_operator::{client, error};
 use stackable_spark_crd::SparkCluster;
-use stackable_spark_crd::{Restart, Start, Stop};
+use stackable_spark_crd::{Cancel, Pause, Restart, Resume, Scale, Start, Stop};
 use tracing::info;
 
 #[tokio::main]
@@ -11,6 +11,8 @@ async fn main() -> Result<(), error::Error> {
     let client = client::create_client(Some("spark.stackable.tech".to_string())).await?;
     tokio::join!(
         stackable_spark_operator::create_controller(client.clone()),
+        stackable_spark_operator::create_application_controller(client.clone()),
+        stackable_spark_operator::create_scheduled_application_controller(client.clone()),
         stackable_operator::command_controller::create_command_controller::<Restart, SparkCluster>(
             client.clone()
         ),
@@ -19,6 +21,18 @@ async fn main() -> Result<(), error::Error> {
         ),
         stackable_operator::command_controller::create_command_controller::<Stop, SparkCluster>(
             client.clone()
+        ),
+        stackable_operator::command_controller::create_command_controller::<Scale, SparkCluster>(
+            client.clone()
+        ),
+        stackable_operator::command_controller::create_command_controller::<Pause, SparkCluster>(
+            client.clone()
+        ),
+        stackable_operator::command_controller::create_command_controller::<Resume, SparkCluster>(
+            client.clone()
+        ),
+        stackable_operator::command_controller::create_command_controller::<Cancel, SparkCluster>(
+            client.clone()
         )
     );
 
@@ -1,5 +1,8 @@
 use crate::cluster::{Data, Load, TestSparkCluster};
-use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::api::core::v1::{
+    ContainerState, ContainerStateWaiting, ContainerStatus, Pod, PodCondition, PodStatus,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
 use serde::de::DeserializeOwned;
 use stackable_spark_crd::{SparkCluster, SparkNodeType};
 use stackable_spark_operator::pod_utils::build_pod;
@@ -21,7 +24,7 @@ pub fn create_master_urls() -> Vec<String> {
     let master_1_url = format!(
         "{}:{}",
         TestSparkCluster::MASTER_1_NODE_NAME,
-        TestSparkCluster::MASTER_1_CONFIG_PORT,
+        TestSparkCluster::MASTER_1_PORT,
     );
 
     let master_2_url = format!(
@@ -72,3 +75,34 @@ pub fn create_master_pods() -> Vec<Pod> {
         .unwrap(),
     ]
 }
+
+pub fn set_pod_running_and_ready(pod: &mut Pod) {
+    pod.status = Some(PodStatus {
+        phase: Some("Running".to_string()),
+        conditions: Some(vec![PodCondition {
+            type_: "Ready".to_string(),
+            status: "True".to_string(),
+            last_transition_time: Some(Time(k8s_openapi::chrono::Utc::now())),
+            ..PodCondition::default()
+        }]),
+        ..PodStatus::default()
+    });
+}
+
+pub fn set_pod_crash_looping(pod: &mut Pod) {
+    pod.status = Some(PodStatus {
+        phase: Some("Running".to_string()),
+        container_statuses: Some(vec![ContainerStatus {
+            name: "spark".to_string(),
+            state: Some(ContainerState {
+                waiting: Some(ContainerStateWaiting {
+                    reason: Some("CrashLoopBackOff".to_string()),
+                    ..ContainerStateWaiting::default()
+                }),
+                ..ContainerState::default()
+            }),
+            ..ContainerStatus::default()
+        }]),
+        ..PodStatus::default()
+    });
+}